@@ -0,0 +1,81 @@
+//! A small Criterion-style harness built on std alone, so benchmarks run offline.
+//!
+//! `cargo bench -- <filter>` runs only the benchmarks whose name contains
+//! `<filter>`; `--quick` takes fewer samples.
+
+#![allow(dead_code)]
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use lc3_vm::asm::assemble;
+use lc3_vm::image::Image;
+
+/// Runs benchmarks selected on the command line and prints their timings
+pub struct Harness {
+    filter: Option<String>,
+    samples: usize,
+    sample_time: Duration,
+}
+
+impl Harness {
+    pub fn from_args() -> Self {
+        let mut harness = Harness {
+            filter: None,
+            samples: 20,
+            sample_time: Duration::from_millis(100),
+        };
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                "--bench" => {}
+                "--quick" => {
+                    harness.samples = 5;
+                    harness.sample_time = Duration::from_millis(20);
+                }
+                _ if arg.starts_with("--") => {}
+                _ => harness.filter = Some(arg),
+            }
+        }
+        harness
+    }
+
+    /// Times `routine`, which returns the number of guest instructions it executed
+    pub fn bench(&self, name: &str, mut routine: impl FnMut() -> u64) {
+        if self.filter.as_ref().is_some_and(|filter| !name.contains(filter.as_str())) {
+            return;
+        }
+
+        // Warm up and size each sample so it lasts about `sample_time`
+        let start = Instant::now();
+        let instructions = black_box(routine());
+        let once = start.elapsed().max(Duration::from_nanos(1));
+        let iterations = (self.sample_time.as_nanos() / once.as_nanos()).clamp(1, 1_000_000) as u32;
+
+        let mut per_iteration: Vec<Duration> = (0..self.samples)
+            .map(|_| {
+                let start = Instant::now();
+                for _ in 0..iterations {
+                    black_box(routine());
+                }
+                start.elapsed() / iterations
+            })
+            .collect();
+        per_iteration.sort();
+
+        let median = per_iteration[per_iteration.len() / 2];
+        let mips = instructions as f64 / median.as_secs_f64() / 1e6;
+        println!(
+            "{:<40} time: [{:>10.3?} {:>10.3?} {:>10.3?}]  thrpt: {:>8.2} MIPS",
+            name,
+            per_iteration[0],
+            median,
+            per_iteration[per_iteration.len() - 1],
+            mips
+        );
+    }
+}
+
+/// Assembles a workload, panicking on errors since the sources are fixed
+pub fn program(source: &str) -> Image {
+    assemble(source).unwrap_or_else(|err| panic!("workload does not assemble: {}", err)).image
+}
@@ -0,0 +1,170 @@
+//! Compares the execution engines on CPU-bound workloads and the bundled programs.
+
+mod common;
+
+use std::io;
+use std::path::Path;
+
+use common::{program, Harness};
+use lc3_vm::image::Image;
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{Engine, Register, LC3};
+
+/// Counts R1 down from 30000 forty times: a two-instruction inner loop
+const COUNTDOWN: &str = "
+        .ORIG x3000
+        LD R2, OUTER
+AGAIN   LD R1, INNER
+LOOP    ADD R1, R1, #-1
+        BRp LOOP
+        ADD R2, R2, #-1
+        BRp AGAIN
+        HALT
+OUTER   .FILL #40
+INNER   .FILL #30000
+        .END
+";
+
+/// Copies 4096 words from x4000 to x6000 eight times with LDR/STR
+const MEMCPY: &str = "
+        .ORIG x3000
+        LD R5, TIMES
+PASS    LD R1, SRC
+        LD R2, DST
+        LD R3, COUNT
+COPY    LDR R0, R1, #0
+        STR R0, R2, #0
+        ADD R1, R1, #1
+        ADD R2, R2, #1
+        ADD R3, R3, #-1
+        BRp COPY
+        ADD R5, R5, #-1
+        BRp PASS
+        HALT
+TIMES   .FILL #8
+SRC     .FILL x4000
+DST     .FILL x6000
+COUNT   .FILL #4096
+        .END
+";
+
+/// Fills 400 words with the low 12 bits of x = 5x + 13 and insertion-sorts them
+const SORT: &str = "
+        .ORIG x3000
+        LEA R3, DATA
+        LD R4, COUNT
+        LD R7, MASK
+        AND R1, R1, #0
+FILL    ADD R2, R1, R1
+        ADD R2, R2, R2
+        ADD R1, R1, R2
+        ADD R1, R1, #13
+        AND R2, R1, R7
+        STR R2, R3, #0
+        ADD R3, R3, #1
+        ADD R4, R4, #-1
+        BRp FILL
+
+        LEA R0, DATA        ; R0: start of the array
+        LD R4, COUNT
+        ADD R4, R4, #-1     ; R4: elements left to insert
+        ADD R5, R0, #1      ; R5: next element to insert
+OUTER   LDR R1, R5, #0      ; R1: the key
+        NOT R7, R1
+        ADD R7, R7, #1      ; R7: -key
+        ADD R6, R5, #-1     ; R6: element compared with the key
+INNER   LDR R2, R6, #0
+        ADD R3, R2, R7
+        BRnz PLACE
+        STR R2, R6, #1
+        ADD R6, R6, #-1
+        NOT R3, R0
+        ADD R3, R3, #1
+        ADD R3, R6, R3
+        BRzp INNER
+PLACE   STR R1, R6, #1
+        ADD R5, R5, #1
+        ADD R4, R4, #-1
+        BRp OUTER
+        HALT
+COUNT   .FILL #400
+MASK    .FILL x0FFF
+DATA    .BLKW #400
+        .END
+";
+
+/// Sieve of Eratosthenes below 16000, leaving the number of primes in R0
+const PRIMES: &str = "
+        .ORIG x3000
+        LEA R5, SIEVE       ; SIEVE[n] is non-zero once n is known to be composite
+        LD R6, LIMIT
+        AND R0, R0, #0
+        AND R1, R1, #0
+        ADD R1, R1, #2      ; R1: candidate
+NEXT    ADD R2, R1, R6
+        BRzp DONE
+        ADD R3, R5, R1
+        LDR R4, R3, #0
+        BRnp SKIP
+        ADD R0, R0, #1
+        ADD R2, R1, R1      ; R2: multiples of R1
+MARK    ADD R3, R2, R6
+        BRzp SKIP
+        ADD R3, R5, R2
+        STR R1, R3, #0
+        ADD R2, R2, R1
+        BRnzp MARK
+SKIP    ADD R1, R1, #1
+        BRnzp NEXT
+DONE    HALT
+LIMIT   .FILL #-16000
+SIEVE   .BLKW #16000
+        .END
+";
+
+/// Interactive programs stop at this many instructions if their input has not run out
+const PROGRAM_LIMIT: u64 = 5_000_000;
+
+/// Runs an image on a fresh machine until it halts, reaches `PROGRAM_LIMIT` or
+/// runs out of input, returning the machine
+fn run(image: &Image, input: &[u8], engine: Engine) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(input));
+    vm.config.engine = engine;
+    vm.config.max_instructions = Some(PROGRAM_LIMIT);
+    vm.load(image);
+    match vm.run() {
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {}
+        Err(err) => panic!("workload failed: {}", err),
+    }
+    vm
+}
+
+fn bundled(name: &str) -> Image {
+    Image::read_file(Path::new(env!("CARGO_MANIFEST_DIR")).join("programs").join(name)).expect("missing program")
+}
+
+fn main() {
+    let harness = Harness::from_args();
+
+    // Sanity-check the workloads once so a broken one cannot report a meaningless speed
+    assert_eq!(run(&program(PRIMES), b"", Engine::Interpreter).registers.get(Register::R0), 1862);
+    let sorted = run(&program(SORT), b"", Engine::Interpreter);
+    let data = &sorted.memory.get_ptr(0x3000 + program(SORT).words.len() as u16 - 400)[..400];
+    assert!(data.windows(2).all(|pair| pair[0] <= pair[1]), "SORT left the data unsorted");
+
+    let workloads: [(&str, Image, &[u8]); 6] = [
+        ("countdown", program(COUNTDOWN), b""),
+        ("memcpy", program(MEMCPY), b""),
+        ("sort", program(SORT), b""),
+        ("primes", program(PRIMES), b""),
+        ("2048", bundled("2048.obj"), b"nwasdwasdssaaddwwsdsadwa"),
+        ("rogue", bundled("rogue.obj"), b"ddddssssddsdsdsdssddddwwaass"),
+    ];
+
+    for (name, image, input) in &workloads {
+        for engine in [Engine::Interpreter, Engine::Cached, Engine::Translated] {
+            harness.bench(&format!("{}/{:?}", name, engine), || run(image, input, engine).instruction_count);
+        }
+    }
+}
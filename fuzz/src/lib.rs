@@ -0,0 +1,52 @@
+//! Executor harness shared by the `executor` fuzz target and the offline
+//! smoke test in `tests/fuzz_executor.rs`.
+//!
+//! An input is a length byte `k`, `k` bytes of scripted keyboard input, and
+//! then an LC-3 object image (big-endian origin followed by words). The image
+//! is loaded into a fresh VM, PC is pointed at the origin and at most
+//! `MAX_STEPS` instructions are executed. Guest faults and exhausted input
+//! simply end the run; panics and broken invariants are bugs.
+
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{CondFlag, Register, LC3};
+
+/// Upper bound on instructions executed per input
+pub const MAX_STEPS: usize = 10_000;
+
+/// Runs one fuzz input, panicking if an invariant is violated
+pub fn execute(data: &[u8]) {
+    let Some((&length, rest)) = data.split_first() else {
+        return;
+    };
+    let split = (length as usize).min(rest.len());
+    let (input, image) = rest.split_at(split);
+
+    let mut vm = LC3::with_console(Console::scripted(input));
+    if vm.read_image(image).is_err() {
+        return;
+    }
+    let origin = u16::from_be_bytes([image[0], image[1]]);
+    vm.registers.set(Register::PC, origin);
+    vm.running = true;
+
+    for _ in 0..MAX_STEPS {
+        if vm.step().is_err() {
+            break;
+        }
+        check_invariants(&vm);
+        if !vm.running {
+            break;
+        }
+    }
+}
+
+fn check_invariants(vm: &LC3) {
+    let cond = vm.registers.get_condition_flag();
+    assert!(
+        [CondFlag::POS, CondFlag::ZRO, CondFlag::NEG]
+            .iter()
+            .any(|&flag| cond == flag as u16),
+        "COND holds x{:04X}, not exactly one of N, Z, P",
+        cond
+    );
+}
@@ -0,0 +1,466 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::image::Image;
+use crate::vm::{Instruction, Operand, MEMORY_SIZE};
+
+/// Result of assembling a source file: the object image and its symbol table
+#[derive(Debug, Clone)]
+pub struct Assembly {
+    pub image: Image,
+    pub symbols: BTreeMap<String, u16>,
+}
+
+/// An error in assembly source, with the 1-based line it occurred on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+/// A token on a source line
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Str(String),
+}
+
+/// A statement with its address, left for the second pass
+struct Statement {
+    line: usize,
+    address: u16,
+    mnemonic: String,
+    operands: Vec<Token>,
+}
+
+/// Assembles LC-3 assembly source into an object image.
+///
+/// Supports every instruction, the trap aliases (`GETC`, `OUT`, `PUTS`, `IN`,
+/// `PUTSP`, `HALT`), `RET`, `NOP` and the `.ORIG`, `.FILL`, `.BLKW`,
+/// `.STRINGZ` and `.END` directives. PC-relative operands may be labels or
+/// literal offsets. Numbers are written `#12`, `12`, `x1F` or `b1010`.
+pub fn assemble(source: &str) -> Result<Assembly, AsmError> {
+    let mut symbols = BTreeMap::new();
+    let mut statements = Vec::new();
+    let mut origin = None;
+    let mut address: u32 = 0;
+
+    for (index, raw) in source.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| AsmError { line, message };
+        let mut tokens = tokenize(raw).map_err(error)?.into_iter().peekable();
+
+        if let Some(Token::Word(word)) = tokens.peek() {
+            if !is_mnemonic(word) {
+                let label = word.trim_end_matches(':').to_string();
+                if !is_label(&label) {
+                    return Err(error(format!("Invalid label: {}", word)));
+                }
+                if origin.is_none() {
+                    return Err(error("Label before .ORIG".to_string()));
+                }
+                if symbols.insert(label.clone(), address as u16).is_some() {
+                    return Err(error(format!("Duplicate label: {}", label)));
+                }
+                tokens.next();
+            }
+        }
+
+        let mnemonic = match tokens.next() {
+            None => continue,
+            Some(Token::Word(word)) => word.to_ascii_uppercase(),
+            Some(Token::Str(_)) => return Err(error("Expected an instruction or directive".to_string())),
+        };
+        let operands: Vec<Token> = tokens.collect();
+
+        if mnemonic == ".ORIG" {
+            if origin.is_some() {
+                return Err(error("Only one .ORIG block is supported".to_string()));
+            }
+            let start = word_operand(&operands, 0).and_then(|text| parse_word(&text)).map_err(error)?;
+            origin = Some(start);
+            address = start as u32;
+            continue;
+        }
+        if origin.is_none() {
+            return Err(error(format!("{} before .ORIG", mnemonic)));
+        }
+        if mnemonic == ".END" {
+            break;
+        }
+
+        let size = match mnemonic.as_str() {
+            ".BLKW" => {
+                let count = word_operand(&operands, 0).and_then(|text| parse_word(&text)).map_err(error)?;
+                count as u32
+            }
+            ".STRINGZ" => match operands.first() {
+                Some(Token::Str(text)) => text.len() as u32 + 1,
+                _ => return Err(error(".STRINGZ expects a string".to_string())),
+            },
+            _ => 1,
+        };
+
+        statements.push(Statement {
+            line,
+            address: address as u16,
+            mnemonic,
+            operands,
+        });
+        address += size;
+        if address > MEMORY_SIZE as u32 {
+            return Err(error("Program does not fit in memory".to_string()));
+        }
+    }
+
+    let origin = origin.ok_or(AsmError { line: 1, message: "Missing .ORIG".to_string() })?;
+    let mut words = Vec::with_capacity((address - origin as u32) as usize);
+    for statement in &statements {
+        encode(statement, &symbols, &mut words).map_err(|message| AsmError { line: statement.line, message })?;
+    }
+
+    Ok(Assembly {
+        image: Image { origin, words },
+        symbols,
+    })
+}
+
+/// Parses a number in LC-3 notation: `#-5`, `-5`, `x1F` or `b1010`
+pub fn parse_number(text: &str) -> Option<i32> {
+    let text = text.trim();
+    let (negative, digits, radix) = if let Some(hex) = text.strip_prefix(['x', 'X']) {
+        match hex.strip_prefix('-') {
+            Some(hex) => (true, hex, 16),
+            None => (false, hex, 16),
+        }
+    } else if let Some(bin) = text.strip_prefix(['b', 'B']) {
+        match bin.strip_prefix('-') {
+            Some(bin) => (true, bin, 2),
+            None => (false, bin, 2),
+        }
+    } else {
+        let decimal = text.strip_prefix('#').unwrap_or(text);
+        match decimal.strip_prefix('-') {
+            Some(decimal) => (true, decimal, 10),
+            None => (false, decimal, 10),
+        }
+    };
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let value = i32::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Parses a number in LC-3 notation that fits in a word, signed or unsigned
+pub fn parse_word(text: &str) -> Result<u16, String> {
+    match parse_number(text) {
+        Some(value) if (-32768..=65535).contains(&value) => Ok(value as u16),
+        Some(_) => Err(format!("Value out of range: {}", text.trim())),
+        None => Err(format!("Invalid value: {}", text.trim())),
+    }
+}
+
+/// Disassembles an image into source that assembles back to the same words.
+/// Each line is annotated with its address and encoding, and PC-relative
+/// instructions with the address they refer to. Words whose unused bits are
+/// not the ones the assembler would produce are written as `.FILL`.
+pub fn disassemble(image: &Image, symbols: &BTreeMap<String, u16>) -> String {
+    let labels: BTreeMap<u16, &str> = symbols.iter().map(|(label, &address)| (address, label.as_str())).collect();
+    let mut source = format!(".ORIG x{:04X}\n", image.origin);
+
+    for (offset, &word) in image.words.iter().enumerate() {
+        let address = image.origin.wrapping_add(offset as u16);
+        let instruction = Instruction::decode(word);
+        let text = if instruction.encode() == word {
+            instruction.to_string()
+        } else {
+            format!(".FILL x{:04X}", word)
+        };
+
+        let mut line = format!("{:<11} {:<22} ; x{:04X}  x{:04X}", labels.get(&address).unwrap_or(&""), text, address, word);
+        if let Some(target) = instruction.target(address) {
+            line += &format!("  -> x{:04X}", target);
+            if let Some(label) = labels.get(&target) {
+                line += &format!(" {}", label);
+            }
+        }
+        source += &line;
+        source.push('\n');
+    }
+
+    source += ".END\n";
+    source
+}
+
+fn encode(statement: &Statement, symbols: &BTreeMap<String, u16>, words: &mut Vec<u16>) -> Result<(), String> {
+    let operands = &statement.operands;
+    let expect = |count: usize| {
+        if operands.len() == count {
+            Ok(())
+        } else {
+            Err(format!("{} expects {} operand(s), found {}", statement.mnemonic, count, operands.len()))
+        }
+    };
+    let reg = |index: usize| register(&word_operand(operands, index)?);
+    let imm = |index: usize, bits: u32| immediate(&word_operand(operands, index)?, bits);
+    let offset = |index: usize, bits: u32| pc_offset(&word_operand(operands, index)?, bits, statement.address, symbols);
+
+    let instruction = match statement.mnemonic.as_str() {
+        ".FILL" => {
+            expect(1)?;
+            let text = word_operand(operands, 0)?;
+            let value = match symbols.get(&text) {
+                Some(&address) => address,
+                None => parse_word(&text)?,
+            };
+            words.push(value);
+            return Ok(());
+        }
+        ".BLKW" => {
+            expect(1)?;
+            let count = parse_word(&word_operand(operands, 0)?)?;
+            words.extend(std::iter::repeat_n(0, count as usize));
+            return Ok(());
+        }
+        ".STRINGZ" => {
+            expect(1)?;
+            if let Some(Token::Str(text)) = operands.first() {
+                words.extend(text.bytes().map(u16::from));
+                words.push(0);
+            }
+            return Ok(());
+        }
+        "ADD" | "AND" => {
+            expect(3)?;
+            let (dr, sr1) = (reg(0)?, reg(1)?);
+            let text = word_operand(operands, 2)?;
+            let op2 = match register(&text) {
+                Ok(sr2) => Operand::Register(sr2),
+                Err(_) => Operand::Immediate(imm(2, 5)?),
+            };
+            if statement.mnemonic == "ADD" {
+                Instruction::Add { dr, sr1, op2 }
+            } else {
+                Instruction::And { dr, sr1, op2 }
+            }
+        }
+        "NOT" => {
+            expect(2)?;
+            Instruction::Not { dr: reg(0)?, sr: reg(1)? }
+        }
+        "NOP" => match operands.len() {
+            0 => Instruction::Br { nzp: 0, offset: 0 },
+            _ => {
+                expect(1)?;
+                Instruction::Br { nzp: 0, offset: offset(0, 9)? }
+            }
+        },
+        "JMP" => {
+            expect(1)?;
+            Instruction::Jmp { base: reg(0)? }
+        }
+        "RET" => {
+            expect(0)?;
+            Instruction::Jmp { base: 7 }
+        }
+        "JSR" => {
+            expect(1)?;
+            Instruction::Jsr { offset: offset(0, 11)? }
+        }
+        "JSRR" => {
+            expect(1)?;
+            Instruction::Jsrr { base: reg(0)? }
+        }
+        "LD" | "LDI" | "LEA" | "ST" | "STI" => {
+            expect(2)?;
+            let (r, offset) = (reg(0)?, offset(1, 9)?);
+            match statement.mnemonic.as_str() {
+                "LD" => Instruction::Ld { dr: r, offset },
+                "LDI" => Instruction::Ldi { dr: r, offset },
+                "LEA" => Instruction::Lea { dr: r, offset },
+                "ST" => Instruction::St { sr: r, offset },
+                _ => Instruction::Sti { sr: r, offset },
+            }
+        }
+        "LDR" | "STR" => {
+            expect(3)?;
+            let (r, base, offset) = (reg(0)?, reg(1)?, imm(2, 6)?);
+            if statement.mnemonic == "LDR" {
+                Instruction::Ldr { dr: r, base, offset }
+            } else {
+                Instruction::Str { sr: r, base, offset }
+            }
+        }
+        "TRAP" => {
+            expect(1)?;
+            let text = word_operand(operands, 0)?;
+            match parse_number(&text) {
+                Some(vector @ 0..=0xFF) => Instruction::Trap { vector: vector as u8 },
+                _ => return Err(format!("Invalid trap vector: {}", text)),
+            }
+        }
+        "RTI" => {
+            expect(0)?;
+            Instruction::Rti
+        }
+        mnemonic => match trap_alias(mnemonic) {
+            Some(vector) => {
+                expect(0)?;
+                Instruction::Trap { vector }
+            }
+            None => match branch_condition(mnemonic) {
+                Some(nzp) => {
+                    expect(1)?;
+                    Instruction::Br { nzp, offset: offset(0, 9)? }
+                }
+                None => return Err(format!("Unknown instruction: {}", mnemonic)),
+            },
+        },
+    };
+
+    words.push(instruction.encode());
+    Ok(())
+}
+
+/// Splits a line into tokens, dropping the comment
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c == ';' {
+            break;
+        } else if c.is_whitespace() || c == ',' {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err("Unterminated string".to_string()),
+                    Some('"') => break,
+                    Some('\\') => text.push(match chars.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some('e') => '\x1B',
+                        Some('\\') => '\\',
+                        Some('"') => '"',
+                        other => return Err(format!("Invalid escape: \\{}", other.map(String::from).unwrap_or_default())),
+                    }),
+                    Some(c) => text.push(c),
+                }
+            }
+            if !text.is_ascii() {
+                return Err("Strings must be ASCII".to_string());
+            }
+            tokens.push(Token::Str(text));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == ',' || c == ';' || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn word_operand(operands: &[Token], index: usize) -> Result<String, String> {
+    match operands.get(index) {
+        Some(Token::Word(word)) => Ok(word.clone()),
+        Some(Token::Str(text)) => Err(format!("Unexpected string: {:?}", text)),
+        None => Err("Missing operand".to_string()),
+    }
+}
+
+fn is_mnemonic(word: &str) -> bool {
+    let upper = word.to_ascii_uppercase();
+    upper.starts_with('.')
+        || trap_alias(&upper).is_some()
+        || branch_condition(&upper).is_some()
+        || matches!(
+            upper.as_str(),
+            "ADD" | "AND" | "NOT" | "NOP" | "JMP" | "RET" | "JSR" | "JSRR" | "LD" | "LDI" | "LDR" | "LEA" | "ST" | "STI" | "STR" | "TRAP" | "RTI"
+        )
+}
+
+fn is_label(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && register(word).is_err()
+}
+
+fn trap_alias(mnemonic: &str) -> Option<u8> {
+    match mnemonic {
+        "GETC" => Some(0x20),
+        "OUT" => Some(0x21),
+        "PUTS" => Some(0x22),
+        "IN" => Some(0x23),
+        "PUTSP" => Some(0x24),
+        "HALT" => Some(0x25),
+        _ => None,
+    }
+}
+
+/// Returns the nzp bits of `BR`, `BRn`, `BRzp` and so on; plain `BR` branches always
+fn branch_condition(mnemonic: &str) -> Option<u8> {
+    let flags = mnemonic.strip_prefix("BR")?;
+    if flags.is_empty() {
+        return Some(0b111);
+    }
+
+    let mut nzp = 0;
+    let mut rest = flags;
+    for (flag, bit) in [('N', 0b100), ('Z', 0b010), ('P', 0b001)] {
+        if let Some(after) = rest.strip_prefix(flag) {
+            nzp |= bit;
+            rest = after;
+        }
+    }
+    rest.is_empty().then_some(nzp)
+}
+
+fn register(text: &str) -> Result<u8, String> {
+    match text.as_bytes() {
+        [b'R' | b'r', digit @ b'0'..=b'7'] => Ok(digit - b'0'),
+        _ => Err(format!("Expected a register, found {}", text)),
+    }
+}
+
+fn immediate(text: &str, bits: u32) -> Result<i16, String> {
+    let value = parse_number(text).ok_or_else(|| format!("Invalid value: {}", text))?;
+    fit(value, bits).ok_or_else(|| format!("{} does not fit in {} signed bits", text, bits))
+}
+
+/// Resolves a label or literal offset to an offset from the incremented PC
+fn pc_offset(text: &str, bits: u32, address: u16, symbols: &BTreeMap<String, u16>) -> Result<i16, String> {
+    let value = match symbols.get(text) {
+        Some(&target) => target as i32 - (address as i32 + 1),
+        None => parse_number(text).ok_or_else(|| format!("Undefined label: {}", text))?,
+    };
+    fit(value, bits).ok_or_else(|| format!("{} is out of range of a {}-bit offset", text, bits))
+}
+
+fn fit(value: i32, bits: u32) -> Option<i16> {
+    let limit = 1 << (bits - 1);
+    (-limit..limit).contains(&value).then_some(value as i16)
+}
@@ -0,0 +1,200 @@
+//! Several machines in one process, linked by message ports and shared memory.
+//!
+//! A `Cluster` owns its machines. `connect` links two of them with a pair of
+//! message ports, and `share` maps one window of words into several. Both are
+//! devices, so they live in the device page (xFE00-xFFFF) and each machine
+//! chooses where. `run` then executes every machine until it halts, faults or
+//! reaches its own `Config::max_instructions`, either taking turns on the
+//! calling thread or each on a thread of its own.
+//!
+//! Taking turns is deterministic: each machine runs exactly `quantum`
+//! instructions per turn, in the order the machines were added, so a cluster
+//! whose machines have scripted input and no wall-clock devices behaves the
+//! same on every run and every engine. Each machine's console is set up before
+//! its first turn and restored after the last machine stops, and
+//! `Config::timeout` counts from the start of the whole run.
+
+use std::collections::VecDeque;
+use std::io;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crate::vm::{Device, DeviceContext, StopReason, LC3};
+
+/// Port status bit set when a message is waiting in the data register
+pub const PORT_RECEIVE_READY: u16 = 1 << 15;
+/// Port status bit set when the other machine's queue has room for a message
+pub const PORT_TRANSMIT_READY: u16 = 1 << 14;
+/// Messages a port holds before further ones are dropped
+pub const PORT_CAPACITY: usize = 256;
+
+/// How `Cluster::run` interleaves the machines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// Each machine in turn runs `quantum` instructions on the calling thread, reproducibly
+    RoundRobin { quantum: u64 },
+    /// Every machine runs on its own thread at once; the interleaving varies between runs
+    Parallel,
+}
+
+/// `Schedule::Parallel` runs each machine on its own thread, so `LC3` must be
+/// `Send` on every platform
+const _: fn() = _assert_send::<LC3>;
+
+fn _assert_send<T: Send>() {}
+
+/// Machines run together
+#[derive(Default)]
+pub struct Cluster {
+    pub machines: Vec<LC3>,
+}
+
+impl Cluster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a machine, returning its index
+    pub fn add(&mut self, vm: LC3) -> usize {
+        self.machines.push(vm);
+        self.machines.len() - 1
+    }
+
+    /// Links machine `a` and machine `b` with a pair of message ports: a status
+    /// register at `a_address` (resp. `b_address`) and a data register two
+    /// above it. Each word written to one machine's data register is queued
+    /// for the other to read from its own.
+    pub fn connect(&mut self, a: usize, a_address: u16, b: usize, b_address: u16) -> io::Result<()> {
+        if a == b || a.max(b) >= self.machines.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Ports link two different machines of the cluster"));
+        }
+        let (to_a, to_b) = (Queue::default(), Queue::default());
+        let port_a = Port { status: a_address, inbox: to_a.clone(), outbox: to_b.clone(), data: 0 };
+        let port_b = Port { status: b_address, inbox: to_b, outbox: to_a, data: 0 };
+        self.machines[a].attach_device(a_address..=a_address.wrapping_add(2), Box::new(port_a))?;
+        self.machines[b].attach_device(b_address..=b_address.wrapping_add(2), Box::new(port_b))
+    }
+
+    /// Maps the same words at `addresses` into every machine in `members`, starting at zero
+    pub fn share(&mut self, members: &[usize], addresses: RangeInclusive<u16>) -> io::Result<()> {
+        if members.iter().any(|&member| member >= self.machines.len()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No such machine in the cluster"));
+        }
+        let words: Arc<[AtomicU16]> = addresses.clone().map(|_| AtomicU16::new(0)).collect();
+        for &member in members {
+            let window = SharedWindow { start: *addresses.start(), words: words.clone() };
+            self.machines[member].attach_device(addresses.clone(), Box::new(window))?;
+        }
+        Ok(())
+    }
+
+    /// Runs every machine until it halts, faults or reaches its own limits,
+    /// returning how each one stopped. A machine that faults stops while the
+    /// others carry on.
+    pub fn run(&mut self, schedule: Schedule) -> Vec<io::Result<StopReason>> {
+        match schedule {
+            Schedule::RoundRobin { quantum } => self.run_round_robin(quantum.max(1)),
+            Schedule::Parallel => thread::scope(|scope| {
+                let threads: Vec<_> = self.machines.iter_mut().map(|vm| scope.spawn(|| vm.run())).collect();
+                threads.into_iter().map(|thread| thread.join().expect("machine thread panicked")).collect()
+            }),
+        }
+    }
+
+    fn run_round_robin(&mut self, quantum: u64) -> Vec<io::Result<StopReason>> {
+        let start_time = Instant::now();
+        let starts: Vec<u64> = self.machines.iter().map(|vm| vm.instruction_count).collect();
+        // Machines that fail to start stop at once, and are not cleaned up
+        let mut results: Vec<Option<io::Result<StopReason>>> =
+            self.machines.iter_mut().map(|vm| vm.begin_run().err().map(Err)).collect();
+        let started: Vec<bool> = results.iter().map(Option::is_none).collect();
+
+        while results.iter().any(Option::is_none) {
+            for (index, vm) in self.machines.iter_mut().enumerate() {
+                if results[index].is_some() {
+                    continue;
+                }
+                let limit = vm.config.max_instructions;
+                let executed = |vm: &LC3| vm.instruction_count - starts[index];
+                let left = limit.map_or(quantum, |max| max.saturating_sub(executed(vm)));
+                results[index] = match vm.run_until_stopped(Some(left.min(quantum)), start_time) {
+                    Ok(StopReason::InstructionLimit) if limit.is_none_or(|max| executed(vm) < max) => None,
+                    result => Some(result),
+                };
+            }
+        }
+
+        let stopped = self.machines.iter_mut().zip(started).zip(results.into_iter().flatten());
+        stopped.map(|((vm, started), result)| if started { vm.end_run(result) } else { result }).collect()
+    }
+}
+
+type Queue = Arc<Mutex<VecDeque<u16>>>;
+
+/// One end of a message link between two machines
+struct Port {
+    status: u16,
+    inbox: Queue,
+    outbox: Queue,
+    /// The message last read
+    data: u16,
+}
+
+impl Device for Port {
+    fn name(&self) -> &str {
+        "port"
+    }
+
+    fn read(&mut self, address: u16, _context: &mut DeviceContext) -> io::Result<u16> {
+        if address == self.status {
+            let mut status = 0;
+            if !self.inbox.lock().unwrap().is_empty() {
+                status |= PORT_RECEIVE_READY;
+            }
+            if self.outbox.lock().unwrap().len() < PORT_CAPACITY {
+                status |= PORT_TRANSMIT_READY;
+            }
+            Ok(status)
+        } else if address == self.status.wrapping_add(2) {
+            if let Some(message) = self.inbox.lock().unwrap().pop_front() {
+                self.data = message;
+            }
+            Ok(self.data)
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Queues a message for the other machine, dropping it if its queue is full
+    fn write(&mut self, address: u16, value: u16) {
+        if address == self.status.wrapping_add(2) {
+            let mut outbox = self.outbox.lock().unwrap();
+            if outbox.len() < PORT_CAPACITY {
+                outbox.push_back(value);
+            }
+        }
+    }
+}
+
+/// Words shared by several machines
+struct SharedWindow {
+    start: u16,
+    words: Arc<[AtomicU16]>,
+}
+
+impl Device for SharedWindow {
+    fn name(&self) -> &str {
+        "shared memory"
+    }
+
+    fn read(&mut self, address: u16, _context: &mut DeviceContext) -> io::Result<u16> {
+        Ok(self.words[(address - self.start) as usize].load(Ordering::SeqCst))
+    }
+
+    fn write(&mut self, address: u16, value: u16) {
+        self.words[(address - self.start) as usize].store(value, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+
+use crate::asm::parse_word;
+use crate::vm::{condition_name, Fault, Instruction, Register, LC3};
+
+const HELP: &str = "\
+Commands:
+  s, step [n]          Execute n instructions (default 1)
+  u, ustep             Run one state of the microsequencer and show the datapath
+  c, continue          Run until a breakpoint, HALT or a fault
+  b, break [addr]      Set a breakpoint, or list breakpoints
+  d, delete <addr>     Remove a breakpoint
+  r, regs              Show the registers
+  m, mem <addr> [n]    Show n words of memory (default 8)
+  l, list [addr] [n]   Disassemble n instructions (default 8, from PC)
+  set <reg|addr> <v>   Change a register or memory word
+  q, quit              Leave the debugger
+An empty line repeats the previous command. Addresses may be labels.";
+
+/// Interactive debugger driving an `LC3` one instruction at a time
+pub struct Debugger<'a> {
+    vm: &'a mut LC3,
+    breakpoints: BTreeSet<u16>,
+    /// Labels accepted wherever an address is expected and shown in listings
+    pub symbols: BTreeMap<String, u16>,
+}
+
+impl<'a> Debugger<'a> {
+    /// Takes control of `vm`, which starts at its current PC
+    pub fn new(vm: &'a mut LC3) -> Self {
+        vm.running = true;
+        Debugger {
+            vm,
+            breakpoints: BTreeSet::new(),
+            symbols: BTreeMap::new(),
+        }
+    }
+
+    /// Addresses execution stops at
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    /// Reads commands from `input` until `quit` or the end of input
+    pub fn repl<R: BufRead, W: Write>(&mut self, mut input: R, mut output: W) -> io::Result<()> {
+        let mut previous = String::new();
+        self.show_next(&mut output)?;
+
+        loop {
+            write!(output, "(lc3) ")?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(());
+            }
+            let command = match line.trim() {
+                "" => previous.clone(),
+                command => command.to_string(),
+            };
+            if !self.execute(&command, &mut output)? {
+                return Ok(());
+            }
+            previous = command;
+        }
+    }
+
+    /// Runs one command, returning false once the user asks to quit
+    pub fn execute<W: Write>(&mut self, command: &str, output: &mut W) -> io::Result<bool> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let Some((&name, args)) = words.split_first() else {
+            return Ok(true);
+        };
+
+        let result = match name {
+            "s" | "step" => match args.first().map(|count| count.parse::<u64>()) {
+                None => self.resume(1, output),
+                Some(Ok(count)) => self.resume(count, output),
+                Some(Err(_)) => Err("step expects a count".to_string()),
+            },
+            "c" | "continue" => self.resume(u64::MAX, output),
+            "u" | "ustep" => self.microstep(output),
+            "b" | "break" => match args.first() {
+                None => {
+                    for &address in &self.breakpoints {
+                        writeln!(output, "  {}", self.describe(address).trim_end())?;
+                    }
+                    Ok(())
+                }
+                Some(text) => self.address(text).map(|address| {
+                    self.breakpoints.insert(address);
+                }),
+            },
+            "d" | "delete" => match args.first() {
+                Some(text) => self.address(text).and_then(|address| {
+                    if self.breakpoints.remove(&address) {
+                        Ok(())
+                    } else {
+                        Err(format!("No breakpoint at x{:04X}", address))
+                    }
+                }),
+                None => Err("delete expects an address".to_string()),
+            },
+            "r" | "regs" => {
+                self.show_registers(output)?;
+                Ok(())
+            }
+            "m" | "mem" => match args {
+                [start] | [start, _] => {
+                    let count = args.get(1).map_or(Ok(8), |count| parse_word(count));
+                    match (self.address(start), count) {
+                        (Ok(start), Ok(count)) => {
+                            for offset in 0..count {
+                                let address = start.wrapping_add(offset);
+                                let word = self.vm.memory.get_ptr(address)[0];
+                                writeln!(output, "{}  x{:04X}  {}", self.describe(address), word, word as i16)?;
+                            }
+                            Ok(())
+                        }
+                        (Err(err), _) | (_, Err(err)) => Err(err),
+                    }
+                }
+                _ => Err("mem expects an address and an optional count".to_string()),
+            },
+            "l" | "list" => {
+                let pc = self.vm.registers.get(Register::PC);
+                let start = args.first().map_or(Ok(pc), |text| self.address(text));
+                let count = args.get(1).map_or(Ok(8), |count| parse_word(count));
+                match (start, count) {
+                    (Ok(start), Ok(count)) => {
+                        for offset in 0..count {
+                            self.show_instruction(start.wrapping_add(offset), output)?;
+                        }
+                        Ok(())
+                    }
+                    (Err(err), _) | (_, Err(err)) => Err(err),
+                }
+            }
+            "set" => match args {
+                [target, value] => self.set(target, value),
+                _ => Err("set expects a register or address and a value".to_string()),
+            },
+            "h" | "help" => {
+                writeln!(output, "{}", HELP)?;
+                Ok(())
+            }
+            "q" | "quit" => return Ok(false),
+            _ => Err(format!("Unknown command: {} (try help)", name)),
+        };
+
+        if let Err(message) = result {
+            writeln!(output, "{}", message)?;
+        }
+        Ok(true)
+    }
+
+    /// Executes up to `count` instructions, stopping early at a breakpoint, HALT or fault
+    fn resume<W: Write>(&mut self, count: u64, output: &mut W) -> Result<(), String> {
+        if !self.vm.running {
+            return Err("The program has halted".to_string());
+        }
+
+        let breakpoints = &self.breakpoints;
+        let result = with_console(self.vm, |vm| {
+            for executed in 0..count {
+                let pc = vm.registers.get(Register::PC);
+                if executed > 0 && breakpoints.contains(&pc) {
+                    break;
+                }
+                vm.step()?;
+                if !vm.running {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        let report = match result {
+            Ok(()) if !self.vm.running => writeln!(output, "Program halted after {} instructions", self.vm.instruction_count),
+            Ok(()) => self.show_next(output),
+            Err(err) => self.show_error(&err, output),
+        };
+        report.map_err(|err| err.to_string())
+    }
+
+    /// Runs one state of the microsequencer and shows the datapath afterwards
+    fn microstep<W: Write>(&mut self, output: &mut W) -> Result<(), String> {
+        if !self.vm.running {
+            return Err("The program has halted".to_string());
+        }
+
+        let report = match with_console(self.vm, LC3::microstep) {
+            Ok(state) => {
+                let datapath = self.vm.datapath();
+                writeln!(
+                    output,
+                    "State {:<2} -> {:<2}  MAR=x{:04X}  MDR=x{:04X}  IR=x{:04X}  BEN={}",
+                    state, datapath.state, datapath.mar, datapath.mdr, datapath.ir, datapath.ben as u8
+                )
+            }
+            Err(err) => self.show_error(&err, output),
+        };
+        report.map_err(|err| err.to_string())
+    }
+
+    fn show_error<W: Write>(&self, err: &io::Error, output: &mut W) -> io::Result<()> {
+        let pc = self.vm.registers.get(Register::PC).wrapping_sub(1);
+        match Fault::from_io_error(err) {
+            Some(fault) => writeln!(output, "Fault: {} at x{:04X}", fault, pc),
+            None => writeln!(output, "Error: {}", err),
+        }
+    }
+
+    fn set(&mut self, target: &str, value: &str) -> Result<(), String> {
+        let value = parse_word(value)?;
+        match target.parse::<Register>() {
+            Ok(register) => self.vm.registers.set(register, value),
+            Err(_) => {
+                let address = self.address(target)?;
+                self.vm.memory.write(address, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves a label or a number to an address
+    fn address(&self, text: &str) -> Result<u16, String> {
+        match self.symbols.get(text) {
+            Some(&address) => Ok(address),
+            None => parse_word(text),
+        }
+    }
+
+    /// Formats an address with its label, if it has one
+    fn describe(&self, address: u16) -> String {
+        match self.symbols.iter().find(|&(_, &value)| value == address) {
+            Some((label, _)) => format!("x{:04X} {:<10}", address, label),
+            None => format!("x{:04X} {:<10}", address, ""),
+        }
+    }
+
+    fn show_instruction<W: Write>(&self, address: u16, output: &mut W) -> io::Result<()> {
+        let word = self.vm.memory.get_ptr(address)[0];
+        let instruction = Instruction::decode(word);
+        let marker = if self.breakpoints.contains(&address) { '*' } else { ' ' };
+        let mut line = format!("{}{}  x{:04X}  {}", marker, self.describe(address), word, instruction);
+        if let Some(target) = instruction.target(address) {
+            line += &format!("  ; {}", self.describe(target).trim_end());
+        }
+        writeln!(output, "{}", line)
+    }
+
+    fn show_next<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        self.show_instruction(self.vm.registers.get(Register::PC), output)
+    }
+
+    fn show_registers<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let registers = &self.vm.registers;
+        for row in [[Register::R0, Register::R1, Register::R2, Register::R3], [Register::R4, Register::R5, Register::R6, Register::R7]] {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|&register| format!("{:?}=x{:04X}", register, registers.get(register)))
+                .collect();
+            writeln!(output, "{}", cells.join("  "))?;
+        }
+        writeln!(
+            output,
+            "PC=x{:04X}  CC={}  instructions={}",
+            registers.get(Register::PC),
+            condition_name(registers.get_condition_flag()),
+            self.vm.instruction_count
+        )
+    }
+}
+
+/// Runs `f` with the console in raw mode, restoring it afterwards
+fn with_console<T>(vm: &mut LC3, f: impl FnOnce(&mut LC3) -> io::Result<T>) -> io::Result<T> {
+    let ctrl_c = vm.config.ctrl_c;
+    vm.console_mut().setup(ctrl_c)?;
+    let result = f(vm);
+    let cleanup = vm.console_mut().cleanup();
+
+    let value = result?;
+    cleanup?;
+    Ok(value)
+}
@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::asm::parse_word;
+use crate::io::console::Console;
+use crate::vm::{CycleModel, Fault, Register, StopReason, LC3, PC_START};
+
+/// Instruction budget for cases that do not set `max_instructions`
+pub const DEFAULT_MAX_INSTRUCTIONS: u64 = 1_000_000;
+
+/// A grading specification: the program to grade and the cases to run it against.
+///
+/// Addresses and values are JSON numbers or strings in LC-3 notation
+/// (`"x3000"`, `"#-5"`, `"12"`); memory entries take a single value or an
+/// array of consecutive words.
+///
+/// ```json
+/// {
+///   "image": "submission.obj",
+///   "cases": [
+///     {
+///       "name": "adds two numbers",
+///       "registers": { "R1": 5, "R2": "x0007" },
+///       "memory": { "x4000": ["#-1", 2] },
+///       "input": "q",
+///       "max_instructions": 10000,
+///       "expect": {
+///         "halted": true,
+///         "output": "12\nHALT\n",
+///         "registers": { "R0": 12 },
+///         "memory": { "x5000": [1, 2] }
+///       }
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spec {
+    /// Object file to grade, relative to the spec file; may be overridden on the command line
+    #[serde(default)]
+    pub image: Option<String>,
+    pub cases: Vec<Case>,
+}
+
+/// One run of the program on a fresh machine
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    pub name: String,
+    /// Register presets applied after the image is loaded; PC defaults to x3000
+    #[serde(default)]
+    pub registers: BTreeMap<String, Word>,
+    /// Memory presets applied after the image is loaded
+    #[serde(default)]
+    pub memory: BTreeMap<String, Words>,
+    /// Keyboard input, delivered as the program reads it
+    #[serde(default)]
+    pub input: String,
+    #[serde(default)]
+    pub max_instructions: Option<u64>,
+    /// Attaches the random number generator at xFE10 with this seed
+    #[serde(default)]
+    pub random_seed: Option<u64>,
+    #[serde(default)]
+    pub expect: Expect,
+}
+
+/// Conditions a case must satisfy to pass
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expect {
+    /// Whether the program must halt (true) or still be running at the instruction limit (false)
+    #[serde(default)]
+    pub halted: Option<bool>,
+    /// The exact console output
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Text that must appear somewhere in the console output
+    #[serde(default)]
+    pub output_contains: Option<String>,
+    #[serde(default)]
+    pub registers: BTreeMap<String, Word>,
+    #[serde(default)]
+    pub memory: BTreeMap<String, Words>,
+}
+
+/// A 16-bit value written as a JSON number or an LC-3 literal
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Word {
+    Number(i64),
+    Text(String),
+}
+
+/// One word or a run of consecutive words
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Words {
+    One(Word),
+    Many(Vec<Word>),
+}
+
+/// Outcome of a single case
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    pub name: String,
+    pub passed: bool,
+    /// How the run ended: `halted`, `instruction limit`, or a fault or error message
+    pub stop: String,
+    pub instructions: u64,
+    /// Clock cycles under the default `CycleModel`, for comparing solutions
+    pub cycles: u64,
+    pub output: String,
+    pub failures: Vec<String>,
+}
+
+/// Results of every case in a spec
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub passed: usize,
+    pub total: usize,
+    pub cases: Vec<CaseResult>,
+}
+
+impl Spec {
+    /// Parses a JSON grading specification
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        serde_json::from_str(text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl Report {
+    /// Whether every case passed
+    pub fn all_passed(&self) -> bool {
+        self.passed == self.total
+    }
+
+    /// Serializes the report as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("reports always serialize")
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for case in &self.cases {
+            let status = if case.passed { "PASS" } else { "FAIL" };
+            writeln!(f, "{} {} ({}, {} instructions)", status, case.name, case.stop, case.instructions)?;
+            for failure in &case.failures {
+                writeln!(f, "     {}", failure)?;
+            }
+        }
+        write!(f, "{}/{} cases passed", self.passed, self.total)
+    }
+}
+
+/// Runs every case in `spec` against the object image `image`
+pub fn grade(spec: &Spec, image: &[u8]) -> Report {
+    let cases: Vec<CaseResult> = spec.cases.iter().map(|case| run_case(case, image)).collect();
+    Report {
+        passed: cases.iter().filter(|case| case.passed).count(),
+        total: cases.len(),
+        cases,
+    }
+}
+
+fn run_case(case: &Case, image: &[u8]) -> CaseResult {
+    let mut result = CaseResult {
+        name: case.name.clone(),
+        passed: false,
+        stop: String::new(),
+        instructions: 0,
+        cycles: 0,
+        output: String::new(),
+        failures: Vec::new(),
+    };
+
+    let mut vm = LC3::with_console(Console::scripted(case.input.as_bytes()));
+    vm.config.max_instructions = Some(case.max_instructions.unwrap_or(DEFAULT_MAX_INSTRUCTIONS));
+    vm.config.random = case.random_seed.is_some();
+    vm.config.random_seed = case.random_seed;
+    vm.config.cycles = Some(CycleModel::default());
+
+    if let Err(err) = vm.read_image(image) {
+        result.stop = format!("error: {}", err);
+        result.failures.push(format!("Could not load image: {}", err));
+        return result;
+    }
+    vm.registers.set(Register::PC, PC_START);
+    if let Err(err) = preset(&mut vm, case) {
+        result.stop = "not run".to_string();
+        result.failures.push(err);
+        return result;
+    }
+
+    let stop = vm.run();
+    result.instructions = vm.instruction_count;
+    result.cycles = vm.stats().cycles;
+    result.output = String::from_utf8_lossy(vm.console().output()).into_owned();
+    result.stop = match &stop {
+        Ok(StopReason::Halted) => "halted".to_string(),
+        Ok(StopReason::InstructionLimit) => "instruction limit".to_string(),
+        Ok(StopReason::Timeout) => "timeout".to_string(),
+        Err(err) => match Fault::from_io_error(err) {
+            Some(fault) => format!("fault: {}", fault),
+            None => format!("error: {}", err),
+        },
+    };
+
+    let halted = matches!(stop, Ok(StopReason::Halted));
+    match case.expect.halted {
+        Some(true) if !halted => result.failures.push(format!("Expected the program to halt, but it stopped with {}", result.stop)),
+        Some(false) if halted => result.failures.push("Expected the program to keep running, but it halted".to_string()),
+        None if stop.is_err() => result.failures.push(format!("Program stopped with {}", result.stop)),
+        _ => {}
+    }
+
+    if let Some(expected) = &case.expect.output {
+        if result.output != *expected {
+            result.failures.push(format!("Output was {:?}, expected {:?}", result.output, expected));
+        }
+    }
+    if let Some(expected) = &case.expect.output_contains {
+        if !result.output.contains(expected.as_str()) {
+            result.failures.push(format!("Output {:?} does not contain {:?}", result.output, expected));
+        }
+    }
+
+    for (name, value) in &case.expect.registers {
+        match (name.parse::<Register>(), value.parse()) {
+            (Ok(register), Ok(expected)) => {
+                let actual = vm.registers.get(register);
+                if actual != expected {
+                    result.failures.push(format!("{} was x{:04X}, expected x{:04X}", name, actual, expected));
+                }
+            }
+            (Err(err), _) | (_, Err(err)) => result.failures.push(err),
+        }
+    }
+
+    for (address, words) in &case.expect.memory {
+        match (parse_word(address), words.parse()) {
+            (Ok(start), Ok(expected)) => {
+                for (offset, expected) in expected.iter().enumerate() {
+                    let at = start.wrapping_add(offset as u16);
+                    let actual = vm.memory.get_ptr(at)[0];
+                    if actual != *expected {
+                        result.failures.push(format!("x{:04X} was x{:04X}, expected x{:04X}", at, actual, expected));
+                    }
+                }
+            }
+            (Err(err), _) | (_, Err(err)) => result.failures.push(err),
+        }
+    }
+
+    result.passed = result.failures.is_empty();
+    result
+}
+
+/// Applies a case's register and memory presets
+fn preset(vm: &mut LC3, case: &Case) -> Result<(), String> {
+    for (name, value) in &case.registers {
+        vm.registers.set(name.parse::<Register>()?, value.parse()?);
+    }
+    for (address, words) in &case.memory {
+        let start = parse_word(address)?;
+        for (offset, word) in words.parse()?.into_iter().enumerate() {
+            vm.memory.write(start.wrapping_add(offset as u16), word);
+        }
+    }
+    Ok(())
+}
+
+impl Word {
+    fn parse(&self) -> Result<u16, String> {
+        match self {
+            Word::Number(n) if (-32768..=65535).contains(n) => Ok(*n as u16),
+            Word::Number(n) => Err(format!("Value out of range: {}", n)),
+            Word::Text(text) => parse_word(text),
+        }
+    }
+}
+
+impl Words {
+    fn parse(&self) -> Result<Vec<u16>, String> {
+        match self {
+            Words::One(word) => Ok(vec![word.parse()?]),
+            Words::Many(words) => words.iter().map(Word::parse).collect(),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::vm::{LC3, MEMORY_SIZE};
+
+/// On-disk encodings of a program image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Big-endian words, the first being the origin (`.obj`)
+    Obj,
+    /// One four-digit hex word per line, the first being the origin (`.hex`)
+    Hex,
+    /// One sixteen-digit binary word per line, the first being the origin (`.bin`)
+    Bin,
+}
+
+impl Format {
+    /// Picks the format from a file extension, defaulting to `Obj`
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let extension = path.as_ref().extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        match extension.to_ascii_lowercase().as_str() {
+            "hex" => Format::Hex,
+            "bin" => Format::Bin,
+            _ => Format::Obj,
+        }
+    }
+}
+
+/// A contiguous block of words and the address it is loaded at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub origin: u16,
+    pub words: Vec<u16>,
+}
+
+impl Image {
+    /// Decodes an image in the given format
+    pub fn parse(bytes: &[u8], format: Format) -> io::Result<Self> {
+        let words: Vec<u16> = match format {
+            // A trailing odd byte is ignored, as `LC3::read_image` does
+            Format::Obj => bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect(),
+            Format::Hex => parse_lines(bytes, 16, 4)?,
+            Format::Bin => parse_lines(bytes, 2, 16)?,
+        };
+
+        let (&origin, rest) = words.split_first().ok_or_else(|| invalid("Image has no origin"))?;
+        let mut words = rest.to_vec();
+        words.truncate(MEMORY_SIZE - origin as usize);
+        Ok(Image { origin, words })
+    }
+
+    /// Encodes the image in the given format
+    pub fn to_bytes(&self, format: Format) -> Vec<u8> {
+        let all = std::iter::once(self.origin).chain(self.words.iter().copied());
+        match format {
+            Format::Obj => all.flat_map(u16::to_be_bytes).collect(),
+            Format::Hex => all.map(|word| format!("{:04X}\n", word)).collect::<String>().into_bytes(),
+            Format::Bin => all.map(|word| format!("{:016b}\n", word)).collect::<String>().into_bytes(),
+        }
+    }
+
+    /// Reads an image, choosing the format from the file extension
+    pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read(&path)?, Format::from_path(&path))
+    }
+
+    /// Writes the image, choosing the format from the file extension
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(&path, self.to_bytes(Format::from_path(&path)))
+    }
+}
+
+/// Parses one word per line in the given radix, skipping blank lines and `;` comments
+fn parse_lines(bytes: &[u8], radix: u32, digits: usize) -> io::Result<Vec<u16>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid("Image is not valid text"))?;
+    let mut words = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        match u16::from_str_radix(line, radix) {
+            Ok(word) if line.len() == digits => words.push(word),
+            _ => return Err(invalid(&format!("Line {}: expected a {}-digit word, found {:?}", number + 1, digits, line))),
+        }
+    }
+
+    Ok(words)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl LC3 {
+    /// Copies an image into memory at its origin
+    pub fn load(&mut self, image: &Image) {
+        for (offset, &word) in image.words.iter().enumerate() {
+            self.memory.write(image.origin.wrapping_add(offset as u16), word);
+        }
+    }
+}
@@ -1,47 +1,336 @@
-use std::io::{self, Write};
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+use crate::io::pipe::PipeInput;
 use crate::io::platform::Platform;
+use crate::io::recording::{InputEvent, INPUT_LOG_HEADER};
+
+/// Where the console takes its keyboard input from once pending input runs out
+enum Input {
+    /// The host terminal, switched into raw mode while the VM runs
+    Terminal(Platform),
+    /// A pipe or file, read without changing any terminal settings
+    Pipe(PipeInput),
+    /// Nothing beyond the bytes supplied up front
+    Script,
+    /// Recorded keys, each made available once the VM reaches its instruction count
+    Replay(VecDeque<InputEvent>),
+}
+
+/// Output waiting longer than this is flushed by `Console::flush_stale`
+pub const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+/// Buffered output is flushed once it grows to this many bytes
+const BUFFER_LIMIT: usize = 8192;
+
+/// Where the console sends its output
+enum Output {
+    /// The host's standard output, written from this buffer when it is flushed
+    Stdout(Vec<u8>),
+    /// An in-memory buffer that can be inspected afterwards
+    Capture(Vec<u8>),
+}
+
+/// Two polls of an empty keyboard at most this many instructions apart, with
+/// no output in between, mean the program is idle waiting for a key
+const IDLE_POLL_DISTANCE: u64 = 64;
+/// Longest time an idle poll blocks before letting the program run again
+const IDLE_WAIT: Duration = Duration::from_millis(10);
+
+/// When output written to stdout is flushed, besides the flushes made before
+/// waiting for input, on HALT, when the buffer fills and by `flush_stale`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Buffering {
+    /// Also flush at every newline; the default when stdout is a terminal
+    Line,
+    /// Only flush at the points above; the default for pipes and files
+    Block,
+}
+
+/// What pressing Ctrl-C on the terminal does while a program runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum CtrlC {
+    /// Restore the terminal and terminate the process
+    #[default]
+    Kill,
+    /// Pass the key to the program as ASCII ETX (x03)
+    Deliver,
+}
 
 /// Console abstraction for handling input/output operations
 pub struct Console {
-    platform: Platform,
+    input: Input,
+    output: Output,
+    pending: VecDeque<u8>,
+    recorder: Option<Box<dyn Write + Send>>,
+    instruction_count: u64,
+    buffering: Buffering,
+    last_flush: Instant,
+    /// Instruction count at the last poll that found no key, cleared by output
+    last_empty_poll: Option<u64>,
+    /// Set whenever the console may have blocked waiting for input
+    waited: bool,
 }
 
 impl Console {
     pub fn new() -> Self {
+        Self::with_input(Input::Terminal(Platform::new()))
+    }
+
+    /// Creates a console for running without a terminal: keys come from stdin,
+    /// which may be a pipe or a file, and the terminal mode is never changed.
+    /// See `PipeInput` for how the end of input is reported to the guest.
+    pub fn headless() -> Self {
+        Self::piped(PipeInput::stdin())
+    }
+
+    /// Creates a console that reads keys from `pipe` and writes to stdout
+    pub fn piped(pipe: PipeInput) -> Self {
+        Self::with_input(Input::Pipe(pipe))
+    }
+
+    /// Creates a console that reads keys from `input` and captures all output
+    pub fn scripted(input: &[u8]) -> Self {
         Console {
-            platform: Platform::new(),
+            input: Input::Script,
+            output: Output::Capture(Vec::new()),
+            pending: input.iter().copied().collect(),
+            recorder: None,
+            instruction_count: 0,
+            buffering: Buffering::Block,
+            last_flush: Instant::now(),
+            last_empty_poll: None,
+            waited: false,
         }
     }
 
-    /// Prepare the console for raw input mode
-    pub fn setup(&mut self) -> io::Result<()> {
-        self.platform.disable_input_buffering()
+    /// Creates a console that replays a recorded input log and writes to stdout.
+    /// Each key becomes available when the VM reaches the instruction count it was
+    /// recorded at, so polling programs observe it at exactly the same point.
+    pub fn replay(events: Vec<InputEvent>) -> Self {
+        Self::with_input(Input::Replay(events.into()))
     }
 
-    /// Restore the console to its original state
+    fn with_input(input: Input) -> Self {
+        Console {
+            input,
+            output: Output::Stdout(Vec::new()),
+            pending: VecDeque::new(),
+            recorder: None,
+            instruction_count: 0,
+            buffering: if io::stdout().is_terminal() { Buffering::Line } else { Buffering::Block },
+            last_flush: Instant::now(),
+            last_empty_poll: None,
+            waited: false,
+        }
+    }
+
+    /// Logs every key delivered to the guest to `writer`, in the format read by
+    /// `recording::read_input_log`
+    pub fn record_to(&mut self, mut writer: Box<dyn Write + Send>) -> io::Result<()> {
+        writeln!(writer, "{}", INPUT_LOG_HEADER)?;
+        self.recorder = Some(writer);
+        Ok(())
+    }
+
+    /// Tells the console how many instructions the VM has executed
+    pub fn set_instruction_count(&mut self, count: u64) {
+        self.instruction_count = count;
+    }
+
+    /// The instruction count last set by `set_instruction_count`
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    /// Returns input that has been supplied but not yet read by the guest
+    pub fn pending_input(&self) -> Vec<u8> {
+        self.pending.iter().copied().collect()
+    }
+
+    /// Replaces the input delivered ahead of the underlying input source
+    pub fn set_pending_input(&mut self, input: &[u8]) {
+        self.pending = input.iter().copied().collect();
+    }
+
+    /// Changes when output to stdout is flushed
+    pub fn set_buffering(&mut self, buffering: Buffering) {
+        self.buffering = buffering;
+    }
+
+    /// Returns everything written so far when output is captured
+    pub fn output(&self) -> &[u8] {
+        match &self.output {
+            Output::Stdout(_) => &[],
+            Output::Capture(buffer) => buffer,
+        }
+    }
+
+    /// Prepare the console for raw input mode. `ctrl_c` only matters for terminal input.
+    pub fn setup(&mut self, ctrl_c: CtrlC) -> io::Result<()> {
+        match &mut self.input {
+            Input::Terminal(platform) => platform.disable_input_buffering(ctrl_c),
+            Input::Pipe(_) | Input::Script | Input::Replay(_) => Ok(()),
+        }
+    }
+
+    /// Restore the console to its original state, flushing any buffered output
     pub fn cleanup(&mut self) -> io::Result<()> {
-        self.platform.restore_input_buffering()
+        let flushed = self.flush();
+        let restored = match &mut self.input {
+            Input::Terminal(platform) => platform.restore_input_buffering(),
+            Input::Pipe(_) | Input::Script | Input::Replay(_) => Ok(()),
+        };
+        restored.and(flushed)
     }
 
-    /// Check if a key is available without blocking
+    /// Check if a key is available. Finding none means the program is waiting
+    /// for input, so buffered output is flushed.
+    ///
+    /// When a program polls in a tight loop, waiting for a key from the terminal
+    /// or a pipe, each empty poll blocks for up to `IDLE_WAIT` rather than
+    /// returning at once, so the loop stops spinning the host CPU. The guest
+    /// still executes every instruction of the loop, just less often; replayed
+    /// and scripted input never wait, so their instruction counts are unchanged.
     pub fn check_key(&mut self) -> io::Result<bool> {
-        self.platform.check_key()
+        if !self.pending.is_empty() {
+            return Ok(true);
+        }
+        let idle = self
+            .last_empty_poll
+            .is_some_and(|last| self.instruction_count.saturating_sub(last) <= IDLE_POLL_DISTANCE);
+
+        self.waited |= idle && matches!(self.input, Input::Terminal(_) | Input::Pipe(_));
+        let available = match &mut self.input {
+            Input::Terminal(platform) if idle => platform.wait_key(Some(IDLE_WAIT))?,
+            Input::Terminal(platform) => platform.check_key()?,
+            Input::Pipe(pipe) if idle => pipe.wait_key(IDLE_WAIT),
+            Input::Pipe(pipe) => pipe.check_key(),
+            Input::Script => false,
+            Input::Replay(events) => events
+                .front()
+                .is_some_and(|event| event.instruction <= self.instruction_count),
+        };
+
+        if available {
+            self.last_empty_poll = None;
+        } else {
+            self.last_empty_poll = Some(self.instruction_count);
+            self.flush()?;
+        }
+        Ok(available)
     }
 
-    /// Read a single key from the keyboard
+    /// Read a single key from the keyboard, flushing output first since this may block
     pub fn read_key(&mut self) -> io::Result<u8> {
-        self.platform.read_key()
+        self.flush()?;
+        let key = match self.pending.pop_front() {
+            Some(key) => key,
+            None => match &mut self.input {
+                Input::Terminal(platform) => {
+                    self.waited = true;
+                    platform.read_key()?
+                }
+                Input::Pipe(pipe) => {
+                    self.waited = true;
+                    pipe.read_key()
+                }
+                Input::Script => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Input script exhausted"));
+                }
+                Input::Replay(events) => match events.pop_front() {
+                    Some(event) => event.key,
+                    None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Input log exhausted")),
+                },
+            },
+        };
+
+        if let Some(recorder) = &mut self.recorder {
+            InputEvent { instruction: self.instruction_count, key }.write_to(recorder)?;
+        }
+        Ok(key)
+    }
+
+    /// Whether the console may have blocked waiting for input since the last call,
+    /// so wall-clock time may have passed without instructions executing
+    pub(crate) fn take_waited(&mut self) -> bool {
+        std::mem::take(&mut self.waited)
     }
 
     /// Write a single character to the console
     pub fn write_char(&mut self, c: u8) -> io::Result<()> {
-        io::stdout().write_all(&[c])?;
-        io::stdout().flush()
+        self.write_bytes(&[c])
     }
 
     /// Write a string to the console
     pub fn write_str(&mut self, s: &str) -> io::Result<()> {
-        io::stdout().write_all(s.as_bytes())?;
-        io::stdout().flush()
+        self.write_bytes(s.as_bytes())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.last_empty_poll = None;
+        match &mut self.output {
+            Output::Stdout(buffer) => {
+                buffer.extend_from_slice(bytes);
+                let newline = self.buffering == Buffering::Line && bytes.contains(&b'\n');
+                if newline || buffer.len() >= BUFFER_LIMIT {
+                    self.flush()?;
+                }
+                Ok(())
+            }
+            Output::Capture(buffer) => {
+                buffer.extend_from_slice(bytes);
+                Ok(())
+            }
+        }
     }
-}
\ No newline at end of file
+
+    /// Writes terminal control output, such as a display frame, straight through
+    /// to stdout. It does not count as program output for idle detection, and is
+    /// dropped when output is captured.
+    pub fn draw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match &mut self.output {
+            Output::Stdout(buffer) => {
+                buffer.extend_from_slice(bytes);
+                self.flush()
+            }
+            Output::Capture(_) => Ok(()),
+        }
+    }
+
+    /// Writes buffered output to stdout
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        match &mut self.output {
+            Output::Stdout(buffer) if !buffer.is_empty() => {
+                let bytes = std::mem::take(buffer);
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(&bytes)?;
+                stdout.flush()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Flushes output that has been buffered for longer than `FLUSH_INTERVAL`.
+    /// `LC3::run` calls this periodically so output shows up while a program
+    /// computes without writing a newline or reading a key.
+    pub fn flush_stale(&mut self) -> io::Result<()> {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Console {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
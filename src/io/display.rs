@@ -0,0 +1,177 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::vm::{Memory, IO_PAGE_START, VIDEO_START};
+
+/// Display width in pixels
+pub const WIDTH: usize = 128;
+/// Display height in pixels
+pub const HEIGHT: usize = 124;
+
+/// Terminal rows the display occupies when drawn with half blocks
+pub const TERMINAL_ROWS: usize = HEIGHT / 2;
+
+/// Image file formats frames can be saved in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum FrameFormat {
+    #[default]
+    Png,
+    /// Binary PPM (P6)
+    Ppm,
+}
+
+impl FrameFormat {
+    /// File extension for the format, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Png => "png",
+            FrameFormat::Ppm => "ppm",
+        }
+    }
+}
+
+/// One frame of the bitmap display: a word per pixel, row by row, each
+/// holding 15-bit colour as `0RRRRRGGGGGBBBBB`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub pixels: Vec<u16>,
+}
+
+impl Frame {
+    /// Captures video memory (xC000-xFDFF)
+    pub fn capture(memory: &Memory) -> Self {
+        let pixels = memory.get_ptr(VIDEO_START)[..(IO_PAGE_START - VIDEO_START) as usize].to_vec();
+        Frame { pixels }
+    }
+
+    /// The 8-bit red, green and blue components of the pixel at (`x`, `y`)
+    pub fn rgb(&self, x: usize, y: usize) -> [u8; 3] {
+        let word = self.pixels[y * WIDTH + x];
+        let expand = |shift: u16| {
+            let value = ((word >> shift) & 0x1F) as u8;
+            (value << 3) | (value >> 2)
+        };
+        [expand(10), expand(5), expand(0)]
+    }
+
+    fn rgb_rows(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        (0..HEIGHT).map(move |y| (0..WIDTH).flat_map(|x| self.rgb(x, y)).collect())
+    }
+
+    /// Encodes the frame as a binary PPM image
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", WIDTH, HEIGHT).into_bytes();
+        for row in self.rgb_rows() {
+            bytes.extend_from_slice(&row);
+        }
+        bytes
+    }
+
+    /// Encodes the frame as an uncompressed PNG image
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut raw = Vec::with_capacity(HEIGHT * (1 + WIDTH * 3));
+        for row in self.rgb_rows() {
+            raw.push(0); // No filter
+            raw.extend_from_slice(&row);
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&(WIDTH as u32).to_be_bytes());
+        header.extend_from_slice(&(HEIGHT as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit RGB, no interlacing
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_chunk(&mut png, b"IHDR", &header);
+        png_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Encodes the frame in the given format
+    pub fn encode(&self, format: FrameFormat) -> Vec<u8> {
+        match format {
+            FrameFormat::Png => self.to_png(),
+            FrameFormat::Ppm => self.to_ppm(),
+        }
+    }
+
+    /// Saves the frame, choosing PPM for a `.ppm` extension and PNG otherwise
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let extension = path.as_ref().extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let format = if extension.eq_ignore_ascii_case("ppm") { FrameFormat::Ppm } else { FrameFormat::Png };
+        fs::write(path, self.encode(format))
+    }
+
+    /// Draws the frame in the top `TERMINAL_ROWS` rows of a terminal, two pixels per
+    /// character cell using upper half blocks with 24-bit colours. The cursor is
+    /// saved and restored around the drawing.
+    pub fn to_ansi(&self) -> String {
+        let mut text = String::from("\x1b7");
+        for row in 0..TERMINAL_ROWS {
+            let _ = write!(text, "\x1b[{};1H", row + 1);
+            let mut colours = None;
+            for x in 0..WIDTH {
+                let cell = (self.rgb(x, row * 2), self.rgb(x, row * 2 + 1));
+                if colours != Some(cell) {
+                    let ([r, g, b], [br, bg, bb]) = cell;
+                    let _ = write!(text, "\x1b[38;2;{};{};{};48;2;{};{};{}m", r, g, b, br, bg, bb);
+                    colours = Some(cell);
+                }
+                text.push('\u{2580}');
+            }
+            text.push_str("\x1b[0m");
+        }
+        text.push_str("\x1b8");
+        text
+    }
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps `data` in a zlib stream of stored (uncompressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none() as u8;
+        let length = block.len() as u16;
+        stream.push(last);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -1,4 +1,12 @@
 /// Console I/O functionality
 pub mod console;
 /// Platform-specific I/O implementations
-pub mod platform;
\ No newline at end of file
+pub mod platform;
+/// Keyboard input from pipes and files
+pub mod pipe;
+/// Keyboard input recording and replay
+pub mod recording;
+/// Bitmap display frames and their image and terminal encodings
+pub mod display;
+/// Serial links backing the UART device
+pub mod serial;
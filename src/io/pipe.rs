@@ -0,0 +1,105 @@
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::time::Duration;
+use std::thread;
+
+/// Key delivered to the guest once the input stream has ended (ASCII EOT, Ctrl-D)
+pub const EOF_KEY: u8 = 0x04;
+
+/// Keyboard input read from a pipe or file rather than a terminal.
+///
+/// A background thread reads the stream so checking for a key never blocks.
+/// Once the stream ends a key is always available and every read returns
+/// `EOF_KEY`.
+pub struct PipeInput {
+    receiver: Receiver<u8>,
+    peeked: Option<u8>,
+    eof: bool,
+}
+
+impl PipeInput {
+    /// Reads keys from the process's standard input
+    pub fn stdin() -> Self {
+        Self::from_reader(io::stdin())
+    }
+
+    /// Reads keys from any byte stream
+    pub fn from_reader<R: Read + Send + 'static>(mut reader: R) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let mut buffer = [0u8; 256];
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        if buffer[..n].iter().any(|&byte| sender.send(byte).is_err()) {
+                            break;
+                        }
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(_) => break,
+                }
+            }
+        });
+
+        PipeInput {
+            receiver,
+            peeked: None,
+            eof: false,
+        }
+    }
+
+    /// Checks if a key (or the end of input) is available without blocking
+    pub fn check_key(&mut self) -> bool {
+        if self.peeked.is_some() || self.eof {
+            return true;
+        }
+        match self.receiver.try_recv() {
+            Ok(byte) => {
+                self.peeked = Some(byte);
+                true
+            }
+            Err(TryRecvError::Empty) => false,
+            Err(TryRecvError::Disconnected) => {
+                self.eof = true;
+                true
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for a key (or the end of input), returning whether one is available
+    pub fn wait_key(&mut self, timeout: Duration) -> bool {
+        if self.peeked.is_some() || self.eof {
+            return true;
+        }
+        match self.receiver.recv_timeout(timeout) {
+            Ok(byte) => {
+                self.peeked = Some(byte);
+                true
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => {
+                self.eof = true;
+                true
+            }
+        }
+    }
+
+    /// Reads the next key, waiting for one if necessary
+    pub fn read_key(&mut self) -> u8 {
+        if let Some(byte) = self.peeked.take() {
+            return byte;
+        }
+        if self.eof {
+            return EOF_KEY;
+        }
+        match self.receiver.recv() {
+            Ok(byte) => byte,
+            Err(_) => {
+                self.eof = true;
+                EOF_KEY
+            }
+        }
+    }
+}
@@ -9,6 +9,8 @@ mod unix;
 pub use self::unix::UnixPlatform as PlatformImpl;
 
 use std::io;
+use std::time::Duration;
+use crate::io::console::CtrlC;
 
 /// Platform abstraction layer that handles platform-specific terminal operations
 pub struct Platform {
@@ -22,9 +24,11 @@ impl Platform {
         }
     }
 
-    /// Puts the terminal in raw mode for immediate character input
-    pub fn disable_input_buffering(&mut self) -> io::Result<()> {
-        self.inner.disable_input_buffering()
+    /// Puts the terminal in raw mode for immediate character input. The original
+    /// mode is restored by `restore_input_buffering`, when the platform is dropped,
+    /// or when the process is interrupted or terminated by a signal.
+    pub fn disable_input_buffering(&mut self, ctrl_c: CtrlC) -> io::Result<()> {
+        self.inner.disable_input_buffering(ctrl_c)
     }
 
     /// Restores the terminal to its original state
@@ -37,8 +41,19 @@ impl Platform {
         self.inner.check_key()
     }
 
-    /// Reads a single key from the keyboard
+    /// Waits up to `timeout` (forever for `None`) for a key, returning whether one is available
+    pub fn wait_key(&mut self, timeout: Option<Duration>) -> io::Result<bool> {
+        self.inner.wait_key(timeout)
+    }
+
+    /// Reads a single key from the keyboard, waiting for one if necessary
     pub fn read_key(&mut self) -> io::Result<u8> {
         self.inner.read_key()
     }
-}
\ No newline at end of file
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,81 +1,187 @@
 use std::io;
-use libc::{self, termios, STDIN_FILENO, TCSANOW, ECHO, ICANON, VMIN, VTIME};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Once, OnceLock};
+use std::time::Duration;
+use libc::{self, termios, STDIN_FILENO, TCSANOW, ECHO, ICANON, ISIG, VMIN, VTIME};
+use crate::io::console::CtrlC;
+
+/// The terminal settings from before raw mode was first entered, for the signal handler
+static ORIGINAL_TERMIOS: OnceLock<termios> = OnceLock::new();
+/// Whether the terminal is currently in raw mode
+static RAW_MODE_ACTIVE: AtomicBool = AtomicBool::new(false);
+static INSTALL_HANDLERS: Once = Once::new();
+
+/// Signals that restore the terminal before terminating the process
+const RESTORING_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
 
 /// Unix-specific implementation of platform I/O operations
 pub struct UnixPlatform {
-    original_termios: Option<termios>,
+    raw_mode: Option<RawMode>,
+}
+
+/// The terminal in raw mode; the original settings are restored when this is dropped,
+/// so an early return or a panic cannot leave the terminal without echo
+struct RawMode {
+    original: termios,
+}
+
+impl RawMode {
+    fn enter(ctrl_c: CtrlC) -> io::Result<Self> {
+        let mut original: termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        ORIGINAL_TERMIOS.get_or_init(|| original);
+        INSTALL_HANDLERS.call_once(install_signal_handlers);
+
+        // Set up raw mode - no echo, no line buffering, and Ctrl-C as a plain key if asked
+        let mut raw = original;
+        raw.c_lflag &= !(ECHO | ICANON);
+        if ctrl_c == CtrlC::Deliver {
+            raw.c_lflag &= !ISIG;
+        }
+        raw.c_cc[VMIN] = 0;  // Don't wait for input
+        raw.c_cc[VTIME] = 0; // No timeout
+
+        RAW_MODE_ACTIVE.store(true, Ordering::SeqCst);
+        if unsafe { libc::tcsetattr(STDIN_FILENO, TCSANOW, &raw) } != 0 {
+            RAW_MODE_ACTIVE.store(false, Ordering::SeqCst);
+            return Err(io::Error::last_os_error());
+        }
+        Ok(RawMode { original })
+    }
+
+    /// Restores the original settings, reporting any failure
+    fn leave(self) -> io::Result<()> {
+        let result = restore(&self.original);
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = restore(&self.original);
+    }
+}
+
+fn restore(original: &termios) -> io::Result<()> {
+    RAW_MODE_ACTIVE.store(false, Ordering::SeqCst);
+    if unsafe { libc::tcsetattr(STDIN_FILENO, TCSANOW, original) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Restores the terminal if needed, then lets the signal terminate the process as usual.
+/// Only async-signal-safe calls are made here.
+extern "C" fn restore_and_reraise(signal: libc::c_int) {
+    if RAW_MODE_ACTIVE.load(Ordering::SeqCst) {
+        if let Some(original) = ORIGINAL_TERMIOS.get() {
+            unsafe { libc::tcsetattr(STDIN_FILENO, TCSANOW, original) };
+        }
+    }
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+fn install_signal_handlers() {
+    let handler = restore_and_reraise as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    for signal in RESTORING_SIGNALS {
+        // Leave signals the process was told to ignore (e.g. by nohup) ignored
+        let previous = unsafe { libc::signal(signal, handler) };
+        if previous == libc::SIG_IGN {
+            unsafe { libc::signal(signal, libc::SIG_IGN) };
+        }
+    }
 }
 
 impl UnixPlatform {
     pub fn new() -> Self {
         UnixPlatform {
-            original_termios: None,
+            raw_mode: None,
         }
     }
 
-    pub fn disable_input_buffering(&mut self) -> io::Result<()> {
-        unsafe {
-            let mut term: termios = std::mem::zeroed();
-            if libc::tcgetattr(STDIN_FILENO, &mut term) != 0 {
-                return Err(io::Error::last_os_error());
-            }
-            
-            self.original_termios = Some(term);
-            
-            // Set up raw mode - no echo, no line buffering
-            let mut raw = term;
-            raw.c_lflag &= !(ECHO | ICANON);
-            raw.c_cc[VMIN] = 0;  // Don't wait for input
-            raw.c_cc[VTIME] = 0; // No timeout
-            
-            if libc::tcsetattr(STDIN_FILENO, TCSANOW, &raw) != 0 {
-                return Err(io::Error::last_os_error());
-            }
+    pub fn disable_input_buffering(&mut self, ctrl_c: CtrlC) -> io::Result<()> {
+        if self.raw_mode.is_none() {
+            self.raw_mode = Some(RawMode::enter(ctrl_c)?);
         }
         Ok(())
     }
 
     pub fn restore_input_buffering(&mut self) -> io::Result<()> {
-        if let Some(term) = self.original_termios {
-            unsafe {
-                if libc::tcsetattr(STDIN_FILENO, TCSANOW, &term) != 0 {
-                    return Err(io::Error::last_os_error());
-                }
-            }
+        match self.raw_mode.take() {
+            Some(raw_mode) => raw_mode.leave(),
+            None => Ok(()),
         }
-        Ok(())
     }
 
     pub fn check_key(&mut self) -> io::Result<bool> {
+        self.wait_key(Some(Duration::ZERO))
+    }
+
+    /// Waits up to `timeout` (forever for `None`) for a key to become readable.
+    /// A signal interrupting the wait counts as no key.
+    pub fn wait_key(&mut self, timeout: Option<Duration>) -> io::Result<bool> {
         unsafe {
             let mut readfds: libc::fd_set = std::mem::zeroed();
             libc::FD_ZERO(&mut readfds);
             libc::FD_SET(STDIN_FILENO, &mut readfds);
-            
-            let mut timeout: libc::timeval = std::mem::zeroed();
-            timeout.tv_sec = 0;
-            timeout.tv_usec = 0;
-            
-            let result = libc::select(STDIN_FILENO + 1, &mut readfds, std::ptr::null_mut(), std::ptr::null_mut(), &mut timeout);
-            
+
+            let mut tv: libc::timeval = std::mem::zeroed();
+            let tv_ptr = match timeout {
+                Some(timeout) => {
+                    tv.tv_sec = timeout.as_secs() as libc::time_t;
+                    tv.tv_usec = timeout.subsec_micros() as libc::suseconds_t;
+                    &mut tv as *mut libc::timeval
+                }
+                None => std::ptr::null_mut(),
+            };
+
+            let result = libc::select(STDIN_FILENO + 1, &mut readfds, std::ptr::null_mut(), std::ptr::null_mut(), tv_ptr);
+
             if result == -1 {
-                return Err(io::Error::last_os_error());
+                let err = io::Error::last_os_error();
+                return if err.kind() == io::ErrorKind::Interrupted { Ok(false) } else { Err(err) };
             }
-            
+
             Ok(result > 0 && libc::FD_ISSET(STDIN_FILENO, &readfds))
         }
     }
 
+    /// Reads one key, blocking until one arrives. Raw mode sets VMIN to 0 so
+    /// `read` itself never waits; `wait_key` does the waiting instead.
     pub fn read_key(&mut self) -> io::Result<u8> {
-        let mut buffer = [0u8; 1];
-        let result = unsafe {
-            libc::read(STDIN_FILENO, buffer.as_mut_ptr() as *mut libc::c_void, 1)
-        };
-        
-        if result <= 0 {
-            Err(io::Error::new(io::ErrorKind::Other, "Failed to read character"))
-        } else {
-            Ok(buffer[0])
+        loop {
+            if !self.wait_key(None)? {
+                continue;
+            }
+
+            let mut buffer = [0u8; 1];
+            let result = unsafe {
+                libc::read(STDIN_FILENO, buffer.as_mut_ptr() as *mut libc::c_void, 1)
+            };
+
+            match result {
+                1 => return Ok(buffer[0]),
+                // Readable with nothing to read means the terminal has gone away
+                0 => return Err(io::Error::other("Failed to read character")),
+                _ => {
+                    let err = io::Error::last_os_error();
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                }
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+impl Default for UnixPlatform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,71 +1,146 @@
 use std::io;
-use winapi::um::consoleapi::{GetConsoleMode, SetConsoleMode};
-use winapi::um::wincon::{ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT, FlushConsoleInputBuffer};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Once;
+use std::time::Duration;
+use winapi::um::consoleapi::{GetConsoleMode, SetConsoleCtrlHandler, SetConsoleMode};
+use winapi::um::wincon::{ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT, FlushConsoleInputBuffer};
 use winapi::um::synchapi::WaitForSingleObject;
 use winapi::um::winnt::HANDLE;
 use winapi::um::handleapi::INVALID_HANDLE_VALUE;
-use winapi::um::winbase::WAIT_OBJECT_0;
-use winapi::shared::minwindef::DWORD;
+use winapi::um::winbase::{INFINITE, WAIT_OBJECT_0};
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE};
 use winapi::um::processenv::GetStdHandle;
 use winapi::um::winbase::STD_INPUT_HANDLE;
+use crate::io::console::CtrlC;
 
-/// Windows-specific implementation of platform I/O operations
+/// The console mode and handle to restore from the Ctrl-C handler
+static ORIGINAL_MODE: AtomicU32 = AtomicU32::new(0);
+static STDIN_HANDLE: AtomicUsize = AtomicUsize::new(0);
+/// Whether the console is currently in raw mode
+static RAW_MODE_ACTIVE: AtomicBool = AtomicBool::new(false);
+static INSTALL_HANDLER: Once = Once::new();
+
+/// Windows-specific implementation of platform I/O operations. Handles are
+/// kept as addresses, like `STDIN_HANDLE`, so the platform is `Send`.
 pub struct WindowsPlatform {
-    stdin_handle: HANDLE,
-    old_mode: DWORD,
+    stdin_handle: usize,
+    raw_mode: Option<RawMode>,
 }
 
-impl WindowsPlatform {
-    pub fn new() -> Self {
-        WindowsPlatform {
-            stdin_handle: INVALID_HANDLE_VALUE,
-            old_mode: 0,
-        }
-    }
+/// The console in raw mode; the original mode is restored when this is dropped,
+/// so an early return or a panic cannot leave the console without echo
+struct RawMode {
+    handle: usize,
+    original: DWORD,
+}
 
-    pub fn disable_input_buffering(&mut self) -> io::Result<()> {
+impl RawMode {
+    fn enter(handle: HANDLE, ctrl_c: CtrlC) -> io::Result<Self> {
+        // Save the current console mode
+        let mut original: DWORD = 0;
         unsafe {
-            // Get the standard input handle
-            self.stdin_handle = GetStdHandle(STD_INPUT_HANDLE);
-            if self.stdin_handle == INVALID_HANDLE_VALUE {
+            if GetConsoleMode(handle, &mut original) == 0 {
                 return Err(io::Error::last_os_error());
             }
+            ORIGINAL_MODE.store(original, Ordering::SeqCst);
+            STDIN_HANDLE.store(handle as usize, Ordering::SeqCst);
+            INSTALL_HANDLER.call_once(|| {
+                SetConsoleCtrlHandler(Some(restore_on_ctrl), TRUE);
+            });
 
-            // Save the current console mode
-            let mut old_mode: DWORD = 0;
-            if GetConsoleMode(self.stdin_handle, &mut old_mode) == 0 {
-                return Err(io::Error::last_os_error());
+            // Disable line input and echoing, and Ctrl-C processing if the guest should see it
+            let mut new_mode = original & !(ENABLE_ECHO_INPUT | ENABLE_LINE_INPUT);
+            if ctrl_c == CtrlC::Deliver {
+                new_mode &= !ENABLE_PROCESSED_INPUT;
             }
-            self.old_mode = old_mode;
-
-            // Disable line input and echoing
-            let new_mode = old_mode & !(ENABLE_ECHO_INPUT | ENABLE_LINE_INPUT);
-            if SetConsoleMode(self.stdin_handle, new_mode) == 0 {
+            RAW_MODE_ACTIVE.store(true, Ordering::SeqCst);
+            if SetConsoleMode(handle, new_mode) == 0 {
+                RAW_MODE_ACTIVE.store(false, Ordering::SeqCst);
                 return Err(io::Error::last_os_error());
             }
 
             // Clear any pending input
-            if FlushConsoleInputBuffer(self.stdin_handle) == 0 {
-                return Err(io::Error::last_os_error());
+            if FlushConsoleInputBuffer(handle) == 0 {
+                let err = io::Error::last_os_error();
+                let _ = restore(handle, original);
+                return Err(err);
             }
         }
+        Ok(RawMode { handle: handle as usize, original })
+    }
+
+    /// Restores the original mode, reporting any failure
+    fn leave(self) -> io::Result<()> {
+        let result = restore(self.handle as HANDLE, self.original);
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = restore(self.handle as HANDLE, self.original);
+    }
+}
+
+fn restore(handle: HANDLE, original: DWORD) -> io::Result<()> {
+    RAW_MODE_ACTIVE.store(false, Ordering::SeqCst);
+    if unsafe { SetConsoleMode(handle, original) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Restores the console on Ctrl-C or Ctrl-Break, then lets the default handler end the process
+unsafe extern "system" fn restore_on_ctrl(_ctrl_type: DWORD) -> BOOL {
+    if RAW_MODE_ACTIVE.load(Ordering::SeqCst) {
+        SetConsoleMode(STDIN_HANDLE.load(Ordering::SeqCst) as HANDLE, ORIGINAL_MODE.load(Ordering::SeqCst));
+    }
+    FALSE
+}
+
+impl WindowsPlatform {
+    pub fn new() -> Self {
+        WindowsPlatform {
+            stdin_handle: INVALID_HANDLE_VALUE as usize,
+            raw_mode: None,
+        }
+    }
+
+    pub fn disable_input_buffering(&mut self, ctrl_c: CtrlC) -> io::Result<()> {
+        if self.raw_mode.is_some() {
+            return Ok(());
+        }
+
+        // Get the standard input handle
+        self.stdin_handle = unsafe { GetStdHandle(STD_INPUT_HANDLE) } as usize;
+        if self.stdin_handle == INVALID_HANDLE_VALUE as usize {
+            return Err(io::Error::last_os_error());
+        }
+        self.raw_mode = Some(RawMode::enter(self.stdin_handle as HANDLE, ctrl_c)?);
         Ok(())
     }
 
     pub fn restore_input_buffering(&mut self) -> io::Result<()> {
-        unsafe {
-            if self.stdin_handle != INVALID_HANDLE_VALUE {
-                if SetConsoleMode(self.stdin_handle, self.old_mode) == 0 {
-                    return Err(io::Error::last_os_error());
-                }
-            }
+        match self.raw_mode.take() {
+            Some(raw_mode) => raw_mode.leave(),
+            None => Ok(()),
         }
-        Ok(())
     }
 
     pub fn check_key(&mut self) -> io::Result<bool> {
+        self.wait_key(Some(Duration::ZERO))
+    }
+
+    /// Waits up to `timeout` (forever for `None`) for a key to become readable.
+    /// Console events other than key presses end the wait early without a key.
+    pub fn wait_key(&mut self, timeout: Option<Duration>) -> io::Result<bool> {
+        let millis = match timeout {
+            Some(timeout) => timeout.as_millis().min(INFINITE as u128 - 1) as DWORD,
+            None => INFINITE,
+        };
         unsafe {
-            let result = WaitForSingleObject(self.stdin_handle, 0);
+            let result = WaitForSingleObject(self.stdin_handle as HANDLE, millis);
             if result == WAIT_OBJECT_0 {
                 extern "C" {
                     fn _kbhit() -> i32;
@@ -83,9 +158,15 @@ impl WindowsPlatform {
         }
         let c = unsafe { getchar() };
         if c == -1 {
-            Err(io::Error::new(io::ErrorKind::Other, "Failed to read character"))
+            Err(io::Error::other("Failed to read character"))
         } else {
             Ok(c as u8)
         }
     }
-}
\ No newline at end of file
+}
+
+impl Default for WindowsPlatform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
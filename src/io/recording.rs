@@ -0,0 +1,52 @@
+use std::io::{self, BufRead, Write};
+
+/// First line of every input log
+pub const INPUT_LOG_HEADER: &str = "# lc3-vm input log v1";
+
+/// A key delivered to the guest and the instruction count at which it was read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    pub instruction: u64,
+    pub key: u8,
+}
+
+impl InputEvent {
+    /// Writes the event as one log line: `<instruction count> x<key>`
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{} x{:02X}", self.instruction, self.key)
+    }
+}
+
+/// Parses an input log, skipping blank lines and `#` comments
+pub fn read_input_log<R: BufRead>(reader: R) -> io::Result<Vec<InputEvent>> {
+    let mut events = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let text = line.split('#').next().unwrap_or("").trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid input log entry on line {}: {}", index + 1, line),
+            )
+        };
+        let mut fields = text.split_whitespace();
+        let instruction = fields.next().and_then(|n| n.parse().ok()).ok_or_else(invalid)?;
+        let key = fields
+            .next()
+            .and_then(|k| k.strip_prefix('x'))
+            .and_then(|k| u8::from_str_radix(k, 16).ok())
+            .ok_or_else(invalid)?;
+        if fields.next().is_some() {
+            return Err(invalid());
+        }
+
+        events.push(InputEvent { instruction, key });
+    }
+
+    Ok(events)
+}
@@ -0,0 +1,243 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+/// What the other end of a serial link is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerialBackend {
+    /// Bytes are read from `input`, which may be a FIFO, and appended to `output`
+    Files { input: PathBuf, output: PathBuf },
+    /// Connects to a Unix socket another program is listening on
+    #[cfg(unix)]
+    Connect(PathBuf),
+    /// Creates a Unix socket and serves the first program to connect
+    #[cfg(unix)]
+    Listen(PathBuf),
+    /// Creates a pseudo-terminal in raw mode and a symbolic link to it at the
+    /// given path, for terminal programs and scripts to open
+    #[cfg(unix)]
+    Pty(PathBuf),
+}
+
+impl FromStr for SerialBackend {
+    type Err = String;
+
+    /// Parses `file:IN,OUT`, `connect:SOCKET`, `listen:SOCKET` or `pty:LINK`
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (kind, path) = text.split_once(':').ok_or("expected file:IN,OUT, connect:PATH, listen:PATH or pty:LINK")?;
+        match kind {
+            "file" => {
+                let (input, output) = path.split_once(',').ok_or("expected file:IN,OUT")?;
+                Ok(SerialBackend::Files { input: input.into(), output: output.into() })
+            }
+            #[cfg(unix)]
+            "connect" => Ok(SerialBackend::Connect(path.into())),
+            #[cfg(unix)]
+            "listen" => Ok(SerialBackend::Listen(path.into())),
+            #[cfg(unix)]
+            "pty" => Ok(SerialBackend::Pty(path.into())),
+            #[cfg(not(unix))]
+            "connect" | "listen" | "pty" => Err(format!("{} links are not supported on this platform", kind)),
+            _ => Err(format!("Unknown serial link: {}", kind)),
+        }
+    }
+}
+
+type Writer = Box<dyn Write + Send>;
+
+/// An open serial link. A background thread reads from the other end, so
+/// checking for a byte never blocks; writes go straight through.
+pub struct SerialLink {
+    receiver: Receiver<u8>,
+    peeked: Option<u8>,
+    closed: bool,
+    writer: Option<Writer>,
+    /// Delivers the writer once a listening socket has accepted a connection
+    pending_writer: Option<Receiver<Writer>>,
+    /// Bytes written before the other end connected
+    unsent: Vec<u8>,
+    /// Keeps the pseudo-terminal open while nothing else has it open, so
+    /// reading it does not fail between the programs that use it
+    _keep_open: Option<File>,
+}
+
+impl SerialLink {
+    pub fn open(backend: &SerialBackend) -> io::Result<Self> {
+        match backend {
+            SerialBackend::Files { input, output } => {
+                // Opening a FIFO waits for a writer, so only check the input exists here
+                std::fs::metadata(input)?;
+                let output = OpenOptions::new().append(true).create(true).open(output)?;
+                Ok(Self::new(spawn_file_reader(input.clone()), Some(Box::new(output)), None))
+            }
+            #[cfg(unix)]
+            SerialBackend::Connect(path) => {
+                let stream = std::os::unix::net::UnixStream::connect(path)?;
+                Ok(Self::new(spawn_reader(stream.try_clone()?), Some(Box::new(stream)), None))
+            }
+            #[cfg(unix)]
+            SerialBackend::Listen(path) => listen(path),
+            #[cfg(unix)]
+            SerialBackend::Pty(link) => {
+                let (master, slave) = pty::open(link)?;
+                Ok(Self::new(spawn_reader(master.try_clone()?), Some(Box::new(master)), Some(slave)))
+            }
+        }
+    }
+
+    fn new(receiver: Receiver<u8>, writer: Option<Writer>, keep_open: Option<File>) -> Self {
+        SerialLink { receiver, peeked: None, closed: false, writer, pending_writer: None, unsent: Vec::new(), _keep_open: keep_open }
+    }
+
+    /// Whether a byte is waiting to be read
+    pub fn poll(&mut self) -> bool {
+        if self.peeked.is_none() && !self.closed {
+            match self.receiver.try_recv() {
+                Ok(byte) => self.peeked = Some(byte),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.closed = true,
+            }
+        }
+        self.peeked.is_some()
+    }
+
+    /// Whether the other end has closed the link and every byte it sent has been read
+    pub fn closed(&mut self) -> bool {
+        !self.poll() && self.closed
+    }
+
+    /// Takes the next byte, if one is waiting
+    pub fn take(&mut self) -> Option<u8> {
+        self.poll();
+        self.peeked.take()
+    }
+
+    /// Sends a byte, holding it until the other end connects if it has not yet
+    pub fn send(&mut self, byte: u8) -> io::Result<()> {
+        if self.writer.is_none() {
+            if let Some(writer) = self.pending_writer.as_ref().and_then(|pending| pending.try_recv().ok()) {
+                self.writer = Some(writer);
+                self.pending_writer = None;
+            }
+        }
+        self.unsent.push(byte);
+        if let Some(writer) = &mut self.writer {
+            // Bytes that fail to send are dropped rather than sent again with the next one
+            let unsent = std::mem::take(&mut self.unsent);
+            writer.write_all(&unsent)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads `reader` on a background thread until it ends, passing on every byte
+#[cfg(unix)]
+fn spawn_reader<R: Read + Send + 'static>(reader: R) -> Receiver<u8> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || forward(reader, sender));
+    receiver
+}
+
+/// Opens `path` on a background thread and reads it until it ends, so a FIFO
+/// does not hold up the machine until another program opens it for writing
+fn spawn_file_reader(path: PathBuf) -> Receiver<u8> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        if let Ok(file) = File::open(path) {
+            forward(file, sender);
+        }
+    });
+    receiver
+}
+
+/// Sends every byte read from `reader` until it ends or nobody is listening
+fn forward<R: Read>(mut reader: R, sender: Sender<u8>) {
+    let mut buffer = [0u8; 256];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => {
+                if buffer[..n].iter().any(|&byte| sender.send(byte).is_err()) {
+                    break;
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn listen(path: &std::path::Path) -> io::Result<SerialLink> {
+    use std::fs;
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    // A socket left behind by an earlier run would make binding fail
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+
+    let (sender, receiver) = mpsc::channel();
+    let (writers, pending_writer) = mpsc::channel::<Writer>();
+    thread::spawn(move || {
+        let Ok((stream, _)) = listener.accept() else { return };
+        let Ok(writer) = stream.try_clone() else { return };
+        if writers.send(Box::new(writer)).is_ok() {
+            forward(stream, sender);
+        }
+    });
+
+    let mut link = SerialLink::new(receiver, None, None);
+    link.pending_writer = Some(pending_writer);
+    Ok(link)
+}
+
+#[cfg(unix)]
+mod pty {
+    use std::ffi::CStr;
+    use std::fs::{self, File, OpenOptions};
+    use std::io;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::path::Path;
+
+    /// Opens a pseudo-terminal, puts it in raw mode and links `link` to its
+    /// terminal device. Returns the master side and the terminal device.
+    pub fn open(link: &Path) -> io::Result<(File, File)> {
+        let fd = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let master = unsafe { File::from_raw_fd(fd) };
+        if unsafe { libc::grantpt(fd) } != 0 || unsafe { libc::unlockpt(fd) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let name = unsafe { libc::ptsname(fd) };
+        if name.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let name = unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned();
+
+        let slave = OpenOptions::new().read(true).write(true).custom_flags(libc::O_NOCTTY).open(&name)?;
+        let mut settings: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(slave.as_raw_fd(), &mut settings) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        unsafe { libc::cfmakeraw(&mut settings) };
+        if unsafe { libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &settings) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if fs::symlink_metadata(link).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            fs::remove_file(link)?;
+        }
+        std::os::unix::fs::symlink(&name, link)?;
+        Ok((master, slave))
+    }
+}
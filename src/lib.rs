@@ -2,5 +2,15 @@
 pub mod vm;
 /// I/O subsystem
 pub mod io;
+/// Program images and their on-disk formats
+pub mod image;
+/// Two-pass assembler for LC-3 assembly source
+pub mod asm;
+/// Interactive debugger
+pub mod debugger;
+/// Autograding against declarative test specifications
+pub mod grader;
+/// Several machines linked by message ports and shared memory
+pub mod cluster;
 
 pub use vm::LC3;
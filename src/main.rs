@@ -1,17 +1,609 @@
 use lc3_vm::*;
-use std::io;
+use lc3_vm::asm::{self, parse_word};
+use lc3_vm::debugger::Debugger;
+use lc3_vm::grader::{self, Spec};
+use lc3_vm::image::Image;
+use lc3_vm::io::console::{Console, CtrlC};
+use lc3_vm::io::display::FrameFormat;
+use lc3_vm::io::pipe::PipeInput;
+use lc3_vm::io::recording::read_input_log;
+use lc3_vm::io::serial::SerialBackend;
+use lc3_vm::vm::{condition_name, file_trap_name, is_snapshot, Checkpoint, CycleModel, DisplayConfig, Engine, ExtendedTrap, Fault, FromU16, Register, StopReason, TrapCode, UartConfig, MEMORY_SIZE};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
-fn main() -> io::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+/// Process exit codes, one per way a run can end
+const EXIT_HALTED: u8 = 0;
+const EXIT_IO_ERROR: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_FAULT: u8 = 3;
+const EXIT_INSTRUCTION_LIMIT: u8 = 4;
+const EXIT_TIMEOUT: u8 = 5;
+const EXIT_CASES_FAILED: u8 = 6;
 
-    if args.len() < 2 {
-        eprintln!("Usage: {} <image-file>", args[0]);
-        return Ok(());
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0  the program halted (or the command succeeded)
+  1  an I/O error occurred
+  2  invalid command line, or assembly errors
+  3  the program executed an illegal instruction or unknown trap
+  4  the instruction limit was reached
+  5  the timeout expired
+  6  grade: at least one case failed
+
+Without a subcommand, `run` is assumed: `lc3-vm program.obj`.";
+
+#[derive(Parser)]
+#[command(name = "lc3-vm", version, about = "LC-3 virtual machine and toolchain", after_help = EXIT_STATUS_HELP)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a program (the default subcommand)
+    Run(Box<RunArgs>),
+    /// Assemble LC-3 assembly source into an image
+    Asm {
+        source: PathBuf,
+        /// Output image; the format follows the extension (default: the source with .obj)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Also write the symbol table, one `LABEL xADDR` per line
+        #[arg(long)]
+        symbols: Option<PathBuf>,
+    },
+    /// Disassemble an image into source that assembles back to it
+    Disasm {
+        /// Image (.obj, .hex or .bin) or assembly source
+        image: PathBuf,
+    },
+    /// Step through a program interactively
+    Debug {
+        #[command(flatten)]
+        machine: MachineArgs,
+        /// Take the program's keyboard input from FILE; without it the program reads
+        /// the terminal, or sees the end of input when stdin is not a terminal
+        #[arg(long, value_name = "FILE")]
+        input: Option<PathBuf>,
+    },
+    /// Print the registers and non-zero memory of an image or snapshot
+    Dump {
+        file: PathBuf,
+        /// First address to print
+        #[arg(long, value_parser = parse_word, default_value = "x0000")]
+        from: u16,
+        /// Last address to print
+        #[arg(long, value_parser = parse_word, default_value = "xFFFF")]
+        to: u16,
+    },
+    /// Grade a program against a JSON specification of test cases
+    Grade {
+        spec: PathBuf,
+        /// Image to grade instead of the one named in the spec
+        image: Option<PathBuf>,
+        /// Also write the report as JSON (`-` prints only the JSON)
+        #[arg(long)]
+        json: Option<PathBuf>,
+    },
+    /// Convert an image between the .obj, .hex and .bin formats
+    Convert { input: PathBuf, output: PathBuf },
+}
+
+/// The program to load and the state to start it in
+#[derive(clap::Args)]
+struct MachineArgs {
+    /// Image (.obj, .hex or .bin), assembly source (.asm) or snapshot
+    program: PathBuf,
+    /// Start executing at this address instead of x3000
+    #[arg(long, value_parser = parse_word)]
+    pc: Option<u16>,
+    /// Set a register before starting, e.g. `--reg R1=x10`
+    #[arg(long = "reg", value_name = "REG=VALUE", value_parser = parse_register_preset)]
+    registers: Vec<(Register, u16)>,
+    /// Load another image (or assembly source) into memory before starting
+    #[arg(long, value_name = "FILE")]
+    load: Vec<PathBuf>,
+    /// Set consecutive memory words before starting, e.g. `--mem x4000=1,2,#-3`
+    #[arg(long, value_name = "ADDR=VALUES", value_parser = parse_memory_preset)]
+    mem: Vec<(u16, Vec<u16>)>,
+    /// Attach a random number generator at xFE10, seeded from the clock
+    #[arg(long)]
+    random: bool,
+    /// Attach the random number generator with a fixed seed, for reproducible runs
+    #[arg(long, value_name = "SEED")]
+    seed: Option<u64>,
+    /// Attach an uptime and time-of-day clock at xFE14-xFE1C
+    #[arg(long)]
+    clock: bool,
+    /// Enable the file traps (x30-x34), confined to this directory
+    #[arg(long, value_name = "DIR")]
+    sandbox: Option<PathBuf>,
+    /// Enable the extended console traps: PRINTD x26, READD x27, CLEAR x28 and CURSOR x29
+    #[arg(long)]
+    extended_traps: bool,
+    /// Give an extended trap another vector, enabling it, e.g. `--trap printd=x40`
+    #[arg(long = "trap", value_name = "NAME=VECTOR", value_parser = parse_trap_assignment)]
+    traps: Vec<(ExtendedTrap, u8)>,
+    /// Attach a UART linked to `file:IN,OUT`, `connect:SOCKET`, `listen:SOCKET` or `pty:LINK`
+    #[arg(long, value_name = "LINK")]
+    uart: Option<SerialBackend>,
+    /// Address of the UART status register; the data register follows at +2
+    #[arg(long, value_name = "ADDR", default_value = "xFE20", value_parser = parse_word)]
+    uart_address: u16,
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    #[command(flatten)]
+    machine: MachineArgs,
+    /// Where keyboard input comes from: auto, terminal, stdin or a file
+    #[arg(long, value_name = "SOURCE", default_value = "auto", value_parser = parse_input_source)]
+    input: InputSource,
+    /// Read input from stdin without changing terminal modes (same as --input stdin)
+    #[arg(long, conflicts_with = "input")]
+    headless: bool,
+    /// Log every key the program reads, with the instruction count it was read at
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+    /// What Ctrl-C does when input comes from the terminal
+    #[arg(long, value_name = "ACTION", value_enum, default_value_t = CtrlC::Kill)]
+    ctrl_c: CtrlC,
+    /// How instructions are executed
+    #[arg(long, value_name = "ENGINE", value_enum, default_value_t = Engine::Cached)]
+    engine: Engine,
+    /// Take keyboard input from a log written by --record
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["input", "headless"])]
+    replay: Option<PathBuf>,
+    /// Periodically save a snapshot of the machine to FILE
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+    /// Instructions between checkpoints
+    #[arg(long, value_name = "N", default_value_t = 1_000_000, value_parser = clap::value_parser!(u64).range(1..))]
+    checkpoint_every: u64,
+    /// Stop after executing N instructions
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_instructions: Option<u64>,
+    /// Stop after SECS seconds of wall-clock time
+    #[arg(long, value_name = "SECS", value_parser = parse_timeout)]
+    timeout: Option<Duration>,
+    /// Write a line per executed instruction to FILE (`-` for stderr)
+    #[arg(long, value_name = "FILE")]
+    trace: Option<PathBuf>,
+    /// Print instruction, trap and device counters and the speed to stderr when the run ends
+    #[arg(long)]
+    stats: bool,
+    /// Count clock cycles and add them to the --stats report
+    #[arg(long, requires = "stats")]
+    cycles: bool,
+    /// Cycles each memory access takes in the cycle count (implies --cycles)
+    #[arg(long, value_name = "N", requires = "stats")]
+    memory_latency: Option<u64>,
+    /// Cycles each device register access takes in the cycle count (implies --cycles)
+    #[arg(long, value_name = "N", requires = "stats")]
+    mmio_latency: Option<u64>,
+    /// Treat xC000-xFDFF as a 128x124 display and draw it at the top of the
+    /// terminal when stdout is one
+    #[arg(long)]
+    display: bool,
+    /// Save the display as numbered images in DIR whenever it changes
+    #[arg(long, value_name = "DIR")]
+    frames: Option<PathBuf>,
+    /// Image format for --frames
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t = FrameFormat::Png)]
+    frame_format: FrameFormat,
+    /// Instructions between checks for a changed frame to save
+    #[arg(long, value_name = "N", default_value_t = 100_000, value_parser = clap::value_parser!(u64).range(1..))]
+    frame_every: u64,
+    /// How the exit status is chosen when the program halts
+    #[arg(long, value_enum, default_value_t = ExitPolicy::Status)]
+    exit_code: ExitPolicy,
+}
+
+/// Keyboard input sources for `run`
+#[derive(Clone)]
+enum InputSource {
+    /// The terminal when stdin is one, stdin otherwise
+    Auto,
+    Terminal,
+    Stdin,
+    File(PathBuf),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExitPolicy {
+    /// 0 on HALT; see the exit status table for other outcomes
+    Status,
+    /// The low byte of R0 on HALT, so programs can report their own result
+    R0,
+}
+
+fn parse_input_source(text: &str) -> Result<InputSource, String> {
+    Ok(match text {
+        "auto" => InputSource::Auto,
+        "terminal" => InputSource::Terminal,
+        "stdin" | "-" => InputSource::Stdin,
+        path => InputSource::File(PathBuf::from(path)),
+    })
+}
+
+fn parse_register_preset(text: &str) -> Result<(Register, u16), String> {
+    let (name, value) = text.split_once('=').ok_or("expected REG=VALUE")?;
+    Ok((name.trim().parse()?, parse_word(value)?))
+}
+
+impl MachineArgs {
+    /// Extended traps by vector, as `--extended-traps` and `--trap` assign them
+    fn extended_traps(&self) -> BTreeMap<u8, ExtendedTrap> {
+        let mut traps = BTreeMap::new();
+        if self.extended_traps {
+            traps = ExtendedTrap::DEFAULTS.into_iter().collect();
+        }
+        for &(trap, vector) in &self.traps {
+            traps.retain(|_, assigned| *assigned != trap);
+            traps.insert(vector, trap);
+        }
+        traps
     }
 
-    let mut vm: LC3 = LC3::new();
-    vm.read_image_file(&args[1])?;
-    vm.run()?;
+    /// Rejects an extended trap that would hide one of the file traps `--sandbox` enables
+    fn check(&self) -> Result<(), String> {
+        if self.sandbox.is_none() {
+            return Ok(());
+        }
+        for (vector, trap) in self.extended_traps() {
+            if let Some(name) = file_trap_name(vector) {
+                return Err(format!("{:?} at x{:02X} would hide the file trap {}", trap, vector, name));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_memory_preset(text: &str) -> Result<(u16, Vec<u16>), String> {
+    let (address, values) = text.split_once('=').ok_or("expected ADDR=VALUE[,VALUE...]")?;
+    let words = values.split(',').map(parse_word).collect::<Result<_, _>>()?;
+    Ok((parse_word(address)?, words))
+}
 
+fn parse_trap_assignment(text: &str) -> Result<(ExtendedTrap, u8), String> {
+    let (name, vector) = text.split_once('=').ok_or("expected NAME=VECTOR")?;
+    let vector = u8::try_from(parse_word(vector)?).map_err(|_| "trap vectors range from x00 to xFF".to_string())?;
+    if TrapCode::try_from(vector as u16).is_ok() {
+        return Err(format!("x{:02X} is a standard trap", vector));
+    }
+    Ok((name.trim().parse()?, vector))
+}
+
+fn parse_timeout(text: &str) -> Result<Duration, String> {
+    let secs: f64 = text.parse().map_err(|_| "expected a number of seconds".to_string())?;
+    Duration::try_from_secs_f64(secs).map_err(|err| err.to_string())
+}
+
+/// Inserts `run` when the first argument is not a subcommand, so that
+/// `lc3-vm [options] program.obj` keeps working
+fn with_default_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+    let command = Cli::command();
+    if let Some(first) = args.get(1).and_then(|arg| arg.to_str()) {
+        let is_subcommand = command.get_subcommands().any(|sub| sub.get_name() == first) || first == "help";
+        let is_global = matches!(first, "-h" | "--help" | "-V" | "--version");
+        if !is_subcommand && !is_global {
+            args.insert(1, OsString::from("run"));
+        }
+    }
+    args
+}
+
+/// Ways a run can fail
+enum Failure {
+    Io(io::Error),
+    /// A guest fault and the address of the faulting instruction
+    Fault(Fault, u16),
+}
+
+impl From<io::Error> for Failure {
+    fn from(err: io::Error) -> Self {
+        Failure::Io(err)
+    }
+}
+
+/// Loads an image, assembly source or snapshot, returning any symbols it defines.
+/// Images and sources leave the registers alone; snapshots restore them.
+fn load_program(vm: &mut LC3, path: &Path) -> io::Result<BTreeMap<String, u16>> {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("asm")) {
+        let assembly = assemble_file(path)?;
+        vm.load(&assembly.image);
+        return Ok(assembly.symbols);
+    }
+
+    if is_snapshot_path(path)? {
+        vm.load_snapshot_file(path)?;
+    } else {
+        vm.load(&Image::read_file(path)?);
+    }
+    Ok(BTreeMap::new())
+}
+
+fn assemble_file(path: &Path) -> io::Result<asm::Assembly> {
+    asm::assemble(&fs::read_to_string(path)?)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), err)))
+}
+
+/// Attaches the optional devices, loads the program and extra images, then applies the presets
+fn prepare(vm: &mut LC3, machine: &MachineArgs) -> io::Result<BTreeMap<String, u16>> {
+    vm.config.random = machine.random || machine.seed.is_some();
+    vm.config.random_seed = machine.seed;
+    vm.config.clock = machine.clock;
+    vm.config.sandbox = machine.sandbox.clone();
+    vm.config.uart = machine.uart.clone().map(|backend| UartConfig { address: machine.uart_address, backend });
+    vm.config.extended_traps = machine.extended_traps();
+    vm.attach_configured_devices()?;
+
+    let symbols = load_program(vm, &machine.program)?;
+    for path in &machine.load {
+        load_program(vm, path)?;
+    }
+    for (address, words) in &machine.mem {
+        for (offset, &word) in words.iter().enumerate() {
+            vm.memory.write(address.wrapping_add(offset as u16), word);
+        }
+    }
+    for &(register, value) in &machine.registers {
+        vm.registers.set(register, value);
+    }
+    if let Some(pc) = machine.pc {
+        vm.registers.set(Register::PC, pc);
+    }
+    Ok(symbols)
+}
+
+fn run(args: RunArgs) -> Result<(StopReason, u16), Failure> {
+    let input = if args.headless { InputSource::Stdin } else { args.input };
+    let console = match (&args.replay, input) {
+        (Some(path), _) => Console::replay(read_input_log(BufReader::new(File::open(path)?))?),
+        (None, InputSource::Auto) if !io::stdin().is_terminal() => Console::headless(),
+        (None, InputSource::Auto | InputSource::Terminal) => Console::new(),
+        (None, InputSource::Stdin) => Console::headless(),
+        (None, InputSource::File(path)) => Console::piped(PipeInput::from_reader(File::open(path)?)),
+    };
+
+    let mut vm: LC3 = LC3::with_console(console);
+    if let Some(path) = &args.record {
+        vm.console_mut().record_to(Box::new(File::create(path)?))?;
+    }
+    match &args.trace {
+        Some(path) if path.as_os_str() == "-" => vm.trace_to(Box::new(io::stderr())),
+        Some(path) => vm.trace_to(Box::new(io::BufWriter::new(File::create(path)?))),
+        None => {}
+    }
+    vm.config.checkpoint = args.checkpoint.map(|path| Checkpoint { path, interval: args.checkpoint_every });
+    vm.config.max_instructions = args.max_instructions;
+    vm.config.timeout = args.timeout;
+    if args.cycles || args.memory_latency.is_some() || args.mmio_latency.is_some() {
+        let default = CycleModel::default();
+        vm.config.cycles = Some(CycleModel {
+            memory: args.memory_latency.unwrap_or(default.memory),
+            mmio: args.mmio_latency.unwrap_or(default.mmio),
+            ..default
+        });
+    }
+    vm.config.ctrl_c = args.ctrl_c;
+    vm.config.engine = args.engine;
+    if args.display || args.frames.is_some() {
+        vm.config.display = Some(DisplayConfig {
+            terminal: args.display && io::stdout().is_terminal(),
+            frames: args.frames,
+            format: args.frame_format,
+            frame_interval: args.frame_every,
+        });
+    }
+
+    prepare(&mut vm, &args.machine)?;
+    if !vm.running && is_snapshot_path(&args.machine.program)? {
+        eprintln!("{} is a snapshot of a halted machine", args.machine.program.display());
+        return Ok((StopReason::Halted, vm.registers.get(Register::R0)));
+    }
+
+    let start = Instant::now();
+    let result = vm.run();
+    if args.stats {
+        let stats = vm.stats();
+        let elapsed = start.elapsed();
+        eprintln!("{}", stats);
+        eprintln!(
+            "Time: {:.3}s, {:.2} MIPS",
+            elapsed.as_secs_f64(),
+            stats.retired() as f64 / elapsed.as_secs_f64().max(1e-9) / 1e6
+        );
+    }
+
+    match result {
+        Ok(reason) => Ok((reason, vm.registers.get(Register::R0))),
+        Err(err) => Err(match Fault::from_io_error(&err) {
+            Some(fault) => Failure::Fault(*fault, vm.registers.get(Register::PC).wrapping_sub(1)),
+            None => Failure::Io(err),
+        }),
+    }
+}
+
+fn is_snapshot_path(path: &Path) -> io::Result<bool> {
+    let mut magic = Vec::new();
+    File::open(path)?.take(8).read_to_end(&mut magic)?;
+    Ok(is_snapshot(&magic))
+}
+
+fn assemble(source: &Path, output: Option<PathBuf>, symbols: Option<PathBuf>) -> io::Result<()> {
+    let assembly = assemble_file(source)?;
+    assembly.image.write_file(output.unwrap_or_else(|| source.with_extension("obj")))?;
+    if let Some(path) = symbols {
+        let table: String = assembly
+            .symbols
+            .iter()
+            .map(|(label, address)| format!("{} x{:04X}\n", label, address))
+            .collect();
+        fs::write(path, table)?;
+    }
+    Ok(())
+}
+
+fn disassemble(path: &Path) -> io::Result<()> {
+    let (image, symbols) = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("asm")) {
+        let assembly = assemble_file(path)?;
+        (assembly.image, assembly.symbols)
+    } else {
+        (Image::read_file(path)?, BTreeMap::new())
+    };
+    print!("{}", asm::disassemble(&image, &symbols));
+    Ok(())
+}
+
+fn debug(machine: MachineArgs, input: Option<PathBuf>) -> io::Result<()> {
+    // Commands are read from stdin, so the program only shares it when it is a terminal
+    let console = match input {
+        Some(path) => Console::piped(PipeInput::from_reader(File::open(path)?)),
+        None if io::stdin().is_terminal() => Console::new(),
+        None => Console::piped(PipeInput::from_reader(io::empty())),
+    };
+    let mut vm = LC3::with_console(console);
+    let symbols = prepare(&mut vm, &machine)?;
+    let mut debugger = Debugger::new(&mut vm);
+    debugger.symbols = symbols;
+    debugger.repl(io::stdin().lock(), io::stdout())
+}
+
+fn dump(path: &Path, from: u16, to: u16) -> io::Result<()> {
+    let mut vm = LC3::with_console(Console::scripted(&[]));
+    load_program(&mut vm, path)?;
+
+    let registers: Vec<String> = (0..8)
+        .map(|index| {
+            let register = Register::from_u16(index);
+            format!("{:?}=x{:04X}", register, vm.registers.get(register))
+        })
+        .collect();
+    println!("{}", registers.join(" "));
+    println!(
+        "PC=x{:04X} CC={} instructions={} running={}",
+        vm.registers.get(Register::PC),
+        condition_name(vm.registers.get_condition_flag()),
+        vm.instruction_count,
+        vm.running
+    );
+
+    // Rows of eight words, skipping rows that are entirely zero
+    let memory = vm.memory.get_ptr(0);
+    for row in (from as usize & !7..=to as usize).step_by(8) {
+        let words = &memory[row..(row + 8).min(MEMORY_SIZE)];
+        if words.iter().any(|&word| word != 0) {
+            let cells: Vec<String> = words.iter().map(|word| format!("x{:04X}", word)).collect();
+            println!("x{:04X}: {}", row, cells.join(" "));
+        }
+    }
     Ok(())
 }
+
+/// Grades an image against a spec, printing a human-readable report and
+/// optionally writing a JSON one (`-` for stdout instead of the text report)
+fn grade(spec_path: &Path, image: Option<PathBuf>, json: Option<PathBuf>) -> io::Result<bool> {
+    let spec = Spec::from_json(&fs::read_to_string(spec_path)?)?;
+
+    let image = match (image, &spec.image) {
+        (Some(path), _) => path,
+        (None, Some(path)) => spec_path.parent().unwrap_or(Path::new(".")).join(path),
+        (None, None) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "No image given on the command line or in the spec"));
+        }
+    };
+
+    let report = grader::grade(&spec, &fs::read(image)?);
+
+    match json {
+        Some(path) if path.as_os_str() == "-" => println!("{}", report.to_json()),
+        Some(path) => {
+            fs::write(path, report.to_json() + "\n")?;
+            println!("{}", report);
+        }
+        None => println!("{}", report),
+    }
+
+    Ok(report.all_passed())
+}
+
+fn convert(input: &Path, output: &Path) -> io::Result<()> {
+    Image::read_file(input)?.write_file(output)
+}
+
+/// Maps the result of a command that only fails with I/O errors to an exit status
+fn io_status(result: io::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::from(EXIT_HALTED),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::from(EXIT_IO_ERROR)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse_from(with_default_subcommand(std::env::args_os().collect()));
+    let machine = match &cli.command {
+        Command::Run(args) => Some(&args.machine),
+        Command::Debug { machine, .. } => Some(machine),
+        _ => None,
+    };
+    if let Some(Err(message)) = machine.map(MachineArgs::check) {
+        Cli::command().error(clap::error::ErrorKind::ArgumentConflict, message).exit();
+    }
+
+    match cli.command {
+        Command::Run(args) => {
+            let policy = args.exit_code;
+            match run(*args) {
+                Ok((StopReason::Halted, r0)) if policy == ExitPolicy::R0 => ExitCode::from(r0 as u8),
+                Ok((StopReason::Halted, _)) => ExitCode::from(EXIT_HALTED),
+                Ok((StopReason::InstructionLimit, _)) => {
+                    eprintln!("Stopped: instruction limit reached");
+                    ExitCode::from(EXIT_INSTRUCTION_LIMIT)
+                }
+                Ok((StopReason::Timeout, _)) => {
+                    eprintln!("Stopped: timeout expired");
+                    ExitCode::from(EXIT_TIMEOUT)
+                }
+                Err(Failure::Fault(fault, pc)) => {
+                    eprintln!("Fault: {} at x{:04X}", fault, pc);
+                    ExitCode::from(EXIT_FAULT)
+                }
+                Err(Failure::Io(err)) => {
+                    eprintln!("Error: {}", err);
+                    ExitCode::from(EXIT_IO_ERROR)
+                }
+            }
+        }
+        Command::Asm { source, output, symbols } => match assemble(&source, output, symbols) {
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                eprintln!("{}", err);
+                ExitCode::from(EXIT_USAGE)
+            }
+            result => io_status(result),
+        },
+        Command::Disasm { image } => io_status(disassemble(&image)),
+        Command::Debug { machine, input } => io_status(debug(machine, input)),
+        Command::Dump { file, from, to } => io_status(dump(&file, from, to)),
+        Command::Grade { spec, image, json } => match grade(&spec, image, json) {
+            Ok(true) => ExitCode::from(EXIT_HALTED),
+            Ok(false) => ExitCode::from(EXIT_CASES_FAILED),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                ExitCode::from(EXIT_IO_ERROR)
+            }
+        },
+        Command::Convert { input, output } => io_status(convert(&input, &output)),
+    }
+}
@@ -0,0 +1,228 @@
+//! Routing of the device page (xFE00-xFFFF) to memory-mapped devices.
+//!
+//! Each device attached to the `Bus` answers reads and writes of its own range
+//! of addresses; addresses no device claims behave as ordinary memory. Memory
+//! keeps the value last read from or written to every device address, so
+//! dumps and the debugger show device registers as the program last saw them.
+//!
+//! Devices that change on their own, such as timers, ask for a `tick` at a
+//! given instruction count, and any device can request an interrupt; see
+//! `interrupt.rs`. The bus caches both after every access and tick, so idle
+//! devices cost nothing per instruction.
+
+use std::io;
+use std::ops::RangeInclusive;
+
+use crate::io::console::Console;
+use super::IO_PAGE_START;
+
+/// What a device can reach while handling a read or a tick
+pub struct DeviceContext<'a> {
+    pub console: &'a mut Console,
+    /// Number of instructions the VM has executed
+    pub instruction_count: u64,
+}
+
+/// An interrupt request: the vector table entry to enter and the priority to run it at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interrupt {
+    pub vector: u8,
+    /// 0-7; only interrupts above the running program's priority are taken
+    pub priority: u16,
+}
+
+/// A peripheral mapped into the device page
+pub trait Device: Send {
+    /// Identifies the device's state in snapshots
+    fn name(&self) -> &str;
+
+    /// Returns the value the program reads from `address`, which may have side
+    /// effects such as taking a key from the console
+    fn read(&mut self, address: u16, context: &mut DeviceContext) -> io::Result<u16>;
+
+    /// Handles the program writing `value` to `address`
+    fn write(&mut self, address: u16, value: u16);
+
+    /// Instruction count after which the device next needs `tick`, or `None`.
+    /// A count already reached means after the current instruction.
+    fn next_tick(&self) -> Option<u64> {
+        None
+    }
+
+    /// Lets the device change state on its own. Called once the instruction count
+    /// reaches `next_tick`, and also early after the console has waited for input.
+    fn tick(&mut self, _context: &mut DeviceContext) {}
+
+    /// The interrupt the device is requesting, if any
+    fn interrupt(&self) -> Option<Interrupt> {
+        None
+    }
+
+    /// State beyond the register values kept in memory, for snapshots
+    fn save(&self, _instruction_count: u64) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Checks that `restore` would accept `state`, so a snapshot can be
+    /// rejected before any of it is loaded
+    fn validate(&self, _state: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Restores state written by `save`
+    fn restore(&mut self, _state: &[u8], _instruction_count: u64) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Attached {
+    addresses: RangeInclusive<u16>,
+    device: Box<dyn Device>,
+}
+
+/// Devices attached to the device page
+pub struct Bus {
+    devices: Vec<Attached>,
+    /// One more than the index of the device at each device page address, 0 for none
+    map: Box<[u8]>,
+    /// Earliest `next_tick` of any device, `u64::MAX` if none
+    next_tick: u64,
+    /// Highest-priority interrupt requested by any device
+    request: Option<Interrupt>,
+    /// Set when the console has waited for input, so every device is ticked
+    woken: bool,
+}
+
+impl Bus {
+    /// Creates a bus with no devices
+    pub fn new() -> Self {
+        Bus {
+            devices: Vec::new(),
+            map: vec![0; 0x10000 - IO_PAGE_START as usize].into_boxed_slice(),
+            next_tick: u64::MAX,
+            request: None,
+            woken: false,
+        }
+    }
+
+    /// Maps `device` at `addresses`, which must lie in the device page and not
+    /// overlap another device
+    pub fn attach(&mut self, addresses: RangeInclusive<u16>, device: Box<dyn Device>) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        if addresses.is_empty() || *addresses.start() < IO_PAGE_START {
+            return Err(invalid(format!("{} must be mapped within xFE00-xFFFF", device.name())));
+        }
+        if let Some(taken) = addresses.clone().find(|&address| self.map[offset(address)] != 0) {
+            let other = &self.devices[self.map[offset(taken)] as usize - 1].device;
+            return Err(invalid(format!("{} overlaps {} at x{:04X}", device.name(), other.name(), taken)));
+        }
+        if self.devices.len() == u8::MAX as usize {
+            return Err(invalid("Too many devices".to_string()));
+        }
+
+        self.devices.push(Attached { addresses: addresses.clone(), device });
+        for address in addresses {
+            self.map[offset(address)] = self.devices.len() as u8;
+        }
+        self.refresh();
+        Ok(())
+    }
+
+    /// The attached devices and the addresses each occupies
+    pub fn devices(&self) -> impl Iterator<Item = (RangeInclusive<u16>, &dyn Device)> {
+        self.devices.iter().map(|attached| (attached.addresses.clone(), attached.device.as_ref()))
+    }
+
+    /// Reads `address` from the device mapped there, or returns `None` if there is none
+    pub(crate) fn read(&mut self, address: u16, context: &mut DeviceContext) -> Option<io::Result<u16>> {
+        let index = self.map[offset(address)].checked_sub(1)?;
+        let result = self.devices[index as usize].device.read(address, context);
+        self.woken |= context.console.take_waited();
+        self.refresh();
+        Some(result)
+    }
+
+    /// Passes a write to the device mapped at `address`, if any
+    pub(crate) fn write(&mut self, address: u16, value: u16) {
+        if let Some(index) = self.map[offset(address)].checked_sub(1) {
+            self.devices[index as usize].device.write(address, value);
+            self.refresh();
+        }
+    }
+
+    /// Whether a device needs a tick or requests an interrupt after the instruction
+    /// that brought the count to `instruction_count`
+    #[inline]
+    pub(crate) fn due(&self, instruction_count: u64) -> bool {
+        instruction_count >= self.next_tick || self.request.is_some() || self.woken
+    }
+
+    /// Instruction count at which the next tick is due
+    pub(crate) fn next_tick(&self) -> u64 {
+        self.next_tick
+    }
+
+    /// Ticks every device that is due and returns the interrupt then requested
+    pub(crate) fn tick(&mut self, context: &mut DeviceContext) -> Option<Interrupt> {
+        let woken = std::mem::take(&mut self.woken) | context.console.take_waited();
+        for attached in &mut self.devices {
+            let due = attached.device.next_tick().is_some_and(|at| at <= context.instruction_count);
+            if due || woken {
+                attached.device.tick(context);
+            }
+        }
+        self.refresh();
+        self.request
+    }
+
+    /// The state of every device that has any, by name
+    pub(crate) fn save(&self, instruction_count: u64) -> Vec<(String, Vec<u8>)> {
+        self.devices
+            .iter()
+            .map(|attached| (attached.device.name().to_string(), attached.device.save(instruction_count)))
+            .filter(|(_, state)| !state.is_empty())
+            .collect()
+    }
+
+    /// Checks the state saved for the device called `name` without restoring
+    /// it. Fails if no such device is attached.
+    pub(crate) fn validate(&self, name: &str, state: &[u8]) -> io::Result<()> {
+        match self.devices.iter().find(|attached| attached.device.name() == name) {
+            Some(attached) => attached.device.validate(state),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Snapshot has state for {}, which is not attached", name),
+            )),
+        }
+    }
+
+    /// Restores the state saved for the device called `name`. Snapshots with
+    /// state for devices that are not attached are rejected by `validate`
+    /// before anything is restored.
+    pub(crate) fn restore(&mut self, name: &str, state: &[u8], instruction_count: u64) -> io::Result<()> {
+        if let Some(attached) = self.devices.iter_mut().find(|attached| attached.device.name() == name) {
+            attached.device.restore(state, instruction_count)?;
+        }
+        self.refresh();
+        Ok(())
+    }
+
+    fn refresh(&mut self) {
+        self.next_tick = self.devices.iter().filter_map(|attached| attached.device.next_tick()).min().unwrap_or(u64::MAX);
+        self.request = self
+            .devices
+            .iter()
+            .filter_map(|attached| attached.device.interrupt())
+            .max_by_key(|interrupt| interrupt.priority);
+    }
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn offset(address: u16) -> usize {
+    (address - IO_PAGE_START) as usize
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::io::console::CtrlC;
+use crate::io::display::FrameFormat;
+use crate::io::serial::SerialBackend;
+use super::{CycleModel, ExtendedTrap};
+
+/// Options controlling how `LC3::run` executes a program
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Periodically save a snapshot of the machine while running
+    pub checkpoint: Option<Checkpoint>,
+    /// Stop after executing this many instructions
+    pub max_instructions: Option<u64>,
+    /// Stop once this much wall-clock time has passed. Checked between
+    /// instructions, so time spent waiting for a key counts but cannot
+    /// interrupt the wait.
+    pub timeout: Option<Duration>,
+    /// What Ctrl-C does when input comes from the terminal
+    pub ctrl_c: CtrlC,
+    /// How instructions are fetched and executed
+    pub engine: Engine,
+    /// Where the bitmap display in video memory is shown; `None` treats video
+    /// memory as ordinary memory
+    pub display: Option<DisplayConfig>,
+    /// Attach the random number generator at RNDR (xFE10)
+    pub random: bool,
+    /// Seed for the random number generator, for runs that must be
+    /// reproducible; `None` seeds it from the system clock
+    pub random_seed: Option<u64>,
+    /// Attach the uptime and time-of-day clock at xFE14-xFE1C
+    pub clock: bool,
+    /// Directory the file traps (x30-x34) work in; `None` leaves them
+    /// undefined, so they fault like any unknown trap
+    pub sandbox: Option<PathBuf>,
+    /// Extended console traps by vector; empty disables them. Vectors of the
+    /// standard traps (x20-x25) keep their standard meaning, while an extended
+    /// trap on a file trap vector (x30-x34) takes its place.
+    pub extended_traps: BTreeMap<u8, ExtendedTrap>,
+    /// Attach a UART connected to a socket, pseudo-terminal or pair of files
+    pub uart: Option<UartConfig>,
+    /// Count clock cycles with this model, reported in `Stats::cycles`
+    pub cycles: Option<CycleModel>,
+}
+
+/// Execution strategies; all of them behave identically
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Engine {
+    /// Decode every instruction each time it is fetched
+    Interpreter,
+    /// Decode each address once and reuse the result until the word is written
+    #[default]
+    Cached,
+    /// Translate basic blocks into threaded code and run them back to back.
+    /// `LC3::step` and tracing run one instruction at a time as with `Cached`.
+    #[value(help = "Translate basic blocks into threaded code and run them back to back")]
+    Translated,
+    /// Run each instruction through the states of the LC-3 microsequencer,
+    /// which `LC3::microstep` runs one at a time
+    #[value(help = "Run each instruction through the states of the LC-3 microsequencer")]
+    Microcoded,
+}
+
+/// Where and how often `LC3::run` saves snapshots
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    /// File the snapshot is written to, replacing the previous one
+    pub path: PathBuf,
+    /// Number of instructions between snapshots
+    pub interval: u64,
+}
+
+/// Where the UART is mapped and what it is connected to
+#[derive(Debug, Clone)]
+pub struct UartConfig {
+    /// Address of the status register; the data register is two above it
+    pub address: u16,
+    pub backend: SerialBackend,
+}
+
+/// Outputs for the 128x124 bitmap display mapped at xC000
+#[derive(Debug, Clone)]
+pub struct DisplayConfig {
+    /// Draw the display at the top of the terminal, with text output scrolling below it
+    pub terminal: bool,
+    /// Directory to save numbered frames in (`frame-00000.png`, ...)
+    pub frames: Option<PathBuf>,
+    pub format: FrameFormat,
+    /// Instructions between checks for a changed frame to save. A final frame
+    /// is saved when the run ends if the display changed since the last one.
+    pub frame_interval: u64,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            terminal: false,
+            frames: None,
+            format: FrameFormat::default(),
+            frame_interval: 100_000,
+        }
+    }
+}
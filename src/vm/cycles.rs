@@ -0,0 +1,80 @@
+//! Clock cycle accounting, enabled by `Config::cycles`.
+//!
+//! The default costs follow the LC-3 state machine of Patt & Patel, Appendix
+//! C: each instruction takes the states it passes through that do not touch
+//! memory, three of them (18, 35 and 32) to fetch and decode it, and every
+//! memory access, including the fetch, costs `memory` cycles on top, or `mmio`
+//! cycles when it reaches the device page. A taken branch spends one more
+//! state (22) loading the PC.
+//!
+//! Cycles are worked out from the counters in `Stats`, so every engine
+//! reports the same total. Trap service routines run on the host, so a TRAP
+//! costs only the instruction itself, as if each routine took no time.
+
+use super::{OpCode, Stats};
+
+/// Memory accesses made by each opcode after its fetch
+const DATA_ACCESSES: [u64; 16] = {
+    let mut accesses = [0; 16];
+    accesses[OpCode::LD as usize] = 1;
+    accesses[OpCode::ST as usize] = 1;
+    accesses[OpCode::LDR as usize] = 1;
+    accesses[OpCode::STR as usize] = 1;
+    accesses[OpCode::LDI as usize] = 2;
+    accesses[OpCode::STI as usize] = 2;
+    accesses[OpCode::TRAP as usize] = 1;
+    accesses[OpCode::RTI as usize] = 2;
+    accesses
+};
+
+/// Memory accesses taking an interrupt makes: pushing the PSR and PC, then
+/// reading the vector table
+const INTERRUPT_ACCESSES: u64 = 3;
+
+/// What each part of executing a program costs, in clock cycles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleModel {
+    /// Cycles for each opcode, indexed by opcode, apart from its memory accesses
+    pub opcodes: [u64; 16],
+    /// Extra cycles for a BR that branches
+    pub taken_branch: u64,
+    /// Cycles for taking an interrupt, apart from its memory accesses
+    pub interrupt: u64,
+    /// Cycles for each access to memory below the device page
+    pub memory: u64,
+    /// Cycles for each access to the device page (xFE00-xFFFF)
+    pub mmio: u64,
+}
+
+impl Default for CycleModel {
+    fn default() -> Self {
+        let mut opcodes = [4; 16];
+        for (opcode, cycles) in [
+            (OpCode::JSR, 5),
+            (OpCode::LD, 5),
+            (OpCode::LDR, 5),
+            (OpCode::LDI, 6),
+            (OpCode::ST, 5),
+            (OpCode::STR, 5),
+            (OpCode::STI, 6),
+            (OpCode::TRAP, 5),
+            (OpCode::RTI, 9),
+        ] {
+            opcodes[opcode as usize] = cycles;
+        }
+        CycleModel { opcodes, taken_branch: 1, interrupt: 6, memory: 1, mmio: 1 }
+    }
+}
+
+impl CycleModel {
+    /// Total cycles spent executing what `stats` counts
+    pub fn cycles(&self, stats: &Stats) -> u64 {
+        let mut cycles = stats.branches_taken * self.taken_branch + stats.interrupts * (self.interrupt + INTERRUPT_ACCESSES * self.memory);
+        for (opcode, &count) in stats.opcodes.iter().enumerate() {
+            cycles += count * (self.opcodes[opcode] + (1 + DATA_ACCESSES[opcode]) * self.memory);
+        }
+        // Device accesses were charged as ordinary ones above
+        let mmio = stats.mmio_reads + stats.mmio_writes;
+        (cycles + mmio * self.mmio).saturating_sub(mmio * self.memory)
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+
+use super::instructions::sign_extend;
+use super::{OpCode, TrapCode};
+
+/// Second source operand of ADD and AND
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Register(u8),
+    Immediate(i16),
+}
+
+/// A decoded LC-3 instruction with its fields extracted and offsets sign-extended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Add { dr: u8, sr1: u8, op2: Operand },
+    And { dr: u8, sr1: u8, op2: Operand },
+    Not { dr: u8, sr: u8 },
+    /// `nzp` holds the condition bits in the same layout as `CondFlag`
+    Br { nzp: u8, offset: i16 },
+    Jmp { base: u8 },
+    Jsr { offset: i16 },
+    Jsrr { base: u8 },
+    Ld { dr: u8, offset: i16 },
+    Ldi { dr: u8, offset: i16 },
+    Ldr { dr: u8, base: u8, offset: i16 },
+    Lea { dr: u8, offset: i16 },
+    St { sr: u8, offset: i16 },
+    Sti { sr: u8, offset: i16 },
+    Str { sr: u8, base: u8, offset: i16 },
+    Trap { vector: u8 },
+    Rti,
+    /// The reserved opcode, with the full instruction word
+    Reserved(u16),
+}
+
+impl Instruction {
+    /// Decodes an instruction word. Every word decodes to something; bits the
+    /// ISA leaves unspecified are ignored.
+    pub fn decode(instr: u16) -> Self {
+        let dr = ((instr >> 9) & 0x7) as u8;
+        let sr1 = ((instr >> 6) & 0x7) as u8;
+        let offset6 = sign_extend(instr & 0x3F, 6) as i16;
+        let offset9 = sign_extend(instr & 0x1FF, 9) as i16;
+        let op2 = if (instr & 0x20) == 0 {
+            Operand::Register((instr & 0x7) as u8)
+        } else {
+            Operand::Immediate(sign_extend(instr & 0x1F, 5) as i16)
+        };
+
+        match OpCode::from((instr >> 12) as u8) {
+            OpCode::ADD => Instruction::Add { dr, sr1, op2 },
+            OpCode::AND => Instruction::And { dr, sr1, op2 },
+            OpCode::NOT => Instruction::Not { dr, sr: sr1 },
+            OpCode::BR => Instruction::Br { nzp: dr, offset: offset9 },
+            OpCode::JMP => Instruction::Jmp { base: sr1 },
+            OpCode::JSR if (instr >> 11) & 1 != 0 => Instruction::Jsr {
+                offset: sign_extend(instr & 0x7FF, 11) as i16,
+            },
+            OpCode::JSR => Instruction::Jsrr { base: sr1 },
+            OpCode::LD => Instruction::Ld { dr, offset: offset9 },
+            OpCode::LDI => Instruction::Ldi { dr, offset: offset9 },
+            OpCode::LDR => Instruction::Ldr { dr, base: sr1, offset: offset6 },
+            OpCode::LEA => Instruction::Lea { dr, offset: offset9 },
+            OpCode::ST => Instruction::St { sr: dr, offset: offset9 },
+            OpCode::STI => Instruction::Sti { sr: dr, offset: offset9 },
+            OpCode::STR => Instruction::Str { sr: dr, base: sr1, offset: offset6 },
+            OpCode::TRAP => Instruction::Trap { vector: (instr & 0xFF) as u8 },
+            OpCode::RTI => Instruction::Rti,
+            OpCode::RES => Instruction::Reserved(instr),
+        }
+    }
+
+    /// Encodes the instruction, with unspecified bits set the way assemblers set them
+    pub fn encode(&self) -> u16 {
+        let reg = |r: u8, shift: u16| ((r & 0x7) as u16) << shift;
+        let bits = |value: i16, width: u16| (value as u16) & ((1 << width) - 1);
+        let op2 = |operand: &Operand| match *operand {
+            Operand::Register(sr2) => reg(sr2, 0),
+            Operand::Immediate(imm) => 0x20 | bits(imm, 5),
+        };
+
+        match self {
+            Instruction::Add { dr, sr1, op2: operand } => 0x1000 | reg(*dr, 9) | reg(*sr1, 6) | op2(operand),
+            Instruction::And { dr, sr1, op2: operand } => 0x5000 | reg(*dr, 9) | reg(*sr1, 6) | op2(operand),
+            Instruction::Not { dr, sr } => 0x903F | reg(*dr, 9) | reg(*sr, 6),
+            Instruction::Br { nzp, offset } => reg(*nzp, 9) | bits(*offset, 9),
+            Instruction::Jmp { base } => 0xC000 | reg(*base, 6),
+            Instruction::Jsr { offset } => 0x4800 | bits(*offset, 11),
+            Instruction::Jsrr { base } => 0x4000 | reg(*base, 6),
+            Instruction::Ld { dr, offset } => 0x2000 | reg(*dr, 9) | bits(*offset, 9),
+            Instruction::Ldi { dr, offset } => 0xA000 | reg(*dr, 9) | bits(*offset, 9),
+            Instruction::Ldr { dr, base, offset } => 0x6000 | reg(*dr, 9) | reg(*base, 6) | bits(*offset, 6),
+            Instruction::Lea { dr, offset } => 0xE000 | reg(*dr, 9) | bits(*offset, 9),
+            Instruction::St { sr, offset } => 0x3000 | reg(*sr, 9) | bits(*offset, 9),
+            Instruction::Sti { sr, offset } => 0xB000 | reg(*sr, 9) | bits(*offset, 9),
+            Instruction::Str { sr, base, offset } => 0x7000 | reg(*sr, 9) | reg(*base, 6) | bits(*offset, 6),
+            Instruction::Trap { vector } => 0xF000 | *vector as u16,
+            Instruction::Rti => 0x8000,
+            Instruction::Reserved(instr) => *instr,
+        }
+    }
+
+    /// The instruction's opcode
+    pub fn opcode(&self) -> OpCode {
+        match self {
+            Instruction::Add { .. } => OpCode::ADD,
+            Instruction::And { .. } => OpCode::AND,
+            Instruction::Not { .. } => OpCode::NOT,
+            Instruction::Br { .. } => OpCode::BR,
+            Instruction::Jmp { .. } => OpCode::JMP,
+            Instruction::Jsr { .. } | Instruction::Jsrr { .. } => OpCode::JSR,
+            Instruction::Ld { .. } => OpCode::LD,
+            Instruction::Ldi { .. } => OpCode::LDI,
+            Instruction::Ldr { .. } => OpCode::LDR,
+            Instruction::Lea { .. } => OpCode::LEA,
+            Instruction::St { .. } => OpCode::ST,
+            Instruction::Sti { .. } => OpCode::STI,
+            Instruction::Str { .. } => OpCode::STR,
+            Instruction::Trap { .. } => OpCode::TRAP,
+            Instruction::Rti => OpCode::RTI,
+            Instruction::Reserved(_) => OpCode::RES,
+        }
+    }
+
+    /// The address a PC-relative instruction at `address` refers to
+    pub fn target(&self, address: u16) -> Option<u16> {
+        let next = address.wrapping_add(1);
+        match *self {
+            Instruction::Br { offset, .. }
+            | Instruction::Jsr { offset }
+            | Instruction::Ld { offset, .. }
+            | Instruction::Ldi { offset, .. }
+            | Instruction::Lea { offset, .. }
+            | Instruction::St { offset, .. }
+            | Instruction::Sti { offset, .. } => Some(next.wrapping_add(offset as u16)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Register(r) => write!(f, "R{}", r),
+            Operand::Immediate(imm) => write!(f, "#{}", imm),
+        }
+    }
+}
+
+/// Formats the instruction in assembler syntax, with PC-relative offsets as `#n`
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Add { dr, sr1, op2 } => write!(f, "ADD R{}, R{}, {}", dr, sr1, op2),
+            Instruction::And { dr, sr1, op2 } => write!(f, "AND R{}, R{}, {}", dr, sr1, op2),
+            Instruction::Not { dr, sr } => write!(f, "NOT R{}, R{}", dr, sr),
+            Instruction::Br { nzp: 0, offset } => write!(f, "NOP #{}", offset),
+            Instruction::Br { nzp, offset } => {
+                let flag = |mask: u8, c: char| if nzp & mask != 0 { Some(c) } else { None };
+                let flags: String = [flag(4, 'n'), flag(2, 'z'), flag(1, 'p')].into_iter().flatten().collect();
+                write!(f, "BR{} #{}", flags, offset)
+            }
+            Instruction::Jmp { base: 7 } => write!(f, "RET"),
+            Instruction::Jmp { base } => write!(f, "JMP R{}", base),
+            Instruction::Jsr { offset } => write!(f, "JSR #{}", offset),
+            Instruction::Jsrr { base } => write!(f, "JSRR R{}", base),
+            Instruction::Ld { dr, offset } => write!(f, "LD R{}, #{}", dr, offset),
+            Instruction::Ldi { dr, offset } => write!(f, "LDI R{}, #{}", dr, offset),
+            Instruction::Ldr { dr, base, offset } => write!(f, "LDR R{}, R{}, #{}", dr, base, offset),
+            Instruction::Lea { dr, offset } => write!(f, "LEA R{}, #{}", dr, offset),
+            Instruction::St { sr, offset } => write!(f, "ST R{}, #{}", sr, offset),
+            Instruction::Sti { sr, offset } => write!(f, "STI R{}, #{}", sr, offset),
+            Instruction::Str { sr, base, offset } => write!(f, "STR R{}, R{}, #{}", sr, base, offset),
+            Instruction::Trap { vector } => match TrapCode::try_from(*vector as u16) {
+                Ok(trap) => write!(f, "{:?}", trap),
+                Err(_) => write!(f, "TRAP x{:02X}", vector),
+            },
+            Instruction::Rti => write!(f, "RTI"),
+            Instruction::Reserved(instr) => write!(f, ".FILL x{:04X}", instr),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::io;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::vm::{Device, DeviceContext, MemoryMappedRegister};
+
+/// Read-only clock at xFE14-xFE1C:
+///
+/// - CLKS and CLKM: seconds since the machine started (wrapping at 65536) and
+///   milliseconds within the current second
+/// - RTCH, RTCM and RTCS: the time of day in UTC
+///
+/// Reading CLKS latches the uptime and reading RTCH latches the time of day,
+/// so the registers that follow agree with it. Writes are ignored. The clock
+/// follows the host, so unlike the random number generator it differs between runs.
+#[derive(Debug)]
+pub struct Clock {
+    started: Instant,
+    uptime: Duration,
+    time_of_day: u64,
+}
+
+impl Clock {
+    /// A clock whose uptime starts now
+    pub fn new() -> Self {
+        Clock { started: Instant::now(), uptime: Duration::ZERO, time_of_day: 0 }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Device for Clock {
+    fn name(&self) -> &str {
+        "clock"
+    }
+
+    fn read(&mut self, address: u16, _context: &mut DeviceContext) -> io::Result<u16> {
+        Ok(match address {
+            a if a == MemoryMappedRegister::CLKS as u16 => {
+                self.uptime = self.started.elapsed();
+                self.uptime.as_secs() as u16
+            }
+            a if a == MemoryMappedRegister::CLKM as u16 => self.uptime.subsec_millis() as u16,
+            a if a == MemoryMappedRegister::RTCH as u16 => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                self.time_of_day = now.as_secs() % 86_400;
+                (self.time_of_day / 3600) as u16
+            }
+            a if a == MemoryMappedRegister::RTCM as u16 => (self.time_of_day / 60 % 60) as u16,
+            a if a == MemoryMappedRegister::RTCS as u16 => (self.time_of_day % 60) as u16,
+            _ => 0,
+        })
+    }
+
+    fn write(&mut self, _address: u16, _value: u16) {}
+
+    /// Milliseconds of uptime, so a restored machine's clock carries on from there
+    fn save(&self, _instruction_count: u64) -> Vec<u8> {
+        (self.started.elapsed().as_millis() as u64).to_be_bytes().to_vec()
+    }
+
+    fn validate(&self, state: &[u8]) -> io::Result<()> {
+        match state.len() {
+            8 => Ok(()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid clock state")),
+        }
+    }
+
+    fn restore(&mut self, state: &[u8], _instruction_count: u64) -> io::Result<()> {
+        let state: [u8; 8] =
+            state.try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid clock state"))?;
+        let uptime = Duration::from_millis(u64::from_be_bytes(state));
+        self.started = Instant::now().checked_sub(uptime).unwrap_or_else(Instant::now);
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use std::io;
+
+use crate::vm::{Device, DeviceContext, MemoryMappedRegister};
+
+/// KBSR bit set when a key is ready in KBDR
+pub const KEY_READY: u16 = 1 << 15;
+
+/// The keyboard at KBSR (xFE00) and KBDR (xFE02). Reading KBSR polls the
+/// console and, when a key is available, takes it into KBDR. The keyboard does
+/// not interrupt.
+#[derive(Debug, Default)]
+pub struct Keyboard {
+    status: u16,
+    data: u16,
+}
+
+impl Keyboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Device for Keyboard {
+    fn name(&self) -> &str {
+        "keyboard"
+    }
+
+    fn read(&mut self, address: u16, context: &mut DeviceContext) -> io::Result<u16> {
+        match address {
+            a if a == MemoryMappedRegister::KBSR as u16 => {
+                if context.console.check_key()? {
+                    self.status = KEY_READY;
+                    self.data = context.console.read_key()? as u16;
+                } else {
+                    self.status = 0;
+                }
+                Ok(self.status)
+            }
+            a if a == MemoryMappedRegister::KBDR as u16 => Ok(self.data),
+            _ => Ok(0),
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u16) {
+        match address {
+            a if a == MemoryMappedRegister::KBSR as u16 => self.status = value,
+            a if a == MemoryMappedRegister::KBDR as u16 => self.data = value,
+            _ => {}
+        }
+    }
+
+    /// KBSR and KBDR, which memory may not hold yet if the program has not read them
+    fn save(&self, _instruction_count: u64) -> Vec<u8> {
+        [self.status.to_be_bytes(), self.data.to_be_bytes()].concat()
+    }
+
+    fn validate(&self, state: &[u8]) -> io::Result<()> {
+        match state.len() {
+            4 => Ok(()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid keyboard state")),
+        }
+    }
+
+    fn restore(&mut self, state: &[u8], _instruction_count: u64) -> io::Result<()> {
+        let [status_high, status_low, data_high, data_low] = state else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid keyboard state"));
+        };
+        self.status = u16::from_be_bytes([*status_high, *status_low]);
+        self.data = u16::from_be_bytes([*data_high, *data_low]);
+        Ok(())
+    }
+}
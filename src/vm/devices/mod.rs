@@ -0,0 +1,14 @@
+//! The keyboard and timer every machine has, and the optional devices
+//! `LC3::attach_configured_devices` adds
+
+mod clock;
+mod keyboard;
+mod random;
+mod timer;
+mod uart;
+
+pub use self::clock::*;
+pub use self::keyboard::*;
+pub use self::random::*;
+pub use self::timer::*;
+pub use self::uart::*;
@@ -0,0 +1,77 @@
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::vm::{Device, DeviceContext, MemoryMappedRegister};
+
+/// Pseudo-random number generator at RNDR (xFE10). Every read of RNDR returns
+/// the next number of a xorshift64* sequence; writing RNDR reseeds it with the
+/// value written, so a program can replay a sequence. The same seed gives the
+/// same numbers on every engine and platform.
+#[derive(Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// A generator whose sequence is fixed by `seed`
+    pub fn new(seed: u64) -> Self {
+        Random { state: scramble(seed) }
+    }
+
+    /// A generator seeded from the system clock
+    pub fn from_clock() -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        Self::new(now.as_nanos() as u64)
+    }
+
+    fn next(&mut self) -> u16 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 48) as u16
+    }
+}
+
+/// Spreads a seed over all 64 bits (splitmix64), never returning the
+/// all-zero state xorshift cannot leave
+fn scramble(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (z ^ (z >> 31)).max(1)
+}
+
+impl Device for Random {
+    fn name(&self) -> &str {
+        "random"
+    }
+
+    fn read(&mut self, address: u16, _context: &mut DeviceContext) -> io::Result<u16> {
+        Ok(if address == MemoryMappedRegister::RNDR as u16 { self.next() } else { 0 })
+    }
+
+    fn write(&mut self, address: u16, value: u16) {
+        if address == MemoryMappedRegister::RNDR as u16 {
+            self.state = scramble(value as u64);
+        }
+    }
+
+    fn save(&self, _instruction_count: u64) -> Vec<u8> {
+        self.state.to_be_bytes().to_vec()
+    }
+
+    fn validate(&self, state: &[u8]) -> io::Result<()> {
+        match state.len() {
+            8 => Ok(()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid random generator state")),
+        }
+    }
+
+    fn restore(&mut self, state: &[u8], _instruction_count: u64) -> io::Result<()> {
+        let state: [u8; 8] = state
+            .try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid random generator state"))?;
+        self.state = u64::from_be_bytes(state).max(1);
+        Ok(())
+    }
+}
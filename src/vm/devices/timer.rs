@@ -0,0 +1,197 @@
+//! Programmable interval timer with registers at xFE08-xFE0C.
+//!
+//! - TMCR (control): bit 15 runs the timer, bit 14 lets it interrupt, and
+//!   bit 0 counts milliseconds of wall-clock time instead of instructions.
+//! - TMIR (interval): the period in instructions or milliseconds; 0 stops the timer.
+//! - TMSR (status): bit 15 is set each time a period elapses. Programs clear
+//!   it by writing TMSR.
+//!
+//! Writing TMCR or TMIR restarts the count. The timer is periodic: after
+//! expiring it starts counting the next period at once. While TMSR bit 15 and
+//! TMCR bit 14 are both set it requests an interrupt through vector x81 at
+//! priority 4.
+//!
+//! Counting instructions is exact and identical on every engine. The clock is
+//! read every `CLOCK_CHECK_INTERVAL` instructions and after the console waits
+//! for input, so a millisecond timer can expire slightly late, and never while
+//! GETC or IN are blocked waiting for a key.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::vm::{Device, DeviceContext, Interrupt, MemoryMappedRegister};
+
+/// TMCR bit that runs the timer
+pub const TIMER_ENABLE: u16 = 1 << 15;
+/// TMCR bit that lets the timer interrupt
+pub const TIMER_INTERRUPT_ENABLE: u16 = 1 << 14;
+/// TMCR bit that counts milliseconds instead of instructions
+pub const TIMER_MILLISECONDS: u16 = 1 << 0;
+/// TMSR bit set when a period has elapsed
+pub const TIMER_EXPIRED: u16 = 1 << 15;
+/// Interrupt vector of the timer
+pub const TIMER_VECTOR: u8 = 0x81;
+/// Priority the timer interrupts at
+pub const TIMER_PRIORITY: u16 = 4;
+
+/// Instructions between reads of the clock for a millisecond timer
+const CLOCK_CHECK_INTERVAL: u64 = 256;
+
+#[derive(Debug, Clone, Copy)]
+enum Deadline {
+    /// Instruction count at which the timer expires
+    Instruction(u64),
+    Time(Instant),
+}
+
+/// The programmable timer
+#[derive(Debug, Default)]
+pub struct Timer {
+    control: u16,
+    interval: u16,
+    status: u16,
+    /// When the timer next expires, or `None` while it is stopped
+    deadline: Option<Deadline>,
+    /// Instruction count at which to read the clock next
+    next_clock_check: u64,
+    /// Set by writes to TMCR or TMIR, so the count restarts after the instruction
+    restart: bool,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts counting a period with `remaining` instructions or milliseconds
+    /// left, as TMCR selects, or stops the timer if TMCR or `remaining` says so
+    fn resume(&mut self, remaining: u64, instruction_count: u64) {
+        self.deadline = if self.control & TIMER_ENABLE == 0 || self.interval == 0 || remaining == 0 {
+            None
+        } else if self.control & TIMER_MILLISECONDS != 0 {
+            self.next_clock_check = instruction_count;
+            Some(Deadline::Time(Instant::now() + Duration::from_millis(remaining)))
+        } else {
+            Some(Deadline::Instruction(instruction_count + remaining))
+        };
+    }
+
+    /// What is left of the current period, in instructions or milliseconds; 0 when stopped
+    fn remaining(&self, instruction_count: u64) -> u64 {
+        match self.deadline {
+            Some(Deadline::Instruction(at)) => at.saturating_sub(instruction_count).max(1),
+            Some(Deadline::Time(at)) => {
+                let left = at.saturating_duration_since(Instant::now());
+                (left.as_millis() as u64).max(1)
+            }
+            None => 0,
+        }
+    }
+}
+
+impl Device for Timer {
+    fn name(&self) -> &str {
+        "timer"
+    }
+
+    fn read(&mut self, address: u16, _context: &mut DeviceContext) -> io::Result<u16> {
+        Ok(match address {
+            a if a == MemoryMappedRegister::TMCR as u16 => self.control,
+            a if a == MemoryMappedRegister::TMIR as u16 => self.interval,
+            a if a == MemoryMappedRegister::TMSR as u16 => self.status,
+            _ => 0,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u16) {
+        match address {
+            a if a == MemoryMappedRegister::TMCR as u16 => {
+                self.control = value;
+                self.restart = true;
+            }
+            a if a == MemoryMappedRegister::TMIR as u16 => {
+                self.interval = value;
+                self.restart = true;
+            }
+            a if a == MemoryMappedRegister::TMSR as u16 => self.status = value,
+            _ => {}
+        }
+    }
+
+    fn next_tick(&self) -> Option<u64> {
+        if self.restart {
+            return Some(0);
+        }
+        match self.deadline? {
+            Deadline::Instruction(at) => Some(at),
+            Deadline::Time(_) => Some(self.next_clock_check),
+        }
+    }
+
+    /// Restarts the count after a register write and sets TMSR when a period elapses
+    fn tick(&mut self, context: &mut DeviceContext) {
+        let count = context.instruction_count;
+        if self.restart {
+            self.restart = false;
+            self.resume(self.interval as u64, count);
+        }
+
+        let expired = match self.deadline {
+            None => return,
+            Some(Deadline::Instruction(at)) => count >= at,
+            Some(Deadline::Time(at)) => {
+                self.next_clock_check = count + CLOCK_CHECK_INTERVAL;
+                Instant::now() >= at
+            }
+        };
+        if !expired {
+            return;
+        }
+
+        self.status |= TIMER_EXPIRED;
+        let interval = self.interval as u64;
+        self.deadline = match self.deadline {
+            Some(Deadline::Instruction(at)) => Some(Deadline::Instruction(at + interval)),
+            Some(Deadline::Time(at)) => {
+                // Periods missed while the host was busy or blocked are skipped
+                let now = Instant::now();
+                let next = at + Duration::from_millis(interval);
+                Some(Deadline::Time(if next > now { next } else { now + Duration::from_millis(interval) }))
+            }
+            None => None,
+        };
+    }
+
+    fn interrupt(&self) -> Option<Interrupt> {
+        let requesting = self.control & TIMER_INTERRUPT_ENABLE != 0 && self.status & TIMER_EXPIRED != 0;
+        requesting.then_some(Interrupt { vector: TIMER_VECTOR, priority: TIMER_PRIORITY })
+    }
+
+    /// The three registers and what is left of the current period
+    fn save(&self, instruction_count: u64) -> Vec<u8> {
+        let mut state = Vec::new();
+        for register in [self.control, self.interval, self.status] {
+            state.extend_from_slice(&register.to_be_bytes());
+        }
+        state.extend_from_slice(&self.remaining(instruction_count).to_be_bytes());
+        state
+    }
+
+    fn validate(&self, state: &[u8]) -> io::Result<()> {
+        match state.len() {
+            14 => Ok(()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid timer state")),
+        }
+    }
+
+    fn restore(&mut self, state: &[u8], instruction_count: u64) -> io::Result<()> {
+        self.validate(state)?;
+        let word = |index: usize| u16::from_be_bytes([state[index], state[index + 1]]);
+        self.control = word(0);
+        self.interval = word(2);
+        self.status = word(4);
+        self.restart = false;
+        self.resume(u64::from_be_bytes(state[6..14].try_into().unwrap()), instruction_count);
+        Ok(())
+    }
+}
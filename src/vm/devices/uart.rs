@@ -0,0 +1,69 @@
+use std::io;
+
+use crate::io::serial::SerialLink;
+use crate::vm::{Device, DeviceContext};
+
+/// Where `Config::uart` puts the UART's status register unless told otherwise
+pub const UART_ADDRESS: u16 = 0xFE20;
+
+/// Status bit set when a received byte is waiting in the data register
+pub const UART_RECEIVE_READY: u16 = 1 << 15;
+/// Status bit set when the data register can take a byte to send; always set
+pub const UART_TRANSMIT_READY: u16 = 1 << 14;
+/// Status bit set once the other end has closed the link and every byte has been read
+pub const UART_CLOSED: u16 = 1 << 13;
+
+/// Serial port with a status register and, two addresses above it, a data
+/// register. Reading the data register takes the next received byte (or
+/// repeats the last one if none is waiting); writing it sends the low byte.
+/// The link itself is not kept in snapshots.
+pub struct Uart {
+    status: u16,
+    link: SerialLink,
+    data: u16,
+}
+
+impl Uart {
+    /// A UART with its status register at `status` and its data register at `status + 2`
+    pub fn new(status: u16, link: SerialLink) -> Self {
+        Uart { status, link, data: 0 }
+    }
+
+    /// The addresses the UART occupies
+    pub fn addresses(&self) -> std::ops::RangeInclusive<u16> {
+        self.status..=self.status.wrapping_add(2)
+    }
+}
+
+impl Device for Uart {
+    fn name(&self) -> &str {
+        "uart"
+    }
+
+    fn read(&mut self, address: u16, _context: &mut DeviceContext) -> io::Result<u16> {
+        if address == self.status {
+            let mut status = UART_TRANSMIT_READY;
+            if self.link.poll() {
+                status |= UART_RECEIVE_READY;
+            } else if self.link.closed() {
+                status |= UART_CLOSED;
+            }
+            Ok(status)
+        } else if address == self.status.wrapping_add(2) {
+            if let Some(byte) = self.link.take() {
+                self.data = byte as u16;
+            }
+            Ok(self.data)
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Sends the low byte written to the data register; bytes the other end
+    /// can no longer receive are dropped
+    fn write(&mut self, address: u16, value: u16) {
+        if address == self.status.wrapping_add(2) {
+            let _ = self.link.send(value as u8);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Shows the bitmap display in video memory as `Config::display` asks: drawn
+//! at the top of the terminal while the program runs, saved as numbered image
+//! files, or both.
+
+use std::fs;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::io::display::{Frame, TERMINAL_ROWS};
+use super::LC3;
+
+/// Shortest time between two terminal redraws
+const REDRAW_INTERVAL: Duration = Duration::from_millis(33);
+
+/// What has been shown of the display so far
+#[derive(Default)]
+pub(crate) struct DisplayState {
+    /// Whether the terminal has been cleared and its scroll region set below the display
+    terminal_active: bool,
+    /// `Memory::video_writes` when the terminal was last drawn
+    drawn_writes: u64,
+    last_draw: Option<Instant>,
+    /// `Memory::video_writes` when frames were last compared for saving
+    saved_writes: u64,
+    last_saved: Option<Frame>,
+    /// Number of frame files written
+    frames: u32,
+}
+
+impl LC3 {
+    /// Redraws the display on the terminal if video memory has changed, at most
+    /// once per `REDRAW_INTERVAL` unless `force` is set
+    pub(crate) fn draw_display(&mut self, force: bool) -> io::Result<()> {
+        if !self.config.display.as_ref().is_some_and(|display| display.terminal) {
+            return Ok(());
+        }
+        let writes = self.memory.video_writes;
+        if writes == self.display.drawn_writes {
+            return Ok(());
+        }
+        if !force && self.display.last_draw.is_some_and(|time| time.elapsed() < REDRAW_INTERVAL) {
+            return Ok(());
+        }
+
+        let mut text = String::new();
+        if !self.display.terminal_active {
+            // Clear the screen and keep text output scrolling below the display
+            text += &format!("\x1b[2J\x1b[{0}r\x1b[{0};1H", TERMINAL_ROWS + 1);
+            self.display.terminal_active = true;
+        }
+        text += &Frame::capture(&self.memory).to_ansi();
+        self.console.draw(text.as_bytes())?;
+
+        self.display.drawn_writes = writes;
+        self.display.last_draw = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Saves the display as the next numbered frame if it differs from the last one saved
+    pub(crate) fn save_frame(&mut self) -> io::Result<()> {
+        let Some(display) = &self.config.display else {
+            return Ok(());
+        };
+        let Some(directory) = &display.frames else {
+            return Ok(());
+        };
+        let writes = self.memory.video_writes;
+        if writes == self.display.saved_writes {
+            return Ok(());
+        }
+        self.display.saved_writes = writes;
+
+        let frame = Frame::capture(&self.memory);
+        if self.display.last_saved.as_ref() == Some(&frame) {
+            return Ok(());
+        }
+        fs::create_dir_all(directory)?;
+        let path = directory.join(format!("frame-{:05}.{}", self.display.frames, display.format.extension()));
+        fs::write(path, frame.encode(display.format))?;
+        self.display.frames += 1;
+        self.display.last_saved = Some(frame);
+        Ok(())
+    }
+
+    /// Shows the final state of the display when a run ends and gives the
+    /// terminal's scrolling back to the whole screen
+    pub(crate) fn finish_display(&mut self) -> io::Result<()> {
+        self.save_frame()?;
+        self.draw_display(true)?;
+        if self.display.terminal_active {
+            self.console.draw(b"\x1b[r\x1b[999;1H")?;
+            self.display.terminal_active = false;
+            self.display.drawn_writes = 0;
+        }
+        Ok(())
+    }
+}
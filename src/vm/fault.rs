@@ -0,0 +1,38 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use super::OpCode;
+
+/// An error caused by the guest program rather than by host I/O
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// RTI in user mode, or the reserved opcode
+    IllegalOpcode(OpCode),
+    /// A TRAP with a vector that has no service routine
+    UnknownTrap(u8),
+}
+
+impl Fault {
+    /// Returns the fault carried by an error from `LC3::step` or `LC3::run`, if any
+    pub fn from_io_error(err: &io::Error) -> Option<&Fault> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::IllegalOpcode(op) => write!(f, "Unsupported opcode: {:?}", op),
+            Fault::UnknownTrap(vector) => write!(f, "Unknown trap code: x{:02X}", vector),
+        }
+    }
+}
+
+impl Error for Fault {}
+
+impl From<Fault> for io::Error {
+    fn from(fault: Fault) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, fault)
+    }
+}
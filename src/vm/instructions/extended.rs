@@ -0,0 +1,57 @@
+use std::io;
+
+use crate::vm::{ExtendedTrap, Register, LC3};
+
+/// Longest number READD accepts, in characters including the sign
+const MAX_DIGITS: usize = 6;
+
+impl LC3 {
+    /// Executes one of the extended console traps
+    pub(in crate::vm) fn execute_extended_trap(&mut self, trap: ExtendedTrap) -> io::Result<()> {
+        match trap {
+            ExtendedTrap::PRINTD => {
+                let value = self.registers.get(Register::R0) as i16;
+                self.console.write_str(&value.to_string())?;
+            }
+            ExtendedTrap::READD => {
+                let value = self.read_decimal()?;
+                self.registers.set(Register::R0, value as u16);
+                self.registers.update_flags(Register::R0);
+            }
+            ExtendedTrap::CLEAR => self.console.write_str("\x1b[2J\x1b[H")?,
+            ExtendedTrap::CURSOR => {
+                let row = self.registers.get(Register::R0) as u32 + 1;
+                let column = self.registers.get(Register::R1) as u32 + 1;
+                self.console.write_str(&format!("\x1b[{};{}H", row, column))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a line, echoing it, and returns the signed decimal at its start.
+    /// Backspace erases; other characters that cannot extend the number are
+    /// ignored. Values outside -32768..=32767 are clamped.
+    fn read_decimal(&mut self) -> io::Result<i16> {
+        let mut text = String::new();
+        loop {
+            let key = self.console.read_key()?;
+            match key {
+                b'\n' | b'\r' => break,
+                0x08 | 0x7F if !text.is_empty() => {
+                    text.pop();
+                    self.console.write_str("\x08 \x08")?;
+                }
+                b'-' if text.is_empty() => text.push('-'),
+                b'0'..=b'9' if text.len() < MAX_DIGITS => text.push(key as char),
+                _ => continue,
+            }
+            if key != 0x08 && key != 0x7F {
+                self.console.write_char(key)?;
+            }
+        }
+        self.console.write_char(b'\n')?;
+
+        let value: i32 = text.parse().unwrap_or(0);
+        Ok(value.clamp(i16::MIN as i32, i16::MAX as i32) as i16)
+    }
+}
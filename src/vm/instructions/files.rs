@@ -0,0 +1,185 @@
+//! File traps, enabled by `Config::sandbox`, that give programs files in one
+//! host directory and nowhere else.
+//!
+//! | Trap | Vector | Arguments                                  | R0 on success      |
+//! |------|--------|--------------------------------------------|--------------------|
+//! | FOPEN  | x30  | R0 path, R1 mode (0 read, 1 write, 2 append, 3 read/write) | handle |
+//! | FREAD  | x31  | R0 handle, R1 buffer, R2 bytes wanted      | bytes read, 0 at the end |
+//! | FWRITE | x32  | R0 handle, R1 buffer, R2 bytes to write    | bytes written      |
+//! | FCLOSE | x33  | R0 handle                                  | 0                  |
+//! | FSEEK  | x34  | R0 handle, R1 offset, R2 whence (0 start, 1 current, 2 end) | 0 |
+//!
+//! Paths are zero-terminated strings of one character per word, as for PUTS,
+//! relative to the sandbox, and may not leave it through `..`, absolute paths
+//! or symbolic links. Buffers hold one byte per word, in the low byte. R1 of
+//! FSEEK is signed. Paths and buffers are read as memory holds them, so
+//! naming a device register does not read the device. FREAD and FWRITE move
+//! at most x7FFF bytes, so the count in R0 stays positive. Every trap returns -1 in R0 when the host refuses, and sets
+//! the condition codes from R0, so a program can `BRn` to its error handling.
+//! Open files are not kept in snapshots.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::vm::{Register, LC3, MEMORY_SIZE};
+
+pub const TRAP_FOPEN: u8 = 0x30;
+pub const TRAP_FREAD: u8 = 0x31;
+pub const TRAP_FWRITE: u8 = 0x32;
+pub const TRAP_FCLOSE: u8 = 0x33;
+pub const TRAP_FSEEK: u8 = 0x34;
+
+/// Most files a program can have open at once
+pub const MAX_OPEN_FILES: usize = 16;
+
+/// Most bytes one FREAD or FWRITE moves, the largest count positive in R0
+pub const MAX_TRANSFER: u16 = 0x7FFF;
+
+/// Name of the file trap at `vector`, if it is one
+pub fn file_trap_name(vector: u8) -> Option<&'static str> {
+    Some(match vector {
+        TRAP_FOPEN => "FOPEN",
+        TRAP_FREAD => "FREAD",
+        TRAP_FWRITE => "FWRITE",
+        TRAP_FCLOSE => "FCLOSE",
+        TRAP_FSEEK => "FSEEK",
+        _ => return None,
+    })
+}
+
+/// Files a program has opened, by handle
+#[derive(Debug, Default)]
+pub(crate) struct OpenFiles {
+    handles: Vec<Option<File>>,
+}
+
+impl OpenFiles {
+    fn get(&mut self, handle: u16) -> io::Result<&mut File> {
+        self.handles
+            .get_mut(handle as usize)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Bad file handle"))
+    }
+
+    /// Closes the file behind `handle`
+    fn remove(&mut self, handle: u16) -> io::Result<()> {
+        self.get(handle)?;
+        self.handles[handle as usize] = None;
+        Ok(())
+    }
+
+    fn insert(&mut self, file: File) -> io::Result<u16> {
+        let free = self.handles.iter().position(Option::is_none);
+        let handle = match free {
+            Some(handle) => handle,
+            None if self.handles.len() < MAX_OPEN_FILES => {
+                self.handles.push(None);
+                self.handles.len() - 1
+            }
+            None => return Err(io::Error::other("Too many open files")),
+        };
+        self.handles[handle] = Some(file);
+        Ok(handle as u16)
+    }
+}
+
+impl LC3 {
+    /// Executes the file trap at `vector`, returning false if file traps are
+    /// disabled or `vector` is not one of them
+    pub(in crate::vm) fn execute_file_trap(&mut self, vector: u8) -> io::Result<bool> {
+        let Some(sandbox) = self.config.sandbox.clone() else {
+            return Ok(false);
+        };
+        let r0 = self.registers.get(Register::R0);
+        let r1 = self.registers.get(Register::R1);
+        let r2 = self.registers.get(Register::R2);
+
+        // The host refusing fails the trap without stopping the machine
+        let result = match vector {
+            TRAP_FOPEN => {
+                let name = self.read_path(r0);
+                resolve(&sandbox, &name)
+                    .and_then(|path| open(&path, r1))
+                    .and_then(|file| self.files.insert(file))
+            }
+            TRAP_FREAD => {
+                let mut buffer = Vec::new();
+                let read = self.files.get(r0).and_then(|file| file.take(r2.min(MAX_TRANSFER) as u64).read_to_end(&mut buffer));
+                for (offset, &byte) in buffer.iter().enumerate() {
+                    self.memory.write(r1.wrapping_add(offset as u16), byte as u16);
+                }
+                read.map(|count| count as u16)
+            }
+            TRAP_FWRITE => {
+                let count = r2.min(MAX_TRANSFER);
+                let buffer: Vec<u8> = (0..count).map(|offset| self.memory.get_ptr(r1.wrapping_add(offset))[0] as u8).collect();
+                self.files.get(r0).and_then(|file| file.write_all(&buffer)).map(|()| count)
+            }
+            TRAP_FCLOSE => self.files.remove(r0).map(|()| 0),
+            TRAP_FSEEK => {
+                let offset = r1 as i16 as i64;
+                let position = match r2 {
+                    0 if offset >= 0 => Ok(SeekFrom::Start(offset as u64)),
+                    1 => Ok(SeekFrom::Current(offset)),
+                    2 => Ok(SeekFrom::End(offset)),
+                    _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Bad seek")),
+                };
+                position.and_then(|position| self.files.get(r0)?.seek(position)).map(|_| 0)
+            }
+            _ => return Ok(false),
+        };
+
+        self.registers.set(Register::R0, result.unwrap_or(0xFFFF));
+        self.registers.update_flags(Register::R0);
+        Ok(true)
+    }
+
+    /// Reads a zero-terminated string of one character per word
+    fn read_path(&self, mut address: u16) -> String {
+        let mut name = Vec::new();
+        for _ in 0..MEMORY_SIZE {
+            let c = self.memory.get_ptr(address)[0];
+            if c == 0 {
+                break;
+            }
+            name.push(c as u8);
+            address = address.wrapping_add(1);
+        }
+        String::from_utf8_lossy(&name).into_owned()
+    }
+}
+
+fn open(path: &Path, mode: u16) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    match mode {
+        0 => options.read(true),
+        1 => options.write(true).create(true).truncate(true),
+        2 => options.append(true).create(true),
+        3 => options.read(true).write(true),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Bad file mode")),
+    };
+    options.open(path)
+}
+
+/// The host path for `name` within `sandbox`, refusing anything that would leave it
+fn resolve(sandbox: &Path, name: &str) -> io::Result<PathBuf> {
+    let denied = || io::Error::new(io::ErrorKind::PermissionDenied, format!("{} is outside the sandbox", name));
+    let relative = Path::new(name);
+    if name.is_empty() || !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+        return Err(denied());
+    }
+
+    let sandbox = sandbox.canonicalize()?;
+    let path = sandbox.join(relative);
+    // Symbolic links, in the directories or the file itself, must stay inside too
+    let parent = path.parent().unwrap_or(&sandbox).canonicalize()?;
+    let inside = match path.canonicalize() {
+        Ok(target) => target.starts_with(&sandbox),
+        Err(_) => parent.starts_with(&sandbox) && path.symlink_metadata().is_err(),
+    };
+    if !inside {
+        return Err(denied());
+    }
+    Ok(path)
+}
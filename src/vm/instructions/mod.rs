@@ -1,51 +1,59 @@
 mod arithmetic;
 mod branch;
+mod extended;
+mod files;
 mod load_store;
 mod trap;
 mod utils;
 
+pub use self::files::*;
 pub use self::utils::FromU16;
 pub use self::utils::sign_extend;
 
 use std::io;
-use crate::vm::{LC3, OpCode};
+use crate::vm::{Fault, Instruction, LC3, OpCode};
 
 impl LC3 {
-    /// Executes a single instruction based on its opcode
+    /// Decodes and executes a single instruction word
     pub fn execute_instruction(&mut self, instr: u16) -> io::Result<()> {
-        let op = OpCode::from((instr >> 12) as u8);
+        self.execute(Instruction::decode(instr))
+    }
 
-        match op {
+    /// Executes a decoded instruction
+    pub fn execute(&mut self, instruction: Instruction) -> io::Result<()> {
+        match instruction {
             // Arithmetic operations
-            OpCode::ADD => self.execute_add(instr),
-            OpCode::AND => self.execute_and(instr),
-            OpCode::NOT => self.execute_not(instr),
-            
+            Instruction::Add { dr, sr1, op2 } => self.execute_add(dr, sr1, op2),
+            Instruction::And { dr, sr1, op2 } => self.execute_and(dr, sr1, op2),
+            Instruction::Not { dr, sr } => self.execute_not(dr, sr),
+
             // Branch and jump operations
-            OpCode::BR => self.execute_br(instr),
-            OpCode::JMP => self.execute_jmp(instr),
-            OpCode::JSR => self.execute_jsr(instr),
-            
+            Instruction::Br { nzp, offset } => self.execute_br(nzp, offset),
+            Instruction::Jmp { base } => self.execute_jmp(base),
+            Instruction::Jsr { offset } => self.execute_jsr(offset),
+            Instruction::Jsrr { base } => self.execute_jsrr(base),
+
             // Load operations
-            OpCode::LD => self.execute_ld(instr)?,
-            OpCode::LDI => self.execute_ldi(instr)?,
-            OpCode::LDR => self.execute_ldr(instr)?,
-            OpCode::LEA => self.execute_lea(instr),
-            
+            Instruction::Ld { dr, offset } => self.execute_ld(dr, offset)?,
+            Instruction::Ldi { dr, offset } => self.execute_ldi(dr, offset)?,
+            Instruction::Ldr { dr, base, offset } => self.execute_ldr(dr, base, offset)?,
+            Instruction::Lea { dr, offset } => self.execute_lea(dr, offset),
+
             // Store operations
-            OpCode::ST => self.execute_st(instr)?,
-            OpCode::STI => self.execute_sti(instr)?,
-            OpCode::STR => self.execute_str(instr)?,
-            
+            Instruction::St { sr, offset } => self.execute_st(sr, offset),
+            Instruction::Sti { sr, offset } => self.execute_sti(sr, offset)?,
+            Instruction::Str { sr, base, offset } => self.execute_str(sr, base, offset),
+
             // Trap operation
-            OpCode::TRAP => self.execute_trap(instr)?,
-            
+            Instruction::Trap { vector } => self.execute_trap(vector)?,
+
+            // Return from interrupt
+            Instruction::Rti => self.execute_rti()?,
+
             // Unsupported operations
-            OpCode::RTI | OpCode::RES => {
-                return Err(io::Error::new(io::ErrorKind::Other, "Unsupported opcode"));
-            }
+            Instruction::Reserved(_) => return Err(Fault::IllegalOpcode(OpCode::RES).into()),
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,14 +1,22 @@
 use std::io;
-use crate::vm::{LC3, Register, TrapCode};
+use crate::vm::{Fault, LC3, MEMORY_SIZE, Register, TrapCode};
 
 impl LC3 {
     /// Executes TRAP instruction
     /// Format: TRAP TRAPVECT8
-    pub(super) fn execute_trap(&mut self, instr: u16) -> io::Result<()> {
+    pub(in crate::vm) fn execute_trap(&mut self, vector: u8) -> io::Result<()> {
         let pc = self.registers.get(Register::PC);
         self.registers.set(Register::R7, pc);
+        *self.stats.traps.entry(vector).or_default() += 1;
 
-        let trap_code = TrapCode::from(instr & 0xFF);
+        let trap_code = match TrapCode::try_from(vector as u16) {
+            Ok(trap_code) => trap_code,
+            Err(_) if self.config.extended_traps.contains_key(&vector) => {
+                return self.execute_extended_trap(self.config.extended_traps[&vector]);
+            }
+            Err(_) if self.execute_file_trap(vector)? => return Ok(()),
+            Err(_) => return Err(Fault::UnknownTrap(vector).into()),
+        };
 
         match trap_code {
             TrapCode::GETC => {
@@ -21,15 +29,16 @@ impl LC3 {
                 self.console.write_char(c)?;
             }
             TrapCode::PUTS => {
+                // Strings are bounded by the address space so an unterminated one cannot hang the VM
                 let mut address = self.registers.get(Register::R0);
-                loop {
+                for _ in 0..MEMORY_SIZE {
                     let c = self.memory.read(address, &mut self.console)?;
                     if c == 0 {
                         break;
                     }
                     let character = (c & 0xFF) as u8;
                     self.console.write_char(character)?;
-                    address += 1;
+                    address = address.wrapping_add(1);
                 }
             }
             TrapCode::IN => {
@@ -41,7 +50,7 @@ impl LC3 {
             }
             TrapCode::PUTSP => {
                 let mut address = self.registers.get(Register::R0);
-                loop {
+                for _ in 0..MEMORY_SIZE {
                     let value = self.memory.read(address, &mut self.console)?;
                     if value == 0 {
                         break;
@@ -55,11 +64,12 @@ impl LC3 {
                         self.console.write_char(char2)?;
                     }
 
-                    address += 1;
+                    address = address.wrapping_add(1);
                 }
             }
             TrapCode::HALT => {
                 self.console.write_str("HALT\n")?;
+                self.console.flush()?;
                 self.running = false;
             }
         }
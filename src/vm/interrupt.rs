@@ -0,0 +1,90 @@
+//! Interrupts and the privilege modes they switch between.
+//!
+//! Programs start in user mode at priority 0, with the supervisor stack
+//! pointer at `SUPERVISOR_STACK`. After each instruction, a device requesting
+//! an interrupt at a higher priority than the running program interrupts it:
+//! the processor swaps R6 for the supervisor stack pointer if it was in user
+//! mode, pushes the PSR and PC, enters supervisor mode at the device's
+//! priority and jumps to the address in the interrupt vector table entry for
+//! the device (x0100 + vector). RTI pops PC and PSR again, swapping back to the
+//! user stack when returning to user mode.
+//!
+//! Devices keep requesting an interrupt until the program acknowledges them,
+//! e.g. by clearing TMSR, so a service routine that does not will be entered
+//! again as soon as it returns.
+
+use std::io;
+
+use super::{CondFlag, DeviceContext, Fault, OpCode, Register, LC3, PSR_USER};
+
+/// Start of the interrupt vector table (x0100-x01FF)
+pub const INTERRUPT_VECTOR_TABLE: u16 = 0x0100;
+
+impl LC3 {
+    /// Advances the devices by one instruction and enters the service routine
+    /// of any interrupt they request. A machine that is not running, e.g. after
+    /// HALT, takes no interrupts. Returns whether an interrupt was taken.
+    #[inline]
+    pub(crate) fn poll_interrupts(&mut self) -> bool {
+        self.memory.bus.due(self.instruction_count) && self.service_devices()
+    }
+
+    #[inline(never)]
+    fn service_devices(&mut self) -> bool {
+        let mut context = DeviceContext { console: &mut self.console, instruction_count: self.instruction_count };
+        match self.memory.bus.tick(&mut context) {
+            Some(request) if self.running && request.priority > self.registers.priority() => {
+                self.interrupt(request.vector, request.priority);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Interrupts the running program with the service routine for `vector`,
+    /// running it in supervisor mode at `priority`
+    pub fn interrupt(&mut self, vector: u8, priority: u16) {
+        let psr = self.registers.psr();
+        if self.registers.user_mode() {
+            self.registers.saved_usp = self.registers.get(Register::R6);
+            self.registers.set(Register::R6, self.registers.saved_ssp);
+        }
+        self.push(psr);
+        self.push(self.registers.get(Register::PC));
+
+        self.registers.set_psr((priority & 0x7) << 8 | CondFlag::ZRO as u16);
+        let entry = self.memory.get_ptr(INTERRUPT_VECTOR_TABLE + vector as u16)[0];
+        self.registers.set(Register::PC, entry);
+        self.stats.interrupts += 1;
+    }
+
+    /// Executes RTI instruction, returning from an interrupt or trap service routine.
+    /// RTI in user mode is a privilege violation.
+    pub(in crate::vm) fn execute_rti(&mut self) -> io::Result<()> {
+        if self.registers.user_mode() {
+            return Err(Fault::IllegalOpcode(OpCode::RTI).into());
+        }
+        let pc = self.pop()?;
+        let psr = self.pop()?;
+        self.registers.set(Register::PC, pc);
+        self.registers.set_psr(psr);
+        if psr & PSR_USER != 0 {
+            self.registers.saved_ssp = self.registers.get(Register::R6);
+            self.registers.set(Register::R6, self.registers.saved_usp);
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, value: u16) {
+        let sp = self.registers.get(Register::R6).wrapping_sub(1);
+        self.registers.set(Register::R6, sp);
+        self.memory.write(sp, value);
+    }
+
+    fn pop(&mut self) -> io::Result<u16> {
+        let sp = self.registers.get(Register::R6);
+        let value = self.memory.read(sp, &mut self.console)?;
+        self.registers.set(Register::R6, sp.wrapping_add(1));
+        Ok(value)
+    }
+}
@@ -1,17 +1,44 @@
 use std::io;
 use crate::io::console::Console;
-use super::MEMORY_SIZE;
+use super::{Bus, DeviceContext, Instruction, Keyboard, Timer, MEMORY_SIZE};
+
+/// Start of the page reserved for device registers (xFE00-xFFFF)
+pub const IO_PAGE_START: u16 = 0xFE00;
+/// Start of video memory for the bitmap display (xC000-xFDFF)
+pub const VIDEO_START: u16 = 0xC000;
 
 /// Memory-mapped registers for I/O operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MemoryMappedRegister {
     KBSR = 0xFE00, // Keyboard status
     KBDR = 0xFE02, // Keyboard data
+    TMCR = 0xFE08, // Timer control
+    TMIR = 0xFE0A, // Timer interval
+    TMSR = 0xFE0C, // Timer status
+    RNDR = 0xFE10, // Random number
+    CLKS = 0xFE14, // Uptime seconds
+    CLKM = 0xFE16, // Uptime milliseconds within the second
+    RTCH = 0xFE18, // Time of day: hours
+    RTCM = 0xFE1A, // Time of day: minutes
+    RTCS = 0xFE1C, // Time of day: seconds
 }
 
 /// Memory subsystem for the LC-3 VM
 pub struct Memory {
     data: [u16; MEMORY_SIZE],
+    /// Decoded instructions by address, dropped when the word is written
+    decoded: Box<[Option<Instruction>]>,
+    /// Addresses that are part of a translated block
+    code: Box<[bool]>,
+    /// Number of writes made to addresses marked in `code`
+    code_writes: u64,
+    /// Reads and writes of the device page, reported by `LC3::stats`
+    pub(crate) mmio_reads: u64,
+    pub(crate) mmio_writes: u64,
+    /// Writes to video memory, so the display can tell when a frame has changed
+    pub(crate) video_writes: u64,
+    /// Devices mapped into the device page
+    pub(crate) bus: Bus,
 }
 
 impl Memory {
@@ -19,32 +46,101 @@ impl Memory {
     pub fn new() -> Self {
         Memory {
             data: [0; MEMORY_SIZE],
+            decoded: vec![None; MEMORY_SIZE].into_boxed_slice(),
+            code: vec![false; MEMORY_SIZE].into_boxed_slice(),
+            code_writes: 0,
+            mmio_reads: 0,
+            mmio_writes: 0,
+            video_writes: 0,
+            bus: Self::standard_bus(),
         }
     }
 
-    /// Reads a word from memory, handling memory-mapped registers
+    /// A bus with the keyboard and timer every machine has
+    fn standard_bus() -> Bus {
+        let mut bus = Bus::new();
+        bus.attach(MemoryMappedRegister::KBSR as u16..=MemoryMappedRegister::KBDR as u16, Box::new(Keyboard::new()))
+            .expect("keyboard fits the device page");
+        bus.attach(MemoryMappedRegister::TMCR as u16..=MemoryMappedRegister::TMSR as u16, Box::new(Timer::new()))
+            .expect("timer fits the device page");
+        bus
+    }
+
+    /// The devices mapped into the device page
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+
+    /// The devices mapped into the device page, e.g. to attach another
+    pub fn bus_mut(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
+
+    /// Fetches the instruction at `address`, decoding it only on first use.
+    /// Device registers are read afresh every time and never cached.
+    pub fn fetch(&mut self, address: u16, console: &mut Console) -> io::Result<Instruction> {
+        if let Some(instruction) = self.decoded[address as usize] {
+            return Ok(instruction);
+        }
+
+        let instruction = Instruction::decode(self.read(address, console)?);
+        if address < IO_PAGE_START {
+            self.decoded[address as usize] = Some(instruction);
+        }
+        Ok(instruction)
+    }
+
+    /// Reads a word from memory, or from the device mapped at `address`
     pub fn read(&mut self, address: u16, console: &mut Console) -> io::Result<u16> {
-        match address {
-            addr if addr == MemoryMappedRegister::KBSR as u16 => {
-                if console.check_key()? {
-                    self.data[MemoryMappedRegister::KBSR as usize] = 1 << 15;
-                    self.data[MemoryMappedRegister::KBDR as usize] = console.read_key()? as u16;
-                } else {
-                    self.data[MemoryMappedRegister::KBSR as usize] = 0;
-                }
-                Ok(self.data[MemoryMappedRegister::KBSR as usize])
+        if address >= IO_PAGE_START {
+            self.mmio_reads += 1;
+            let instruction_count = console.instruction_count();
+            let mut context = DeviceContext { console, instruction_count };
+            if let Some(value) = self.bus.read(address, &mut context) {
+                self.data[address as usize] = value?;
             }
-            _ => Ok(self.data[address as usize]),
         }
+        Ok(self.data[address as usize])
     }
 
-    /// Writes a word to memory
+    /// Writes a word to memory, passing writes to the device page on to the device mapped there
     pub fn write(&mut self, address: u16, value: u16) {
         self.data[address as usize] = value;
+        if address >= IO_PAGE_START {
+            self.mmio_writes += 1;
+            self.bus.write(address, value);
+        } else if address >= VIDEO_START {
+            self.video_writes += 1;
+        }
+        self.decoded[address as usize] = None;
+        if self.code[address as usize] {
+            self.code_writes += 1;
+        }
+    }
+
+    /// Sets the value memory holds for a device register without passing it to the device
+    pub(crate) fn set_mirror(&mut self, address: u16, value: u16) {
+        self.data[address as usize] = value;
+    }
+
+    /// Marks or unmarks `address` as holding translated code
+    pub(crate) fn set_code(&mut self, address: u16, translated: bool) {
+        self.code[address as usize] = translated;
+    }
+
+    /// Counts writes to translated code, so translations can tell when they are stale
+    pub(crate) fn code_writes(&self) -> u64 {
+        self.code_writes
     }
 
     /// Returns a slice to memory starting at the given address
     pub fn get_ptr(&self, address: u16) -> &[u16] {
         &self.data[address as usize..]
     }
-}
\ No newline at end of file
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,346 @@
+//! `Engine::Microcoded`: instructions run through the states of the LC-3
+//! microsequencer in Patt & Patel, Appendix C, one `LC3::microstep` per state.
+//!
+//! | Instruction | States after fetch (18, 33, 35) and decode (32) |
+//! |-------------|--------------------------------------------------|
+//! | ADD, AND, NOT | 1, 5, 9                                        |
+//! | BR          | 0, then 22 when taken                            |
+//! | JMP         | 12                                               |
+//! | JSR, JSRR   | 4, then 21 or 20                                 |
+//! | LD, LDR     | 2 or 6, 25, 27                                   |
+//! | LDI         | 10, 24, 26, 25, 27                               |
+//! | LEA         | 14                                               |
+//! | ST, STR     | 3 or 7, 23, 16                                   |
+//! | STI         | 11, 29, 31, 23, 16                               |
+//! | TRAP        | 15, 28, 30                                       |
+//! | RTI         | 8, 36, 38, 39, 40, 42, 34, then 51 into user mode; 44 in user mode |
+//! | reserved    | 13                                               |
+//!
+//! Memory accesses take a single state. Trap service routines run on the
+//! host, so state 30 runs the routine instead of loading the PC from the
+//! vector table. State 44 and the reserved opcode fault as in the other
+//! engines rather than entering an exception handler, and interrupts are
+//! taken between instructions by the same logic as `LC3::step`. Snapshots
+//! do not include the datapath, so saving one fails in the middle of an
+//! instruction, and loading one leaves the machine ready to fetch.
+
+use std::io;
+
+use super::{sign_extend, Fault, FromU16, Instruction, OpCode, Register, LC3};
+
+/// The state that fetches the next instruction
+pub const FETCH_STATE: u8 = 18;
+
+/// Datapath registers of the microarchitecture, as left by the last microstep
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Datapath {
+    /// Memory address register
+    pub mar: u16,
+    /// Memory data register
+    pub mdr: u16,
+    /// Instruction register
+    pub ir: u16,
+    /// Branch enable, set during decode when a BR's condition holds
+    pub ben: bool,
+    /// The state that runs next
+    pub state: u8,
+    /// Address the instruction in IR was fetched from
+    fetched_from: u16,
+}
+
+impl Default for Datapath {
+    fn default() -> Self {
+        Datapath { mar: 0, mdr: 0, ir: 0, ben: false, state: FETCH_STATE, fetched_from: 0 }
+    }
+}
+
+impl LC3 {
+    /// Returns the datapath of `Engine::Microcoded`
+    pub fn datapath(&self) -> &Datapath {
+        &self.datapath
+    }
+
+    /// Runs one state of the microsequencer, returning the state that ran.
+    /// The instruction retires when the next state is `FETCH_STATE`; an
+    /// instruction that faults leaves the machine ready to fetch the next one.
+    pub fn microstep(&mut self) -> io::Result<u8> {
+        let state = self.datapath.state;
+        let next = match self.run_state(state) {
+            Ok(next) => next,
+            Err(err) => {
+                self.datapath.state = FETCH_STATE;
+                if !matches!(state, 18 | 33 | 35) && self.trace.is_some() {
+                    self.write_trace(self.datapath.fetched_from, self.datapath.ir)?;
+                }
+                return Err(err);
+            }
+        };
+
+        self.datapath.state = next;
+        if next == FETCH_STATE {
+            let instruction = Instruction::decode(self.datapath.ir);
+            if self.trace.is_some() {
+                self.write_trace(self.datapath.fetched_from, self.datapath.ir)?;
+            }
+            self.instruction_count += 1;
+            self.stats.opcodes[instruction.opcode() as usize] += 1;
+            self.poll_interrupts();
+        }
+        Ok(state)
+    }
+
+    /// Runs microsteps until the instruction in progress, or the next one, retires
+    pub(in crate::vm) fn step_microcoded(&mut self) -> io::Result<()> {
+        loop {
+            self.microstep()?;
+            if self.datapath.state == FETCH_STATE {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Performs the register transfers of `state` and returns the next state
+    fn run_state(&mut self, state: u8) -> io::Result<u8> {
+        let ir = self.datapath.ir;
+        let pc = self.registers.get(Register::PC);
+        // DR, or SR for stores
+        let dr = Register::from_u16(ir >> 9 & 0x7);
+        // SR1, or BaseR
+        let base = Register::from_u16(ir >> 6 & 0x7);
+        let pc_offset9 = pc.wrapping_add(sign_extend(ir & 0x1FF, 9));
+        let base_offset6 = self.registers.get(base).wrapping_add(sign_extend(ir & 0x3F, 6));
+
+        Ok(match state {
+            // MAR <- PC, PC <- PC + 1
+            18 => {
+                self.console.set_instruction_count(self.instruction_count);
+                self.datapath.mar = pc;
+                self.datapath.fetched_from = pc;
+                self.registers.set(Register::PC, pc.wrapping_add(1));
+                33
+            }
+            // MDR <- M
+            33 => {
+                self.datapath.mdr = self.memory.read(self.datapath.mar, &mut self.console)?;
+                35
+            }
+            // IR <- MDR
+            35 => {
+                self.datapath.ir = self.datapath.mdr;
+                32
+            }
+            // BEN <- IR[11] & N + IR[10] & Z + IR[9] & P, then on to the opcode's state
+            32 => {
+                self.datapath.ben = ir >> 9 & 0x7 & self.registers.get_condition_flag() != 0;
+                (ir >> 12) as u8
+            }
+            // ADD and AND: DR <- SR1 op OP2, set CC
+            1 | 5 => {
+                let op2 = if ir & 0x20 != 0 {
+                    sign_extend(ir & 0x1F, 5)
+                } else {
+                    self.registers.get(Register::from_u16(ir & 0x7))
+                };
+                let sr1 = self.registers.get(base);
+                self.registers.set(dr, if state == 1 { sr1.wrapping_add(op2) } else { sr1 & op2 });
+                self.registers.update_flags(dr);
+                FETCH_STATE
+            }
+            // NOT: DR <- NOT(SR), set CC
+            9 => {
+                self.registers.set(dr, !self.registers.get(base));
+                self.registers.update_flags(dr);
+                FETCH_STATE
+            }
+            // BR: [BEN]
+            0 => {
+                if self.datapath.ben {
+                    22
+                } else {
+                    FETCH_STATE
+                }
+            }
+            // PC <- PC + off9
+            22 => {
+                self.registers.set(Register::PC, pc_offset9);
+                self.stats.branches_taken += 1;
+                FETCH_STATE
+            }
+            // JMP: PC <- BaseR
+            12 => {
+                self.registers.set(Register::PC, self.registers.get(base));
+                FETCH_STATE
+            }
+            // JSR: [IR[11]]
+            4 => {
+                if ir & 0x800 != 0 {
+                    21
+                } else {
+                    20
+                }
+            }
+            // R7 <- PC, PC <- PC + off11
+            21 => {
+                self.registers.set(Register::R7, pc);
+                self.registers.set(Register::PC, pc.wrapping_add(sign_extend(ir & 0x7FF, 11)));
+                FETCH_STATE
+            }
+            // R7 <- PC, PC <- BaseR, reading BaseR before R7 changes
+            20 => {
+                let target = self.registers.get(base);
+                self.registers.set(Register::R7, pc);
+                self.registers.set(Register::PC, target);
+                FETCH_STATE
+            }
+            // LD: MAR <- PC + off9
+            2 => {
+                self.datapath.mar = pc_offset9;
+                25
+            }
+            // LDR: MAR <- BaseR + off6
+            6 => {
+                self.datapath.mar = base_offset6;
+                25
+            }
+            // LDI: MAR <- PC + off9
+            10 => {
+                self.datapath.mar = pc_offset9;
+                24
+            }
+            // MDR <- M
+            24 => {
+                self.datapath.mdr = self.memory.read(self.datapath.mar, &mut self.console)?;
+                26
+            }
+            // MAR <- MDR
+            26 => {
+                self.datapath.mar = self.datapath.mdr;
+                25
+            }
+            // MDR <- M
+            25 => {
+                self.datapath.mdr = self.memory.read(self.datapath.mar, &mut self.console)?;
+                27
+            }
+            // DR <- MDR, set CC
+            27 => {
+                self.registers.set(dr, self.datapath.mdr);
+                self.registers.update_flags(dr);
+                FETCH_STATE
+            }
+            // LEA: DR <- PC + off9, set CC
+            14 => {
+                self.registers.set(dr, pc_offset9);
+                self.registers.update_flags(dr);
+                FETCH_STATE
+            }
+            // ST: MAR <- PC + off9
+            3 => {
+                self.datapath.mar = pc_offset9;
+                23
+            }
+            // STR: MAR <- BaseR + off6
+            7 => {
+                self.datapath.mar = base_offset6;
+                23
+            }
+            // STI: MAR <- PC + off9
+            11 => {
+                self.datapath.mar = pc_offset9;
+                29
+            }
+            // MDR <- M
+            29 => {
+                self.datapath.mdr = self.memory.read(self.datapath.mar, &mut self.console)?;
+                31
+            }
+            // MAR <- MDR
+            31 => {
+                self.datapath.mar = self.datapath.mdr;
+                23
+            }
+            // MDR <- SR
+            23 => {
+                self.datapath.mdr = self.registers.get(dr);
+                16
+            }
+            // M[MAR] <- MDR
+            16 => {
+                self.memory.write(self.datapath.mar, self.datapath.mdr);
+                FETCH_STATE
+            }
+            // TRAP: MAR <- ZEXT(trapvect8)
+            15 => {
+                self.datapath.mar = ir & 0xFF;
+                28
+            }
+            // MDR <- M, R7 <- PC
+            28 => {
+                self.datapath.mdr = self.memory.read(self.datapath.mar, &mut self.console)?;
+                self.registers.set(Register::R7, pc);
+                30
+            }
+            // The service routine, on the host
+            30 => {
+                self.execute_trap((ir & 0xFF) as u8)?;
+                FETCH_STATE
+            }
+            // RTI: MAR <- R6, [PSR[15]]
+            8 => {
+                self.datapath.mar = self.registers.get(Register::R6);
+                if self.registers.user_mode() {
+                    44
+                } else {
+                    36
+                }
+            }
+            // Privilege violation
+            44 => return Err(Fault::IllegalOpcode(OpCode::RTI).into()),
+            // MDR <- M
+            36 => {
+                self.datapath.mdr = self.memory.read(self.datapath.mar, &mut self.console)?;
+                38
+            }
+            // PC <- MDR
+            38 => {
+                self.registers.set(Register::PC, self.datapath.mdr);
+                39
+            }
+            // MAR, R6 <- R6 + 1
+            39 => {
+                let sp = self.registers.get(Register::R6).wrapping_add(1);
+                self.registers.set(Register::R6, sp);
+                self.datapath.mar = sp;
+                40
+            }
+            // MDR <- M
+            40 => {
+                self.datapath.mdr = self.memory.read(self.datapath.mar, &mut self.console)?;
+                42
+            }
+            // PSR <- MDR
+            42 => {
+                self.registers.set_psr(self.datapath.mdr);
+                34
+            }
+            // R6 <- R6 + 1, [PSR[15]]
+            34 => {
+                self.registers.set(Register::R6, self.registers.get(Register::R6).wrapping_add(1));
+                if self.registers.user_mode() {
+                    51
+                } else {
+                    FETCH_STATE
+                }
+            }
+            // Saved.SSP <- R6, R6 <- Saved.USP
+            51 => {
+                self.registers.saved_ssp = self.registers.get(Register::R6);
+                self.registers.set(Register::R6, self.registers.saved_usp);
+                FETCH_STATE
+            }
+            // The reserved opcode
+            13 => return Err(Fault::IllegalOpcode(OpCode::RES).into()),
+            _ => unreachable!("the microsequencer has no state {}", state),
+        })
+    }
+}
@@ -2,39 +2,102 @@ mod memory;
 mod registers;
 mod instructions;
 mod opcodes;
+mod config;
+mod snapshot;
+mod fault;
+mod decode;
+mod translate;
+mod stats;
+mod display;
+mod bus;
+mod devices;
+mod interrupt;
+mod cycles;
+mod microcode;
 
 use std::fs::File;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
 use std::path::Path;
+use std::time::Instant;
 
 pub use self::memory::*;
 pub use self::registers::*;
 pub use self::opcodes::*;
 pub use self::instructions::*;
+pub use self::config::*;
+pub use self::snapshot::*;
+pub use self::fault::*;
+pub use self::decode::*;
+pub use self::stats::*;
+pub use self::bus::*;
+pub use self::devices::*;
+pub use self::interrupt::*;
+pub use self::cycles::*;
+pub use self::microcode::*;
 
 use crate::io::console::Console;
+use crate::io::serial::SerialLink;
+use self::translate::BlockCache;
+use self::display::DisplayState;
 
 /// Memory size: 2^16 locations
 pub const MEMORY_SIZE: usize = 1 << 16;
 /// Default program start location
 pub const PC_START: u16 = 0x3000;
+/// Instructions executed between checks of the wall-clock timeout and of
+/// output waiting to be flushed
+const CHECK_INTERVAL: u64 = 4096;
+
+/// Why `LC3::run` stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The program executed HALT
+    Halted,
+    /// `Config::max_instructions` instructions were executed
+    InstructionLimit,
+    /// `Config::timeout` elapsed
+    Timeout,
+}
 
 /// LC-3 Virtual Machine
 pub struct LC3 {
     pub memory: Memory,
     pub registers: Registers,
     pub running: bool,
+    /// Number of instructions executed so far
+    pub instruction_count: u64,
+    pub config: Config,
     console: Console,
+    trace: Option<Box<dyn Write + Send>>,
+    blocks: BlockCache,
+    stats: Stats,
+    display: DisplayState,
+    files: OpenFiles,
+    datapath: Datapath,
 }
 
 impl LC3 {
     /// Creates a new LC-3 VM instance
     pub fn new() -> Self {
+        Self::with_console(Console::new())
+    }
+
+    /// Creates a new LC-3 VM instance that performs its I/O through `console`
+    pub fn with_console(console: Console) -> Self {
         let mut vm = LC3 {
             memory: Memory::new(),
             registers: Registers::new(),
             running: false,
-            console: Console::new(),
+            instruction_count: 0,
+            config: Config::default(),
+            console,
+            trace: None,
+            blocks: BlockCache::default(),
+            stats: Stats::default(),
+            display: DisplayState::default(),
+            files: OpenFiles::default(),
+            datapath: Datapath::default(),
         };
 
         vm.registers.set(Register::PC, PC_START);
@@ -45,14 +108,19 @@ impl LC3 {
 
     /// Loads a program from a binary file
     pub fn read_image_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        let mut file = File::open(path)?;
+        self.read_image(File::open(path)?)
+    }
+
+    /// Loads a program image: a big-endian origin word followed by the words to place there.
+    /// Words that would fall past the end of memory are ignored.
+    pub fn read_image<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
         let mut buffer = [0; 2];
 
-        file.read_exact(&mut buffer)?;
+        reader.read_exact(&mut buffer)?;
         let origin = u16::from_be_bytes(buffer);
 
         let mut i = origin as usize;
-        while let Ok(_) = file.read_exact(&mut buffer) {
+        while i < MEMORY_SIZE && reader.read_exact(&mut buffer).is_ok() {
             self.memory.write(i as u16, u16::from_be_bytes(buffer));
             i += 1;
         }
@@ -60,20 +128,232 @@ impl LC3 {
         Ok(())
     }
 
-    /// Runs the VM until halted
-    pub fn run(&mut self) -> io::Result<()> {
+    /// Maps a custom device at `addresses` in the device page (xFE00-xFFFF).
+    /// Fails if the range leaves the page or overlaps a device already attached,
+    /// such as the keyboard at xFE00-xFE02 or the timer at xFE08-xFE0C.
+    pub fn attach_device(&mut self, addresses: RangeInclusive<u16>, device: Box<dyn Device>) -> io::Result<()> {
+        self.memory.bus.attach(addresses, device)
+    }
+
+    /// Attaches the optional devices enabled in `config` that are not attached
+    /// yet. `run` and `load_snapshot` call this, so it is only needed before
+    /// stepping a machine that was neither run nor loaded from a snapshot.
+    pub fn attach_configured_devices(&mut self) -> io::Result<()> {
+        for (addresses, device) in self.configured_devices() {
+            self.attach_device(addresses, device)?;
+        }
+        if let Some(config) = &self.config.uart {
+            if !self.memory.bus.devices().any(|(_, device)| device.name() == "uart") {
+                let uart = Uart::new(config.address, SerialLink::open(&config.backend)?);
+                self.attach_device(uart.addresses(), Box::new(uart))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The devices enabled in `config` that are not attached yet, apart from the
+    /// UART, whose link is only opened when it is attached
+    fn configured_devices(&self) -> Vec<(RangeInclusive<u16>, Box<dyn Device>)> {
+        let attached = |name: &str| self.memory.bus.devices().any(|(_, device)| device.name() == name);
+        let mut devices: Vec<(RangeInclusive<u16>, Box<dyn Device>)> = Vec::new();
+        if self.config.random && !attached("random") {
+            let random = match self.config.random_seed {
+                Some(seed) => Random::new(seed),
+                None => Random::from_clock(),
+            };
+            let rndr = MemoryMappedRegister::RNDR as u16;
+            devices.push((rndr..=rndr, Box::new(random)));
+        }
+        if self.config.clock && !attached("clock") {
+            let registers = MemoryMappedRegister::CLKS as u16..=MemoryMappedRegister::RTCS as u16;
+            devices.push((registers, Box::new(Clock::new())));
+        }
+        devices
+    }
+
+    /// Runs the VM until it halts or reaches a limit set in `config`.
+    /// The console is restored afterwards even when execution fails.
+    pub fn run(&mut self) -> io::Result<StopReason> {
+        self.begin_run()?;
+        let result = self.run_until_stopped(self.config.max_instructions, Instant::now());
+        self.end_run(result)
+    }
+
+    /// Attaches the configured devices and sets up the console, as `run` does
+    /// before executing anything
+    pub(crate) fn begin_run(&mut self) -> io::Result<()> {
+        self.attach_configured_devices()?;
         self.running = true;
-        self.console.setup()?;
+        self.console.setup(self.config.ctrl_c)
+    }
+
+    /// Finishes the display and restores the console after `begin_run`,
+    /// returning the first error, starting with `result`'s
+    pub(crate) fn end_run(&mut self, result: io::Result<StopReason>) -> io::Result<StopReason> {
+        let display = self.finish_display();
+        let cleanup = self.console.cleanup();
+
+        let reason = result?;
+        display?;
+        cleanup?;
+        Ok(reason)
+    }
+
+    /// Executes until the machine halts, `budget` more instructions have run,
+    /// or `config.timeout` has passed since `start_time`
+    pub(crate) fn run_until_stopped(&mut self, budget: Option<u64>, start_time: Instant) -> io::Result<StopReason> {
+        let start_count = self.instruction_count;
 
-        while self.running {
-            let pc = self.registers.get(Register::PC);
-            self.registers.set(Register::PC, pc + 1);
-            let instr = self.memory.read(pc, &mut self.console)?;
+        loop {
+            if !self.running {
+                return Ok(StopReason::Halted);
+            }
 
-            self.execute_instruction(instr)?;
+            let executed = self.instruction_count - start_count;
+            if budget.is_some_and(|max| executed >= max) {
+                return Ok(StopReason::InstructionLimit);
+            }
+            if executed.is_multiple_of(CHECK_INTERVAL) {
+                if self.config.timeout.is_some_and(|timeout| start_time.elapsed() >= timeout) {
+                    return Ok(StopReason::Timeout);
+                }
+                self.draw_display(false)?;
+                self.console.flush_stale()?;
+            }
+
+            if self.config.engine == Engine::Translated && self.trace.is_none() && self.datapath.state == FETCH_STATE {
+                self.run_blocks(self.block_budget(executed, budget))?;
+            } else {
+                self.step()?;
+            }
+
+            if let Some(checkpoint) = &self.config.checkpoint {
+                if self.running && self.instruction_count.is_multiple_of(checkpoint.interval.max(1)) {
+                    self.save_snapshot_file(&checkpoint.path)?;
+                }
+            }
+            if let Some(display) = &self.config.display {
+                if self.instruction_count.is_multiple_of(display.frame_interval.max(1)) {
+                    self.save_frame()?;
+                }
+            }
         }
+    }
 
-        self.console.cleanup()?;
+    /// How many instructions can run as translated blocks before the loop in
+    /// `run_until_stopped` must check a limit, flush output, or save a checkpoint or frame
+    fn block_budget(&self, executed: u64, limit: Option<u64>) -> u64 {
+        let mut budget = CHECK_INTERVAL - executed % CHECK_INTERVAL;
+        if let Some(max) = limit {
+            budget = budget.min(max - executed);
+        }
+        if let Some(checkpoint) = &self.config.checkpoint {
+            let interval = checkpoint.interval.max(1);
+            budget = budget.min(interval - self.instruction_count % interval);
+        }
+        if let Some(display) = &self.config.display {
+            let interval = display.frame_interval.max(1);
+            budget = budget.min(interval - self.instruction_count % interval);
+        }
+        budget
+    }
+
+    /// Fetches and executes a single instruction, or finishes the one `microstep` started
+    pub fn step(&mut self) -> io::Result<()> {
+        if self.config.engine == Engine::Microcoded || self.datapath.state != FETCH_STATE {
+            return self.step_microcoded();
+        }
+        self.console.set_instruction_count(self.instruction_count);
+
+        let pc = self.registers.get(Register::PC);
+        self.registers.set(Register::PC, pc.wrapping_add(1));
+        let (word, instruction) = match self.config.engine {
+            Engine::Interpreter => {
+                let word = self.memory.read(pc, &mut self.console)?;
+                (word, Instruction::decode(word))
+            }
+            Engine::Cached | Engine::Translated => {
+                let instruction = self.memory.fetch(pc, &mut self.console)?;
+                (self.memory.get_ptr(pc)[0], instruction)
+            }
+            Engine::Microcoded => unreachable!("microcoded instructions run through step_microcoded"),
+        };
+
+        let result = self.execute(instruction);
+        if self.trace.is_some() {
+            self.write_trace(pc, word)?;
+        }
+        result?;
+        self.instruction_count += 1;
+        self.stats.opcodes[instruction.opcode() as usize] += 1;
+        self.poll_interrupts();
+        Ok(())
+    }
+
+    /// Returns the counters collected since the VM was created or the last `reset_stats`
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats {
+            mmio_reads: self.memory.mmio_reads,
+            mmio_writes: self.memory.mmio_writes,
+            ..self.stats.clone()
+        };
+        if let Some(model) = &self.config.cycles {
+            stats.cycles = model.cycles(&stats);
+        }
+        stats
+    }
+
+    /// Sets every counter reported by `stats` back to zero
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+        self.memory.mmio_reads = 0;
+        self.memory.mmio_writes = 0;
+    }
+
+    /// Writes a line to `writer` for every instruction executed from now on:
+    /// its address, encoding and disassembly, then the registers afterwards
+    pub fn trace_to(&mut self, writer: Box<dyn Write + Send>) {
+        self.trace = Some(writer);
+    }
+
+    /// Traces the instruction `word`, fetched from `pc` before it ran, since
+    /// running it may have overwritten it
+    fn write_trace(&mut self, pc: u16, word: u16) -> io::Result<()> {
+        let mut line = format!("x{:04X}  x{:04X}  {:<18}", pc, word, Instruction::decode(word).to_string());
+        for index in 0..8 {
+            let register = Register::from_u16(index);
+            line += &format!(" {:?}=x{:04X}", register, self.registers.get(register));
+        }
+        line += &format!(" CC={}", condition_name(self.registers.get_condition_flag()));
+
+        if let Some(trace) = &mut self.trace {
+            writeln!(trace, "{}", line)?;
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Returns the console the VM performs its I/O through
+    pub fn console(&self) -> &Console {
+        &self.console
+    }
+
+    /// Returns the console mutably, e.g. to start recording input
+    pub fn console_mut(&mut self) -> &mut Console {
+        &mut self.console
+    }
+}
+
+/// Names the condition codes set in a COND register value, e.g. `Z` or `NP`
+pub fn condition_name(cond: u16) -> String {
+    [(CondFlag::NEG, 'N'), (CondFlag::ZRO, 'Z'), (CondFlag::POS, 'P')]
+        .into_iter()
+        .filter(|&(flag, _)| cond & flag as u16 != 0)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+impl Default for LC3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 /// LC-3 Operation Codes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpCode {
@@ -9,7 +11,7 @@ pub enum OpCode {
     AND,     // Bitwise and
     LDR,     // Load register
     STR,     // Store register
-    RTI,     // Unused
+    RTI,     // Return from interrupt
     NOT,     // Bitwise not
     LDI,     // Load indirect
     STI,     // Store indirect
@@ -30,40 +32,81 @@ pub enum TrapCode {
     HALT = 0x25,  // Halt the program
 }
 
+/// Console traps that some course toolchains add to the standard six. They
+/// have no fixed vectors; `Config::extended_traps` assigns them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExtendedTrap {
+    PRINTD, // Print R0 as a signed decimal
+    READD,  // Read a signed decimal into R0, echoed
+    CLEAR,  // Clear the screen
+    CURSOR, // Move the cursor to row R0, column R1, counting from 0
+}
+
+impl ExtendedTrap {
+    /// Every extended trap with the vector it has unless configured otherwise
+    pub const DEFAULTS: [(u8, ExtendedTrap); 4] = [
+        (0x26, ExtendedTrap::PRINTD),
+        (0x27, ExtendedTrap::READD),
+        (0x28, ExtendedTrap::CLEAR),
+        (0x29, ExtendedTrap::CURSOR),
+    ];
+}
+
+impl FromStr for ExtendedTrap {
+    type Err = String;
+
+    /// Parses an extended trap name (`PRINTD`, `READD`, `CLEAR` or `CURSOR`), ignoring case
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_uppercase().as_str() {
+            "PRINTD" => Ok(ExtendedTrap::PRINTD),
+            "READD" => Ok(ExtendedTrap::READD),
+            "CLEAR" => Ok(ExtendedTrap::CLEAR),
+            "CURSOR" => Ok(ExtendedTrap::CURSOR),
+            _ => Err(format!("Unknown extended trap: {}", name)),
+        }
+    }
+}
+
+/// Opcodes indexed by the top four bits of an instruction
+const OPCODES: [OpCode; 16] = [
+    OpCode::BR,
+    OpCode::ADD,
+    OpCode::LD,
+    OpCode::ST,
+    OpCode::JSR,
+    OpCode::AND,
+    OpCode::LDR,
+    OpCode::STR,
+    OpCode::RTI,
+    OpCode::NOT,
+    OpCode::LDI,
+    OpCode::STI,
+    OpCode::JMP,
+    OpCode::RES,
+    OpCode::LEA,
+    OpCode::TRAP,
+];
+
 impl From<u8> for OpCode {
+    /// Decodes the low four bits of `value`; every pattern is a valid opcode
     fn from(value: u8) -> Self {
-        match value {
-            0 => OpCode::BR,
-            1 => OpCode::ADD,
-            2 => OpCode::LD,
-            3 => OpCode::ST,
-            4 => OpCode::JSR,
-            5 => OpCode::AND,
-            6 => OpCode::LDR,
-            7 => OpCode::STR,
-            8 => OpCode::RTI,
-            9 => OpCode::NOT,
-            10 => OpCode::LDI,
-            11 => OpCode::STI,
-            12 => OpCode::JMP,
-            13 => OpCode::RES,
-            14 => OpCode::LEA,
-            15 => OpCode::TRAP,
-            _ => panic!("Invalid opcode: {}", value),
-        }
+        OPCODES[(value & 0xF) as usize]
     }
 }
 
-impl From<u16> for TrapCode {
-    fn from(value: u16) -> Self {
+impl TryFrom<u16> for TrapCode {
+    /// The unrecognized trap vector
+    type Error = u16;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
         match value {
-            0x20 => TrapCode::GETC,
-            0x21 => TrapCode::OUT,
-            0x22 => TrapCode::PUTS,
-            0x23 => TrapCode::IN,
-            0x24 => TrapCode::PUTSP,
-            0x25 => TrapCode::HALT,
-            _ => panic!("Invalid trap code: {}", value),
+            0x20 => Ok(TrapCode::GETC),
+            0x21 => Ok(TrapCode::OUT),
+            0x22 => Ok(TrapCode::PUTS),
+            0x23 => Ok(TrapCode::IN),
+            0x24 => Ok(TrapCode::PUTSP),
+            0x25 => Ok(TrapCode::HALT),
+            _ => Err(value),
         }
     }
-}
\ No newline at end of file
+}
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 /// LC-3 Register definitions
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Register {
@@ -14,6 +16,27 @@ pub enum Register {
     COUNT, // Count of registers
 }
 
+impl FromStr for Register {
+    type Err = String;
+
+    /// Parses a register name (`R0`-`R7`, `PC` or `COND`), ignoring case
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_uppercase().as_str() {
+            "R0" => Ok(Register::R0),
+            "R1" => Ok(Register::R1),
+            "R2" => Ok(Register::R2),
+            "R3" => Ok(Register::R3),
+            "R4" => Ok(Register::R4),
+            "R5" => Ok(Register::R5),
+            "R6" => Ok(Register::R6),
+            "R7" => Ok(Register::R7),
+            "PC" => Ok(Register::PC),
+            "COND" => Ok(Register::COND),
+            _ => Err(format!("Unknown register: {}", name)),
+        }
+    }
+}
+
 /// LC-3 Condition flags
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CondFlag {
@@ -22,16 +45,32 @@ pub enum CondFlag {
     NEG = 1 << 2, // Negative
 }
 
+/// PSR bit 15: set while the processor runs in user mode
+pub const PSR_USER: u16 = 1 << 15;
+/// PSR bits 10-8: the priority of the running program
+const PSR_PRIORITY: u16 = 0x0700;
+/// Initial supervisor stack pointer; the stack grows down from below user programs
+pub const SUPERVISOR_STACK: u16 = 0x3000;
+
 /// Register file for the LC-3 VM
 pub struct Registers {
     data: [u16; Register::COUNT as usize],
+    /// Privilege and priority bits of the PSR; its condition codes are kept in COND
+    status: u16,
+    /// R6 for supervisor mode, kept here while in user mode
+    pub saved_ssp: u16,
+    /// R6 for user mode, kept here while in supervisor mode
+    pub saved_usp: u16,
 }
 
 impl Registers {
-    /// Creates a new register file with zeroed registers
+    /// Creates a new register file with zeroed registers, in user mode at priority 0
     pub fn new() -> Self {
         Registers {
             data: [0; Register::COUNT as usize],
+            status: PSR_USER,
+            saved_ssp: SUPERVISOR_STACK,
+            saved_usp: 0,
         }
     }
 
@@ -69,4 +108,35 @@ impl Registers {
     pub fn get_condition_flag(&self) -> u16 {
         self.data[Register::COND as usize]
     }
-}
\ No newline at end of file
+
+    /// The processor status register: privilege (bit 15), priority (bits 10-8)
+    /// and condition codes (bits 2-0)
+    pub fn psr(&self) -> u16 {
+        self.status | self.get_condition_flag()
+    }
+
+    /// Sets privilege, priority and condition codes from a PSR value. Condition
+    /// codes that are not exactly one of N, Z and P, as a service routine may
+    /// leave in the PSR it returns to, load as Z.
+    pub fn set_psr(&mut self, psr: u16) {
+        self.status = psr & (PSR_USER | PSR_PRIORITY);
+        let cond = psr & 0x7;
+        self.data[Register::COND as usize] = if cond.is_power_of_two() { cond } else { CondFlag::ZRO as u16 };
+    }
+
+    /// Whether the processor runs in user mode
+    pub fn user_mode(&self) -> bool {
+        self.status & PSR_USER != 0
+    }
+
+    /// Priority of the running program, 0-7
+    pub fn priority(&self) -> u16 {
+        (self.status & PSR_PRIORITY) >> 8
+    }
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,254 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::{Datapath, MemoryMappedRegister, FETCH_STATE, IO_PAGE_START, LC3, MEMORY_SIZE, PSR_USER, Register, SUPERVISOR_STACK};
+
+/// Bytes every snapshot file starts with
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"LC3SNAP\0";
+/// Current snapshot format version
+pub const SNAPSHOT_VERSION: u16 = 3;
+
+/// Snapshot layout (all integers big-endian):
+///
+/// | field             | size                         |
+/// |-------------------|------------------------------|
+/// | magic             | 8 bytes, `LC3SNAP\0`         |
+/// | version           | u16                          |
+/// | register count    | u16                          |
+/// | registers         | u16 each, R0-R7, PC, COND    |
+/// | PSR               | u16                          |
+/// | saved SSP, USP    | u16 each                     |
+/// | running           | u8                           |
+/// | instruction count | u64                          |
+/// | pending input     | u32 length, then the bytes   |
+/// | memory            | 65,536 u16 words             |
+/// | device count      | u16                          |
+/// | devices           | u8 name length, name, u32 state length, state |
+///
+/// Memory holds the value last read from or written to each device register;
+/// loading it does not write to the devices. The device section holds what
+/// `Device::save` returns for every device with state, and is restored after
+/// memory; state for devices that are neither attached nor enabled in `Config`
+/// is an error. Version 2 snapshots instead keep the
+/// time left on the timer after the instruction count, and version 1 snapshots
+/// lack the PSR, stack pointers and timer, loading as a machine in user mode
+/// with the timer stopped. Both take the keyboard and timer registers from memory.
+impl LC3 {
+    /// Writes the complete machine state to `writer`. Fails in the middle of
+    /// an instruction started by `microstep`, since the datapath is not saved.
+    pub fn save_snapshot<W: Write>(&self, writer: W) -> io::Result<()> {
+        if self.datapath.state != FETCH_STATE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot save a snapshot in the middle of a microcoded instruction",
+            ));
+        }
+        let mut writer = BufWriter::new(writer);
+
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_be_bytes())?;
+
+        writer.write_all(&(Register::COUNT as u16).to_be_bytes())?;
+        for register in REGISTERS {
+            writer.write_all(&self.registers.get(register).to_be_bytes())?;
+        }
+        writer.write_all(&self.registers.psr().to_be_bytes())?;
+        writer.write_all(&self.registers.saved_ssp.to_be_bytes())?;
+        writer.write_all(&self.registers.saved_usp.to_be_bytes())?;
+
+        writer.write_all(&[self.running as u8])?;
+        writer.write_all(&self.instruction_count.to_be_bytes())?;
+
+        let pending = self.console.pending_input();
+        writer.write_all(&(pending.len() as u32).to_be_bytes())?;
+        writer.write_all(&pending)?;
+
+        for word in self.memory.get_ptr(0) {
+            writer.write_all(&word.to_be_bytes())?;
+        }
+
+        let devices = self.memory.bus.save(self.instruction_count);
+        writer.write_all(&(devices.len() as u16).to_be_bytes())?;
+        for (name, state) in devices {
+            writer.write_all(&[name.len() as u8])?;
+            writer.write_all(name.as_bytes())?;
+            writer.write_all(&(state.len() as u32).to_be_bytes())?;
+            writer.write_all(&state)?;
+        }
+
+        writer.flush()
+    }
+
+    /// Replaces the machine state with a snapshot read from `reader`
+    pub fn load_snapshot<R: Read>(&mut self, reader: R) -> io::Result<()> {
+        let mut reader = BufReader::new(reader);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(invalid("Not an LC-3 snapshot".to_string()));
+        }
+
+        let version = read_u16(&mut reader)?;
+        if !(1..=SNAPSHOT_VERSION).contains(&version) {
+            return Err(invalid(format!("Unsupported snapshot version: {}", version)));
+        }
+
+        let count = read_u16(&mut reader)?;
+        if count != Register::COUNT as u16 {
+            return Err(invalid(format!("Snapshot has {} registers, expected {}", count, Register::COUNT as u16)));
+        }
+        let mut registers = [0; Register::COUNT as usize];
+        for register in registers.iter_mut() {
+            *register = read_u16(&mut reader)?;
+        }
+        let mut psr = PSR_USER | registers[Register::COND as usize];
+        let (mut saved_ssp, mut saved_usp) = (SUPERVISOR_STACK, 0);
+        if version >= 2 {
+            psr = read_u16(&mut reader)?;
+            saved_ssp = read_u16(&mut reader)?;
+            saved_usp = read_u16(&mut reader)?;
+        }
+
+        let mut running = [0; 1];
+        reader.read_exact(&mut running)?;
+
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+
+        let mut timer = [0; 8];
+        if version == 2 {
+            reader.read_exact(&mut timer)?;
+        }
+
+        let mut length = [0; 4];
+        reader.read_exact(&mut length)?;
+        let mut pending = Vec::new();
+        reader.by_ref().take(u32::from_be_bytes(length) as u64).read_to_end(&mut pending)?;
+        if pending.len() != u32::from_be_bytes(length) as usize {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated snapshot"));
+        }
+
+        let mut memory = vec![0; MEMORY_SIZE];
+        for word in memory.iter_mut() {
+            *word = read_u16(&mut reader)?;
+        }
+
+        let devices = if version >= 3 {
+            read_devices(&mut reader)?
+        } else {
+            legacy_devices(&memory, u64::from_be_bytes(timer))
+        };
+
+        // Devices enabled in the config are only attached once their state is known to be valid
+        let configured = self.configured_devices();
+        for (name, state) in &devices {
+            match configured.iter().find(|(_, device)| device.name() == name) {
+                Some((_, device)) => device.validate(state)?,
+                None => self.memory.bus.validate(name, state)?,
+            }
+        }
+        self.attach_configured_devices()?;
+
+        // Only commit once the whole snapshot has been read successfully
+        for (register, value) in REGISTERS.iter().zip(registers) {
+            self.registers.set(*register, value);
+        }
+        self.registers.set_psr(psr);
+        self.registers.saved_ssp = saved_ssp;
+        self.registers.saved_usp = saved_usp;
+        self.running = running[0] != 0;
+        self.datapath = Datapath::default();
+        self.instruction_count = u64::from_be_bytes(count);
+        self.console.set_pending_input(&pending);
+        for (address, word) in memory.iter().enumerate().take(IO_PAGE_START as usize) {
+            self.memory.write(address as u16, *word);
+        }
+        for (address, word) in memory.iter().enumerate().skip(IO_PAGE_START as usize) {
+            self.memory.set_mirror(address as u16, *word);
+        }
+        for (name, state) in devices {
+            self.memory.bus.restore(&name, &state, self.instruction_count)?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves a snapshot to `path`, replacing any existing file only once the new one is complete
+    pub fn save_snapshot_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+
+        self.save_snapshot(File::create(&partial)?)?;
+        fs::rename(&partial, path)
+    }
+
+    /// Loads a snapshot previously written by `save_snapshot_file`
+    pub fn load_snapshot_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.load_snapshot(File::open(path)?)
+    }
+}
+
+/// Returns true if `bytes` begin with the snapshot magic
+pub fn is_snapshot(bytes: &[u8]) -> bool {
+    bytes.starts_with(SNAPSHOT_MAGIC)
+}
+
+const REGISTERS: [Register; Register::COUNT as usize] = [
+    Register::R0,
+    Register::R1,
+    Register::R2,
+    Register::R3,
+    Register::R4,
+    Register::R5,
+    Register::R6,
+    Register::R7,
+    Register::PC,
+    Register::COND,
+];
+
+fn read_devices<R: Read>(reader: &mut R) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let count = read_u16(reader)?;
+    let mut devices = Vec::new();
+    for _ in 0..count {
+        let mut length = [0; 1];
+        reader.read_exact(&mut length)?;
+        let mut name = vec![0; length[0] as usize];
+        reader.read_exact(&mut name)?;
+        let name = String::from_utf8(name).map_err(|_| invalid("Invalid device name".to_string()))?;
+
+        let mut length = [0; 4];
+        reader.read_exact(&mut length)?;
+        let mut state = Vec::new();
+        reader.by_ref().take(u32::from_be_bytes(length) as u64).read_to_end(&mut state)?;
+        if state.len() != u32::from_be_bytes(length) as usize {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated snapshot"));
+        }
+        devices.push((name, state));
+    }
+    Ok(devices)
+}
+
+/// Device state for snapshots from before devices saved their own, built from
+/// the registers in memory and the time left on the timer
+fn legacy_devices(memory: &[u16], timer_remaining: u64) -> Vec<(String, Vec<u8>)> {
+    let words = |registers: &[MemoryMappedRegister]| -> Vec<u8> {
+        registers.iter().flat_map(|register| memory[*register as usize].to_be_bytes()).collect()
+    };
+    let keyboard = words(&[MemoryMappedRegister::KBSR, MemoryMappedRegister::KBDR]);
+    let mut timer = words(&[MemoryMappedRegister::TMCR, MemoryMappedRegister::TMIR, MemoryMappedRegister::TMSR]);
+    timer.extend_from_slice(&timer_remaining.to_be_bytes());
+    vec![("keyboard".to_string(), keyboard), ("timer".to_string(), timer)]
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+    let mut buffer = [0; 2];
+    reader.read_exact(&mut buffer)?;
+    Ok(u16::from_be_bytes(buffer))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{file_trap_name, OpCode, TrapCode};
+
+/// Counters collected while the VM runs, read with `LC3::stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Instructions executed to completion, indexed by opcode
+    pub opcodes: [u64; 16],
+    /// TRAP instructions executed, by vector
+    pub traps: BTreeMap<u8, u64>,
+    /// Reads from the device page (xFE00-xFFFF)
+    pub mmio_reads: u64,
+    /// Writes to the device page (xFE00-xFFFF)
+    pub mmio_writes: u64,
+    /// Interrupts taken
+    pub interrupts: u64,
+    /// BR instructions that branched
+    pub branches_taken: u64,
+    /// Clock cycles spent according to `Config::cycles`; 0 without a cycle model
+    pub cycles: u64,
+}
+
+impl Stats {
+    /// Total number of instructions executed to completion
+    pub fn retired(&self) -> u64 {
+        self.opcodes.iter().sum()
+    }
+
+    /// Number of instructions with `opcode` executed to completion
+    pub fn count(&self, opcode: OpCode) -> u64 {
+        self.opcodes[opcode as usize]
+    }
+}
+
+/// A multi-line report listing every non-zero counter
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let retired = self.retired();
+        writeln!(f, "Instructions retired: {}", retired)?;
+        for value in 0..16u8 {
+            let opcode = OpCode::from(value);
+            let count = self.count(opcode);
+            if count > 0 {
+                let share = count as f64 * 100.0 / retired as f64;
+                writeln!(f, "  {:<5} {:>12}  {:>5.1}%", format!("{:?}", opcode), count, share)?;
+            }
+        }
+
+        if !self.traps.is_empty() {
+            writeln!(f, "Traps:")?;
+            for (&vector, count) in &self.traps {
+                let name = match TrapCode::try_from(vector as u16) {
+                    Ok(trap) => format!("{:?}", trap),
+                    Err(_) => file_trap_name(vector).unwrap_or("?").to_string(),
+                };
+                writeln!(f, "  x{:02X} {:<6} {:>11}", vector, name, count)?;
+            }
+        }
+
+        if self.interrupts > 0 {
+            writeln!(f, "Interrupts: {}", self.interrupts)?;
+        }
+        write!(f, "Device registers: {} reads, {} writes", self.mmio_reads, self.mmio_writes)?;
+        if self.cycles > 0 {
+            write!(f, "\nCycles: {} ({:.2} per instruction)", self.cycles, self.cycles as f64 / retired.max(1) as f64)?;
+        }
+        Ok(())
+    }
+}
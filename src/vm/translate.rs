@@ -0,0 +1,200 @@
+//! Translation of basic blocks into threaded code for `Engine::Translated`.
+//!
+//! A block runs from its entry address up to and including the first
+//! instruction that can change the flow of control: BR, JMP, JSR, JSRR, TRAP,
+//! RTI or the reserved opcode. Each instruction becomes a closure with its
+//! operands already decoded, so executing a block is a series of calls with
+//! no fetching or decoding, and each block remembers the block that followed
+//! it last time so loops go from block to block without a lookup.
+//!
+//! Blocks never include the I/O page; instructions there are executed one at
+//! a time by `LC3::step`, and loads from device registers go through
+//! `Memory::read` exactly as in the other engines. Translated addresses are
+//! marked in `Memory`, and a write to any of them discards every block, even
+//! when it rewrites a later instruction of the block that is running.
+//!
+//! Interrupts are polled between blocks. A block is cut short where a device
+//! next needs a tick or after an instruction that reads or writes a device, so
+//! interrupts are taken after the same instruction as with the other engines.
+
+use std::io;
+
+use super::{Instruction, OpCode, Register, IO_PAGE_START, LC3, MEMORY_SIZE};
+
+/// Most instructions translated into a single block
+const MAX_BLOCK_LEN: usize = 64;
+
+/// One instruction with its operands bound
+type Op = Box<dyn Fn(&mut LC3) -> io::Result<()> + Send>;
+
+struct Block {
+    start: u16,
+    /// Each instruction's opcode, for `Stats`, and its translation
+    ops: Vec<(OpCode, Op)>,
+    /// Start address and index of the block that ran after this one last time
+    successor: Option<(u16, u32)>,
+}
+
+/// Translated blocks, looked up by the address they start at
+#[derive(Default)]
+pub(crate) struct BlockCache {
+    blocks: Vec<Block>,
+    /// One more than the index of the block starting at each address, 0 for none.
+    /// Allocated on first use.
+    entries: Box<[u32]>,
+    /// `Memory::code_writes` when the blocks were translated
+    code_writes: u64,
+}
+
+impl BlockCache {
+    /// Drops every block and unmarks the code it covered
+    fn clear(&mut self, vm: &mut LC3) {
+        for block in self.blocks.drain(..) {
+            self.entries[block.start as usize] = 0;
+            for offset in 0..block.ops.len() {
+                vm.memory.set_code(block.start.wrapping_add(offset as u16), false);
+            }
+        }
+        self.code_writes = vm.memory.code_writes();
+    }
+
+    /// Returns the index of the block starting at `start`, translating it if needed
+    fn block_at(&mut self, start: u16, vm: &mut LC3) -> u32 {
+        if let Some(index) = self.entries[start as usize].checked_sub(1) {
+            return index;
+        }
+
+        let mut ops = Vec::new();
+        let mut address = start;
+        while address < IO_PAGE_START && ops.len() < MAX_BLOCK_LEN {
+            let instruction = Instruction::decode(vm.memory.get_ptr(address)[0]);
+            vm.memory.set_code(address, true);
+            ops.push((instruction.opcode(), compile(instruction)));
+            if ends_block(instruction) {
+                break;
+            }
+            address += 1;
+        }
+
+        let index = self.blocks.len() as u32;
+        self.blocks.push(Block { start, ops, successor: None });
+        self.entries[start as usize] = index + 1;
+        index
+    }
+}
+
+/// Whether execution may continue anywhere but the next address
+fn ends_block(instruction: Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Br { .. }
+            | Instruction::Jmp { .. }
+            | Instruction::Jsr { .. }
+            | Instruction::Jsrr { .. }
+            | Instruction::Trap { .. }
+            | Instruction::Rti
+            | Instruction::Reserved(_)
+    )
+}
+
+/// Binds an instruction's operands to the handler that executes it
+fn compile(instruction: Instruction) -> Op {
+    match instruction {
+        Instruction::Add { dr, sr1, op2 } => infallible(move |vm| vm.execute_add(dr, sr1, op2)),
+        Instruction::And { dr, sr1, op2 } => infallible(move |vm| vm.execute_and(dr, sr1, op2)),
+        Instruction::Not { dr, sr } => infallible(move |vm| vm.execute_not(dr, sr)),
+        Instruction::Br { nzp, offset } => infallible(move |vm| vm.execute_br(nzp, offset)),
+        Instruction::Jmp { base } => infallible(move |vm| vm.execute_jmp(base)),
+        Instruction::Jsr { offset } => infallible(move |vm| vm.execute_jsr(offset)),
+        Instruction::Jsrr { base } => infallible(move |vm| vm.execute_jsrr(base)),
+        Instruction::Ld { dr, offset } => Box::new(move |vm| vm.execute_ld(dr, offset)),
+        Instruction::Ldi { dr, offset } => Box::new(move |vm| vm.execute_ldi(dr, offset)),
+        Instruction::Ldr { dr, base, offset } => Box::new(move |vm| vm.execute_ldr(dr, base, offset)),
+        Instruction::Lea { dr, offset } => infallible(move |vm| vm.execute_lea(dr, offset)),
+        Instruction::St { sr, offset } => infallible(move |vm| vm.execute_st(sr, offset)),
+        Instruction::Sti { sr, offset } => Box::new(move |vm| vm.execute_sti(sr, offset)),
+        Instruction::Str { sr, base, offset } => infallible(move |vm| vm.execute_str(sr, base, offset)),
+        Instruction::Trap { vector } => Box::new(move |vm| vm.execute_trap(vector)),
+        Instruction::Rti | Instruction::Reserved(_) => Box::new(move |vm| vm.execute(instruction)),
+    }
+}
+
+/// Wraps a handler that cannot fail
+fn infallible(handler: impl Fn(&mut LC3) + Send + 'static) -> Op {
+    Box::new(move |vm| {
+        handler(vm);
+        Ok(())
+    })
+}
+
+impl LC3 {
+    /// Executes up to `budget` instructions through translated blocks, stopping
+    /// early if the machine halts
+    pub(crate) fn run_blocks(&mut self, budget: u64) -> io::Result<()> {
+        let mut cache = std::mem::take(&mut self.blocks);
+        if cache.entries.is_empty() {
+            cache.entries = vec![0; MEMORY_SIZE].into_boxed_slice();
+        }
+        let result = self.run_blocks_with(&mut cache, budget);
+        self.blocks = cache;
+        result
+    }
+
+    fn run_blocks_with(&mut self, cache: &mut BlockCache, budget: u64) -> io::Result<()> {
+        let end = self.instruction_count.saturating_add(budget);
+        let mut previous: Option<u32> = None;
+
+        while self.running && self.instruction_count < end {
+            if cache.code_writes != self.memory.code_writes() {
+                cache.clear(self);
+                previous = None;
+            }
+
+            let pc = self.registers.get(Register::PC);
+            if pc >= IO_PAGE_START {
+                self.step()?;
+                previous = None;
+                continue;
+            }
+
+            let linked = previous.and_then(|index| cache.blocks[index as usize].successor);
+            let index = match linked {
+                Some((start, index)) if start == pc => index,
+                _ => {
+                    let index = cache.block_at(pc, self);
+                    if let Some(previous) = previous {
+                        cache.blocks[previous as usize].successor = Some((pc, index));
+                    }
+                    index
+                }
+            };
+
+            // Stop where a device next needs a tick, and after any instruction that
+            // touches a device, so interrupts come after the instruction `step` takes them
+            let ticks_left = self.memory.bus.next_tick().saturating_sub(self.instruction_count);
+            let limit = (end - self.instruction_count).min(ticks_left.max(1));
+            let accesses = self.memory.mmio_reads + self.memory.mmio_writes;
+
+            let block = &cache.blocks[index as usize];
+            let count = block.ops.len().min(limit as usize);
+            for (address, (opcode, op)) in (block.start..).zip(&block.ops[..count]) {
+                self.console.set_instruction_count(self.instruction_count);
+                self.registers.set(Register::PC, address.wrapping_add(1));
+                op(self)?;
+                self.instruction_count += 1;
+                self.stats.opcodes[*opcode as usize] += 1;
+                if cache.code_writes != self.memory.code_writes()
+                    || self.memory.mmio_reads + self.memory.mmio_writes != accesses
+                {
+                    break;
+                }
+            }
+            previous = Some(index);
+            if self.poll_interrupts() {
+                previous = None;
+            }
+        }
+
+        Ok(())
+    }
+}
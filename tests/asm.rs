@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use lc3_vm::asm::{assemble, disassemble, parse_word};
+use lc3_vm::image::{Format, Image};
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{Instruction, Register, LC3};
+
+#[test]
+fn assembles_every_instruction_form() {
+    let source = "
+        .ORIG x3000
+TOP     ADD R1, R2, R3
+        ADD R1, R2, #-16
+        AND R7, R0, #15
+        NOT R4, R5
+        BRnp TOP
+        BR TOP
+        NOP
+        JMP R3
+        RET
+        JSR TOP
+        JSRR R2
+        LD R0, DATA
+        LDI R1, DATA
+        LDR R2, R3, #-32
+        LEA R3, DATA
+        ST R4, DATA
+        STI R5, DATA
+        STR R6, R7, #31
+        TRAP x25
+        RTI
+        GETC
+        OUT
+        PUTS
+        IN
+        PUTSP
+        HALT
+DATA    .FILL TOP
+        .END
+        ADD R0, R0, R0 ; ignored after .END
+    ";
+
+    let assembly = assemble(source).unwrap();
+    assert_eq!(assembly.image.origin, 0x3000);
+    assert_eq!(
+        assembly.image.words,
+        [
+            0x1283, 0x12B0, 0x5E2F, 0x997F, 0x0BFB, 0x0FFA, 0x0000, 0xC0C0, 0xC1C0, 0x4FF6, 0x4080, 0x200E, 0xA20D,
+            0x64E0, 0xE60B, 0x380A, 0xBA09, 0x7DDF, 0xF025, 0x8000, 0xF020, 0xF021, 0xF022, 0xF023, 0xF024, 0xF025,
+            0x3000,
+        ]
+    );
+    assert_eq!(assembly.symbols["TOP"], 0x3000);
+    assert_eq!(assembly.symbols["DATA"], 0x301A);
+}
+
+#[test]
+fn assembles_data_directives() {
+    let assembly = assemble(".ORIG x4000\nA .BLKW 2\nB: .STRINGZ \"a\\n\\\"b\"\n.FILL b101\n.FILL #-1\n.END").unwrap();
+    assert_eq!(assembly.image.words, [0, 0, 'a' as u16, '\n' as u16, '"' as u16, 'b' as u16, 0, 5, 0xFFFF]);
+    assert_eq!(assembly.symbols["B"], 0x4002);
+}
+
+#[test]
+fn reports_errors_with_line_numbers() {
+    let cases = [
+        ("ADD R0, R0, #1", 1, "ADD before .ORIG"),
+        (".ORIG x3000\nADD R0, R0, #16", 2, "#16 does not fit in 5 signed bits"),
+        (".ORIG x3000\nLD R0, NOWHERE", 2, "Undefined label: NOWHERE"),
+        (".ORIG x3000\nX ADD R0, R0, R0\nX HALT", 3, "Duplicate label: X"),
+        (".ORIG x3000\nADD R0, R0", 2, "ADD expects 3 operand(s), found 2"),
+        (".ORIG x3000\nBR FAR\n.BLKW 300\nFAR HALT", 2, "FAR is out of range of a 9-bit offset"),
+        (".ORIG x3000\nLDR R8, R0, #0", 2, "Expected a register, found R8"),
+        (".ORIG x3000\n.STRINGZ \"open", 2, "Unterminated string"),
+    ];
+
+    for (source, line, message) in cases {
+        let err = assemble(source).unwrap_err();
+        assert_eq!((err.line, err.message.as_str()), (line, message), "{}", source);
+    }
+}
+
+#[test]
+fn every_canonical_encoding_disassembles_to_source_that_reassembles() {
+    let words: Vec<u16> = (0..=u16::MAX).collect();
+    let image = Image { origin: 0x0000, words };
+
+    let source = disassemble(&image, &BTreeMap::new());
+    assert_eq!(assemble(&source).unwrap().image, image);
+
+    for word in 0..=u16::MAX {
+        let instruction = Instruction::decode(word);
+        assert_eq!(Instruction::decode(instruction.encode()), instruction, "x{:04X}", word);
+    }
+}
+
+#[test]
+fn parses_lc3_number_notation() {
+    assert_eq!(parse_word("x1F"), Ok(0x1F));
+    assert_eq!(parse_word("#-1"), Ok(0xFFFF));
+    assert_eq!(parse_word("-2"), Ok(0xFFFE));
+    assert_eq!(parse_word("b1010"), Ok(10));
+    assert_eq!(parse_word("65535"), Ok(0xFFFF));
+    assert!(parse_word("65536").is_err());
+    assert!(parse_word("xG").is_err());
+}
+
+#[test]
+fn image_formats_round_trip() {
+    let image = Image { origin: 0x3000, words: vec![0x1234, 0xF025, 0x0000] };
+    for format in [Format::Obj, Format::Hex, Format::Bin] {
+        assert_eq!(Image::parse(&image.to_bytes(format), format).unwrap(), image);
+    }
+
+    assert_eq!(image.to_bytes(Format::Hex), b"3000\n1234\nF025\n0000\n");
+    assert_eq!(Format::from_path("a/b.HEX"), Format::Hex);
+    assert_eq!(Format::from_path("b.bin"), Format::Bin);
+    assert_eq!(Format::from_path("b.obj"), Format::Obj);
+    assert!(Image::parse(b"3000\n123\n", Format::Hex).is_err());
+}
+
+#[test]
+fn assembled_programs_run() {
+    let source = "
+        .ORIG x3000
+        LEA R0, MSG
+        PUTS
+        AND R1, R1, #0
+LOOP    ADD R1, R1, #1
+        ADD R2, R1, #-5
+        BRn LOOP
+        HALT
+MSG     .STRINGZ \"hi\"
+        .END
+    ";
+
+    let mut vm = LC3::with_console(Console::scripted(&[]));
+    vm.load(&assemble(source).unwrap().image);
+    vm.run().unwrap();
+
+    assert_eq!(vm.console().output(), b"hiHALT\n");
+    assert_eq!(vm.registers.get(Register::R1), 5);
+}
@@ -0,0 +1,163 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// Reads a key, adds R1 to it and stores the sum at x4000, leaving it in R0:
+/// GETC / ADD R0, R0, R1 / STI R0, PTR / HALT / PTR .FILL x4000
+const SOURCE: &str = "
+        .ORIG x3000
+        GETC
+        ADD R0, R0, R1
+        STI R0, PTR
+        HALT
+PTR     .FILL x4000
+        .END
+";
+
+fn lc3_vm(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lc3-vm"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn assembles_and_runs_with_presets() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("add.asm"), SOURCE).unwrap();
+    fs::write(dir.path().join("input.txt"), "A").unwrap();
+
+    let output = lc3_vm(dir.path(), &["asm", "add.asm", "--symbols", "add.sym"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(dir.path().join("add.sym")).unwrap(), "PTR x3004\n");
+
+    // 'A' (65) + 2 = 67, reported through the exit status
+    let output = lc3_vm(dir.path(), &["run", "add.obj", "--input", "input.txt", "--reg", "R1=2", "--exit-code", "r0"]);
+    assert_eq!(output.status.code(), Some(67));
+    assert_eq!(output.stdout, b"HALT\n");
+
+    // Without a subcommand `run` is assumed; the pointer is redirected with a memory preset
+    let output = lc3_vm(dir.path(), &["add.obj", "--input", "input.txt", "--mem", "x3004=x5000", "--trace", "trace.txt"]);
+    assert_eq!(output.status.code(), Some(0));
+    let trace = fs::read_to_string(dir.path().join("trace.txt")).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].starts_with("x3001  x1001  ADD R0, R0, R1"), "{}", lines[1]);
+    assert!(lines[1].contains("R0=x0041"), "{}", lines[1]);
+
+    // --pc skips the GETC, so R0 starts at zero
+    let output = lc3_vm(dir.path(), &["add.asm", "--pc", "x3001", "--reg", "R1=#9", "--exit-code", "r0", "--stats"]);
+    assert_eq!(output.status.code(), Some(9));
+    let stats = String::from_utf8(output.stderr).unwrap();
+    assert!(stats.starts_with("Instructions retired: 3\n"), "{}", stats);
+    assert!(stats.contains("MIPS"), "{}", stats);
+}
+
+#[test]
+fn converts_between_formats_and_disassembles() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("add.asm"), SOURCE).unwrap();
+    assert!(lc3_vm(dir.path(), &["asm", "add.asm", "-o", "add.hex"]).status.success());
+    assert_eq!(fs::read_to_string(dir.path().join("add.hex")).unwrap(), "3000\nF020\n1001\nB001\nF025\n4000\n");
+
+    assert!(lc3_vm(dir.path(), &["convert", "add.hex", "add.bin"]).status.success());
+    assert!(lc3_vm(dir.path(), &["convert", "add.bin", "add.obj"]).status.success());
+    let obj = fs::read(dir.path().join("add.obj")).unwrap();
+    assert_eq!(obj, [0x30, 0x00, 0xF0, 0x20, 0x10, 0x01, 0xB0, 0x01, 0xF0, 0x25, 0x40, 0x00]);
+
+    let output = lc3_vm(dir.path(), &["disasm", "add.obj"]);
+    let listing = String::from_utf8(output.stdout).unwrap();
+    assert!(listing.contains("STI R0, #1"), "{}", listing);
+    assert!(listing.starts_with(".ORIG x3000\n"));
+}
+
+#[test]
+fn dumps_memory_and_reports_usage_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("add.asm"), SOURCE).unwrap();
+
+    let output = lc3_vm(dir.path(), &["dump", "add.asm", "--from", "x3000", "--to", "x3007"]);
+    let dump = String::from_utf8(output.stdout).unwrap();
+    assert!(dump.contains("x3000: xF020 x1001 xB001 xF025 x4000 x0000 x0000 x0000"), "{}", dump);
+
+    assert_eq!(lc3_vm(dir.path(), &["run", "add.asm", "--reg", "R9=1"]).status.code(), Some(2));
+    assert_eq!(lc3_vm(dir.path(), &["run"]).status.code(), Some(2));
+
+    fs::write(dir.path().join("bad.asm"), ".ORIG x3000\nFOO R1\n").unwrap();
+    let output = lc3_vm(dir.path(), &["asm", "bad.asm"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 2"));
+}
+
+#[test]
+fn debugger_runs_to_breakpoints() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("add.asm"), SOURCE).unwrap();
+    fs::write(dir.path().join("input.txt"), "A").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lc3-vm"))
+        .current_dir(dir.path())
+        .args(["debug", "add.asm", "--input", "input.txt", "--reg", "R1=1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::io::Write::write_all(child.stdin.as_mut().unwrap(), b"break x3003\ncontinue\nregs\nmem x4000 1\nstep\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let transcript = String::from_utf8(output.stdout).unwrap();
+
+    assert!(transcript.contains("*x3003             xF025  HALT"), "{}", transcript);
+    assert!(transcript.contains("R0=x0042"), "{}", transcript);
+    assert!(transcript.contains("x4000             x0042  66"), "{}", transcript);
+    assert!(transcript.contains("Program halted after 4 instructions"), "{}", transcript);
+}
+
+#[test]
+fn seed_attaches_a_reproducible_random_generator() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("dice.asm"), ".ORIG x3000\nLDI R0, RNDR\nHALT\nRNDR .FILL xFE10\n.END\n").unwrap();
+
+    let roll = |args: &[&str]| {
+        let output = lc3_vm(dir.path(), &[&["dice.asm", "--exit-code", "r0"], args].concat());
+        output.status.code().unwrap()
+    };
+    assert_eq!(roll(&["--seed", "7"]), roll(&["--seed", "7"]));
+    assert_eq!(roll(&[]), 0);
+}
+
+#[test]
+fn extended_traps_can_be_moved_but_not_onto_standard_or_file_traps() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("print.asm"), ".ORIG x3000\nAND R0, R0, #0\nADD R0, R0, #-7\nTRAP x40\nHALT\n.END\n").unwrap();
+
+    let output = lc3_vm(dir.path(), &["print.asm", "--extended-traps", "--trap", "printd=x40"]);
+    assert_eq!(output.stdout, b"-7HALT\n");
+
+    let output = lc3_vm(dir.path(), &["print.asm", "--trap", "printd=x21"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("x21 is a standard trap"));
+
+    // x31 is FREAD under --sandbox, and free for extended traps otherwise
+    let output = lc3_vm(dir.path(), &["print.asm", "--trap", "printd=x31", "--sandbox", "."]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("would hide the file trap FREAD"));
+    fs::write(dir.path().join("print.asm"), ".ORIG x3000\nAND R0, R0, #0\nADD R0, R0, #-7\nTRAP x31\nHALT\n.END\n").unwrap();
+    let output = lc3_vm(dir.path(), &["print.asm", "--trap", "printd=x31"]);
+    assert_eq!(output.stdout, b"-7HALT\n");
+}
+
+#[test]
+fn stats_report_cycles_with_the_chosen_latency() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("clear.asm"), ".ORIG x3000\nAND R0, R0, #0\nHALT\n.END\n").unwrap();
+
+    let stats = |args: &[&str]| String::from_utf8(lc3_vm(dir.path(), &[&["clear.asm", "--stats"], args].concat()).stderr).unwrap();
+    assert!(!stats(&[]).contains("Cycles"));
+    assert!(stats(&["--cycles"]).contains("Cycles: 12 (6.00 per instruction)"), "{}", stats(&["--cycles"]));
+    assert!(stats(&["--memory-latency", "3"]).contains("Cycles: 18 "));
+
+    let output = lc3_vm(dir.path(), &["clear.asm", "--cycles"]);
+    assert_eq!(output.status.code(), Some(2));
+}
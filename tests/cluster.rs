@@ -0,0 +1,160 @@
+use std::io;
+use std::time::Duration;
+
+use lc3_vm::asm::assemble;
+use lc3_vm::cluster::{Cluster, Schedule};
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{Engine, Register, StopReason, LC3};
+
+/// Sends 1 to 5 through the port at xFE40 and sums the replies into R5
+const PING: &str = "
+        .ORIG x3000
+        AND R2, R2, #0
+        AND R5, R5, #0
+        AND R1, R1, #0
+        ADD R1, R1, #5
+NEXT    ADD R2, R2, #1
+SEND    LDI R3, STATUS
+        LD R4, TXMASK
+        AND R3, R3, R4
+        BRz SEND
+        STI R2, DATA
+WAIT    LDI R3, STATUS
+        BRzp WAIT
+        LDI R3, DATA
+        ADD R5, R5, R3
+        ADD R1, R1, #-1
+        BRp NEXT
+        HALT
+STATUS  .FILL xFE40
+DATA    .FILL xFE42
+TXMASK  .FILL x4000
+        .END
+";
+
+/// Answers five messages on the port at xFE50 with twice their value
+const PONG: &str = "
+        .ORIG x3000
+        AND R1, R1, #0
+        ADD R1, R1, #5
+WAIT    LDI R3, STATUS
+        BRzp WAIT
+        LDI R3, DATA
+        ADD R3, R3, R3
+        STI R3, DATA
+        ADD R1, R1, #-1
+        BRp WAIT
+        HALT
+STATUS  .FILL xFE50
+DATA    .FILL xFE52
+        .END
+";
+
+/// Adds one to the shared word at xFE60 fifty times without any locking
+const INCREMENT: &str = "
+        .ORIG x3000
+        AND R1, R1, #0
+        ADD R1, R1, #15
+        ADD R1, R1, #15
+        ADD R1, R1, #15
+        ADD R1, R1, #5
+LOOP    LDI R0, COUNTER
+        ADD R0, R0, #1
+        STI R0, COUNTER
+        ADD R1, R1, #-1
+        BRp LOOP
+        HALT
+COUNTER .FILL xFE60
+        .END
+";
+
+fn machine(source: &str, engine: Engine) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.engine = engine;
+    vm.load(&assemble(source).unwrap().image);
+    vm
+}
+
+fn ping_pong(engine: Engine) -> Cluster {
+    let mut cluster = Cluster::new();
+    let ping = cluster.add(machine(PING, engine));
+    let pong = cluster.add(machine(PONG, engine));
+    cluster.connect(ping, 0xFE40, pong, 0xFE50).unwrap();
+    cluster
+}
+
+#[test]
+fn round_robin_is_reproducible_on_every_engine() {
+    let mut runs = Vec::new();
+    for engine in [Engine::Interpreter, Engine::Cached, Engine::Translated, Engine::Interpreter] {
+        let mut cluster = ping_pong(engine);
+        let results = cluster.run(Schedule::RoundRobin { quantum: 7 });
+        assert!(results.into_iter().all(|result| result.unwrap() == StopReason::Halted));
+        assert_eq!(cluster.machines[0].registers.get(Register::R5), 30);
+        runs.push(cluster.machines.iter().map(|vm| vm.instruction_count).collect::<Vec<_>>());
+    }
+    assert!(runs.iter().all(|run| *run == runs[0]), "{:?}", runs);
+}
+
+#[test]
+fn parallel_machines_exchange_messages() {
+    let mut cluster = ping_pong(Engine::Translated);
+    let results = cluster.run(Schedule::Parallel);
+    assert!(results.into_iter().all(|result| result.unwrap() == StopReason::Halted));
+    assert_eq!(cluster.machines[0].registers.get(Register::R5), 30);
+}
+
+fn shared_counter(quantum: u64) -> u16 {
+    let mut cluster = Cluster::new();
+    cluster.add(machine(INCREMENT, Engine::Interpreter));
+    cluster.add(machine(INCREMENT, Engine::Interpreter));
+    cluster.share(&[0, 1], 0xFE60..=0xFE61).unwrap();
+    let results = cluster.run(Schedule::RoundRobin { quantum });
+    assert!(results.into_iter().all(|result| result.unwrap() == StopReason::Halted));
+    cluster.machines[1].memory.read(0xFE60, &mut Console::scripted(b"")).unwrap()
+}
+
+#[test]
+fn shared_memory_interleaves_as_scheduled() {
+    // Whole programs in one turn never race; in lockstep every update races and half are lost
+    assert_eq!(shared_counter(1000), 100);
+    assert_eq!(shared_counter(1), 50);
+}
+
+#[test]
+fn machines_stop_at_their_own_limits() {
+    let mut cluster = ping_pong(Engine::Interpreter);
+    // Pong stops early, so ping waits for replies that never come until its own limit
+    cluster.machines[0].config.max_instructions = Some(500);
+    cluster.machines[1].config.max_instructions = Some(20);
+    let results = cluster.run(Schedule::RoundRobin { quantum: 3 });
+
+    assert!(results.iter().all(|result| *result.as_ref().unwrap() == StopReason::InstructionLimit));
+    assert_eq!(cluster.machines[0].instruction_count, 500);
+    assert_eq!(cluster.machines[1].instruction_count, 20);
+    assert_eq!(cluster.machines[0].config.max_instructions, Some(500));
+}
+
+#[test]
+fn the_timeout_covers_the_whole_round_robin_run() {
+    let mut cluster = Cluster::new();
+    let looping = cluster.add(machine(".ORIG x3000\nBRnzp #-1\n.END\n", Engine::Cached));
+    cluster.add(machine(INCREMENT, Engine::Cached));
+    cluster.machines[looping].config.timeout = Some(Duration::from_millis(50));
+    let results = cluster.run(Schedule::RoundRobin { quantum: 10 });
+
+    assert_eq!(*results[0].as_ref().unwrap(), StopReason::Timeout);
+    assert_eq!(*results[1].as_ref().unwrap(), StopReason::Halted);
+}
+
+#[test]
+fn links_must_join_different_machines() {
+    let mut cluster = Cluster::new();
+    cluster.add(LC3::new());
+    cluster.add(LC3::new());
+
+    assert_eq!(cluster.connect(0, 0xFE40, 0, 0xFE50).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(cluster.connect(0, 0xFE40, 2, 0xFE50).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(cluster.share(&[0, 2], 0xFE60..=0xFE6F).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert!(cluster.connect(0, 0xFE00, 1, 0xFE50).is_err(), "overlaps the keyboard");
+}
@@ -0,0 +1,247 @@
+//! Parser for the data-described instruction cases in `tests/cases`.
+//!
+//! A case file is a sequence of cases, each opened by a `case <name>` line
+//! and followed by setup and expectation lines. `#` starts a comment line
+//! and `;` a trailing comment.
+//!
+//! ```text
+//! case ADD register mode
+//! reg R1 5
+//! reg R2 x0007
+//! cond z
+//! mem x3000 x1242        ; ADD R1, R1, R2
+//! input "ab"
+//! steps 1
+//! expect reg R1 #12
+//! expect cond p
+//! expect mem x3000 x1242
+//! expect output "HALT\n"
+//! expect halted
+//! expect error
+//! ```
+//!
+//! Values are decimal (`12`, `-4`, `#-4`), hex (`x1F`) or characters (`'a'`).
+//! `mem` and `expect mem` take any number of consecutive words. Strings
+//! accept `\n`, `\t`, `\0`, `\\`, `\"` and `\xNN` escapes. Unless given,
+//! PC starts at x3000 and a case executes a single instruction.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    N,
+    Z,
+    P,
+}
+
+#[derive(Debug)]
+pub enum Expect {
+    Reg(usize, u16),
+    Cond(Flag),
+    Mem(u16, Vec<u16>),
+    Output(Vec<u8>),
+    Halted,
+    Error,
+}
+
+#[derive(Debug)]
+pub struct Case {
+    pub name: String,
+    pub line: usize,
+    /// Register presets in R0-R7, PC order
+    pub regs: Vec<(usize, u16)>,
+    pub cond: Option<Flag>,
+    pub mem: Vec<(u16, Vec<u16>)>,
+    pub input: Vec<u8>,
+    pub steps: usize,
+    pub expects: Vec<(usize, Expect)>,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Parses every case in `source`
+pub fn parse(source: &str) -> Result<Vec<Case>, ParseError> {
+    let mut cases: Vec<Case> = Vec::new();
+
+    for (index, raw) in source.lines().enumerate() {
+        let line = index + 1;
+        let text = strip_comment(raw).trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+
+        let error = |message: String| ParseError { line, message };
+        let (keyword, rest) = split_word(text);
+
+        if keyword == "case" {
+            cases.push(Case {
+                name: rest.to_string(),
+                line,
+                regs: Vec::new(),
+                cond: None,
+                mem: Vec::new(),
+                input: Vec::new(),
+                steps: 1,
+                expects: Vec::new(),
+            });
+            continue;
+        }
+
+        let case = cases
+            .last_mut()
+            .ok_or_else(|| error("expected `case <name>` first".to_string()))?;
+
+        match keyword {
+            "reg" => {
+                let (register, value) = split_word(rest);
+                case.regs.push((parse_register(register).map_err(error)?, parse_value(value).map_err(error)?));
+            }
+            "cond" => case.cond = Some(parse_flag(rest).map_err(error)?),
+            "mem" => {
+                let (address, words) = split_word(rest);
+                case.mem.push((parse_value(address).map_err(error)?, parse_values(words).map_err(error)?));
+            }
+            "input" => case.input = parse_string(rest).map_err(error)?,
+            "steps" => case.steps = rest.parse().map_err(|_| error(format!("invalid step count `{}`", rest)))?,
+            "expect" => {
+                let (what, rest) = split_word(rest);
+                let expect = match what {
+                    "reg" => {
+                        let (register, value) = split_word(rest);
+                        Expect::Reg(parse_register(register).map_err(error)?, parse_value(value).map_err(error)?)
+                    }
+                    "cond" => Expect::Cond(parse_flag(rest).map_err(error)?),
+                    "mem" => {
+                        let (address, words) = split_word(rest);
+                        Expect::Mem(parse_value(address).map_err(error)?, parse_values(words).map_err(error)?)
+                    }
+                    "output" => Expect::Output(parse_string(rest).map_err(error)?),
+                    "halted" => Expect::Halted,
+                    "error" => Expect::Error,
+                    other => return Err(error(format!("unknown expectation `{}`", other))),
+                };
+                case.expects.push((line, expect));
+            }
+            other => return Err(error(format!("unknown directive `{}`", other))),
+        }
+    }
+
+    Ok(cases)
+}
+
+/// Removes a trailing `;` comment, ignoring semicolons inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match (quoted, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quoted = None,
+            (None, '"') | (None, '\'') => quoted = Some(c),
+            (None, ';') => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    match text.find(char::is_whitespace) {
+        Some(index) => (&text[..index], text[index..].trim_start()),
+        None => (text, ""),
+    }
+}
+
+fn parse_register(text: &str) -> Result<usize, String> {
+    match text.to_ascii_uppercase().as_str() {
+        "PC" => Ok(8),
+        name => name
+            .strip_prefix('R')
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|&n| n < 8)
+            .ok_or_else(|| format!("invalid register `{}`", text)),
+    }
+}
+
+fn parse_flag(text: &str) -> Result<Flag, String> {
+    match text.to_ascii_lowercase().as_str() {
+        "n" => Ok(Flag::N),
+        "z" => Ok(Flag::Z),
+        "p" => Ok(Flag::P),
+        _ => Err(format!("invalid condition flag `{}`", text)),
+    }
+}
+
+fn parse_values(text: &str) -> Result<Vec<u16>, String> {
+    let values = text.split_whitespace().map(parse_value).collect::<Result<Vec<_>, _>>()?;
+    if values.is_empty() {
+        return Err("expected at least one value".to_string());
+    }
+    Ok(values)
+}
+
+fn parse_value(text: &str) -> Result<u16, String> {
+    let invalid = || format!("invalid value `{}`", text);
+
+    if let Some(hex) = text.strip_prefix('x').or_else(|| text.strip_prefix('X')) {
+        return u16::from_str_radix(hex, 16).map_err(|_| invalid());
+    }
+    if text.len() >= 3 && text.starts_with('\'') && text.ends_with('\'') {
+        let bytes = unescape(&text[1..text.len() - 1])?;
+        return match bytes.as_slice() {
+            [byte] => Ok(*byte as u16),
+            _ => Err(invalid()),
+        };
+    }
+
+    let decimal = text.strip_prefix('#').unwrap_or(text);
+    let value: i32 = decimal.parse().map_err(|_| invalid())?;
+    if !(-32768..=65535).contains(&value) {
+        return Err(invalid());
+    }
+    Ok(value as u16)
+}
+
+fn parse_string(text: &str) -> Result<Vec<u8>, String> {
+    if text.len() < 2 || !text.starts_with('"') || !text.ends_with('"') {
+        return Err(format!("expected a quoted string, found `{}`", text));
+    }
+    unescape(&text[1..text.len() - 1])
+}
+
+fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('"') => bytes.push(b'"'),
+            Some('\'') => bytes.push(b'\''),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                bytes.push(u8::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape `\\x{}`", hex))?);
+            }
+            other => return Err(format!("invalid escape `\\{}`", other.map(String::from).unwrap_or_default())),
+        }
+    }
+    Ok(bytes)
+}
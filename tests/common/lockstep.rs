@@ -0,0 +1,86 @@
+use std::fmt;
+
+/// Number of architectural registers compared: R0-R7, PC and COND
+pub const REGISTER_COUNT: usize = 10;
+
+const REGISTER_NAMES: [&str; REGISTER_COUNT] = ["R0", "R1", "R2", "R3", "R4", "R5", "R6", "R7", "PC", "COND"];
+
+/// An LC-3 implementation that can be driven one instruction at a time
+pub trait Machine {
+    /// Fetches and executes the instruction at PC
+    fn step(&mut self);
+    /// Reads register `index` in R0-R7, PC, COND order
+    fn register(&self, index: usize) -> u16;
+    /// The full 64K-word address space
+    fn memory(&self) -> &[u16];
+    /// Whether this implementation can execute `instr` at all
+    fn supports(&self, _instr: u16) -> bool {
+        true
+    }
+}
+
+/// The first point at which two machines disagree
+#[derive(Debug)]
+pub struct Divergence {
+    pub step: usize,
+    pub pc: u16,
+    pub instr: u16,
+    pub detail: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "diverged after step {} (pc x{:04X}, instr x{:04X}): {}",
+            self.step, self.pc, self.instr, self.detail
+        )
+    }
+}
+
+/// Runs `a` and `b` side by side for up to `steps` instructions, comparing
+/// registers and memory after each one. Stops early, without error, when the
+/// next instruction is unsupported by either machine. Returns the number of
+/// instructions executed.
+pub fn run<A: Machine, B: Machine>(a: &mut A, b: &mut B, steps: usize) -> Result<usize, Divergence> {
+    if let Some(detail) = compare(a, b) {
+        return Err(Divergence { step: 0, pc: a.register(8), instr: 0, detail });
+    }
+
+    for step in 1..=steps {
+        let pc = a.register(8);
+        let instr = a.memory()[pc as usize];
+        if !a.supports(instr) || !b.supports(instr) {
+            return Ok(step - 1);
+        }
+
+        a.step();
+        b.step();
+
+        if let Some(detail) = compare(a, b) {
+            return Err(Divergence { step, pc, instr, detail });
+        }
+    }
+
+    Ok(steps)
+}
+
+fn compare<A: Machine, B: Machine>(a: &A, b: &B) -> Option<String> {
+    for (index, name) in REGISTER_NAMES.iter().enumerate() {
+        let (left, right) = (a.register(index), b.register(index));
+        if left != right {
+            return Some(format!("{} is x{:04X} vs x{:04X}", name, left, right));
+        }
+    }
+
+    let (left, right) = (a.memory(), b.memory());
+    if left != right {
+        let address = left.iter().zip(right).position(|(l, r)| l != r).unwrap_or(0);
+        return Some(format!(
+            "memory x{:04X} is x{:04X} vs x{:04X}",
+            address, left[address], right[address]
+        ));
+    }
+
+    None
+}
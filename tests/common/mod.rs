@@ -0,0 +1,26 @@
+#![allow(dead_code)]
+
+pub mod cases;
+pub mod lockstep;
+
+/// Small xorshift generator so test inputs are reproducible from a seed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    pub fn next_u16(&mut self) -> u16 {
+        (self.next_u64() >> 32) as u16
+    }
+}
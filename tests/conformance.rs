@@ -0,0 +1,137 @@
+//! Instruction-level conformance suite driven by the case files in
+//! `tests/cases`. See `tests/common/cases.rs` for the file format.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::cases::{self, Case, Expect, Flag};
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{CondFlag, Engine, Register, LC3, PC_START};
+
+const REGISTERS: [Register; 9] = [
+    Register::R0, Register::R1, Register::R2, Register::R3, Register::R4,
+    Register::R5, Register::R6, Register::R7, Register::PC,
+];
+
+fn cond_flag(flag: Flag) -> CondFlag {
+    match flag {
+        Flag::N => CondFlag::NEG,
+        Flag::Z => CondFlag::ZRO,
+        Flag::P => CondFlag::POS,
+    }
+}
+
+/// Runs a single case and returns a description of every unmet expectation
+fn check(case: &Case, engine: Engine) -> Vec<String> {
+    let mut vm = LC3::with_console(Console::scripted(&case.input));
+    vm.config.engine = engine;
+    vm.registers.set(Register::PC, PC_START);
+    for &(register, value) in &case.regs {
+        vm.registers.set(REGISTERS[register], value);
+    }
+    if let Some(flag) = case.cond {
+        vm.registers.set_condition_flag(cond_flag(flag));
+    }
+    for (address, words) in &case.mem {
+        for (offset, word) in words.iter().enumerate() {
+            vm.memory.write(address.wrapping_add(offset as u16), *word);
+        }
+    }
+
+    vm.running = true;
+    let mut result = Ok(());
+    if engine == Engine::Translated {
+        // Blocks only run under `LC3::run`; `step` would bypass them
+        vm.config.max_instructions = Some(case.steps as u64);
+        result = vm.run().map(|_| ());
+    } else {
+        for _ in 0..case.steps {
+            if !vm.running {
+                break;
+            }
+            result = vm.step();
+            if result.is_err() {
+                break;
+            }
+        }
+    }
+
+    let mut failures = Vec::new();
+    let expects_error = case.expects.iter().any(|(_, e)| matches!(e, Expect::Error));
+    match &result {
+        Err(err) if !expects_error => failures.push(format!("unexpected error: {}", err)),
+        Ok(()) if expects_error => failures.push("expected an error".to_string()),
+        _ => {}
+    }
+
+    for (line, expect) in &case.expects {
+        let failure = match expect {
+            Expect::Reg(register, value) => {
+                let actual = vm.registers.get(REGISTERS[*register]);
+                (actual != *value).then(|| {
+                    format!("{:?} is x{:04X}, expected x{:04X}", REGISTERS[*register], actual, value)
+                })
+            }
+            Expect::Cond(flag) => {
+                let actual = vm.registers.get_condition_flag();
+                (actual != cond_flag(*flag) as u16)
+                    .then(|| format!("COND is x{:04X}, expected {:?}", actual, flag))
+            }
+            Expect::Mem(address, words) => words.iter().enumerate().find_map(|(offset, word)| {
+                let at = address.wrapping_add(offset as u16);
+                let actual = vm.memory.get_ptr(at)[0];
+                (actual != *word).then(|| format!("x{:04X} is x{:04X}, expected x{:04X}", at, actual, word))
+            }),
+            Expect::Output(text) => {
+                let actual = vm.console().output();
+                (actual != text.as_slice()).then(|| {
+                    format!(
+                        "output is {:?}, expected {:?}",
+                        String::from_utf8_lossy(actual),
+                        String::from_utf8_lossy(text)
+                    )
+                })
+            }
+            Expect::Halted => vm.running.then(|| "VM is still running".to_string()),
+            Expect::Error => None,
+        };
+        if let Some(failure) = failure {
+            failures.push(format!("line {}: {}", line, failure));
+        }
+    }
+
+    failures
+}
+
+#[test]
+fn case_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cases");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .expect("tests/cases is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cases"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no case files in {}", dir.display());
+
+    let mut total = 0;
+    let mut failures = Vec::new();
+    for path in &paths {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let source = fs::read_to_string(path).unwrap();
+        let cases = cases::parse(&source).unwrap_or_else(|err| panic!("{}: {}", name, err));
+        total += cases.len();
+
+        for case in &cases {
+            for engine in [Engine::Interpreter, Engine::Cached, Engine::Translated, Engine::Microcoded] {
+                for failure in check(case, engine) {
+                    failures.push(format!("{}:{} [{}] {:?}: {}", name, case.line, case.name, engine, failure));
+                }
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{} of {} cases failed:\n{}", failures.len(), total, failures.join("\n"));
+}
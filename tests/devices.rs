@@ -0,0 +1,271 @@
+use std::io;
+
+use lc3_vm::asm::assemble;
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{Device, DeviceContext, Engine, Interrupt, Register, StopReason, LC3};
+
+/// Rings once, `delay` instructions after a delay is written to xFE11, by
+/// requesting interrupt x90 until the program writes xFE10. Reading xFE10
+/// returns how many times it has rung.
+#[derive(Default)]
+struct Doorbell {
+    rings: u16,
+    at: Option<u64>,
+    ringing: bool,
+    /// Delay written since the last tick, started when the count is known
+    delay: Option<u64>,
+}
+
+impl Device for Doorbell {
+    fn name(&self) -> &str {
+        "doorbell"
+    }
+
+    fn read(&mut self, address: u16, _context: &mut DeviceContext) -> io::Result<u16> {
+        Ok(if address == 0xFE10 { self.rings } else { 0 })
+    }
+
+    fn write(&mut self, address: u16, value: u16) {
+        match address {
+            0xFE10 => self.ringing = false,
+            _ => self.delay = Some(value as u64),
+        }
+    }
+
+    fn next_tick(&self) -> Option<u64> {
+        if self.delay.is_some() {
+            return Some(0);
+        }
+        self.at
+    }
+
+    fn tick(&mut self, context: &mut DeviceContext) {
+        if let Some(delay) = self.delay.take() {
+            self.at = Some(context.instruction_count + delay);
+        } else if self.at.is_some_and(|at| context.instruction_count >= at) {
+            self.at = None;
+            self.ringing = true;
+            self.rings += 1;
+        }
+    }
+
+    fn interrupt(&self) -> Option<Interrupt> {
+        self.ringing.then_some(Interrupt { vector: 0x90, priority: 2 })
+    }
+
+    fn save(&self, instruction_count: u64) -> Vec<u8> {
+        let left = self.at.map_or(0, |at| at - instruction_count);
+        [&self.rings.to_be_bytes()[..], &left.to_be_bytes(), &[self.ringing as u8]].concat()
+    }
+
+    fn restore(&mut self, state: &[u8], instruction_count: u64) -> io::Result<()> {
+        self.rings = u16::from_be_bytes([state[0], state[1]]);
+        let left = u64::from_be_bytes(state[2..10].try_into().unwrap());
+        self.at = (left > 0).then_some(instruction_count + left);
+        self.ringing = state[10] != 0;
+        self.delay = None;
+        Ok(())
+    }
+}
+
+/// Arms the doorbell for 40 instructions, then counts in R4 until it rings.
+/// The service routine records R4 in R1, reads the ring count into R3,
+/// answers the door and re-arms it.
+const RING: &str = "
+        .ORIG x3000
+        LEA R0, ISR
+        STI R0, VECTOR
+        LD R0, DELAY
+        STI R0, ARM
+LOOP    ADD R4, R4, #1
+        BRnzp LOOP
+ISR     ADD R1, R4, #0
+        LDI R3, BELL
+        STI R0, BELL
+        STI R0, ARM
+        RTI
+VECTOR  .FILL x0190
+BELL    .FILL xFE10
+ARM     .FILL xFE11
+DELAY   .FILL #40
+        .END
+";
+
+fn doorbell_vm(engine: Engine) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.engine = engine;
+    vm.attach_device(0xFE10..=0xFE11, Box::new(Doorbell::default())).unwrap();
+    vm.load(&assemble(RING).unwrap().image);
+    vm
+}
+
+#[test]
+fn custom_devices_interrupt_identically_on_every_engine() {
+    let mut results = Vec::new();
+    for engine in [Engine::Interpreter, Engine::Cached, Engine::Translated, Engine::Microcoded] {
+        let mut vm = doorbell_vm(engine);
+        vm.config.max_instructions = Some(1000);
+        assert_eq!(vm.run().unwrap(), StopReason::InstructionLimit);
+        results.push((vm.registers.get(Register::R1), vm.registers.get(Register::R3), vm.stats().interrupts));
+    }
+
+    assert!(results[0].1 > 5, "{:?}", results);
+    assert_eq!(results[0].2, results[0].1 as u64);
+    assert!(results.iter().all(|result| *result == results[0]), "{:?}", results);
+}
+
+#[test]
+fn snapshots_carry_custom_device_state() {
+    let mut original = doorbell_vm(Engine::Interpreter);
+    original.running = true;
+    for _ in 0..100 {
+        original.step().unwrap();
+    }
+    let mut bytes = Vec::new();
+    original.save_snapshot(&mut bytes).unwrap();
+
+    let mut restored = LC3::with_console(Console::scripted(b""));
+    restored.attach_device(0xFE10..=0xFE11, Box::new(Doorbell::default())).unwrap();
+    restored.load_snapshot(bytes.as_slice()).unwrap();
+    for _ in 0..100 {
+        original.step().unwrap();
+        restored.step().unwrap();
+    }
+
+    assert_eq!(restored.registers.get(Register::R1), original.registers.get(Register::R1));
+    assert_eq!(restored.registers.get(Register::R3), original.registers.get(Register::R3));
+    assert_eq!(restored.registers.get(Register::PC), original.registers.get(Register::PC));
+}
+
+#[test]
+fn devices_must_fit_the_device_page_without_overlapping() {
+    let mut vm = LC3::new();
+    let names: Vec<_> = vm.memory.bus().devices().map(|(_, device)| device.name().to_string()).collect();
+    assert_eq!(names, ["keyboard", "timer"]);
+
+    let err = vm.attach_device(0xFDFF..=0xFE10, Box::new(Doorbell::default())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let err = vm.attach_device(0xFE0C..=0xFE0D, Box::new(Doorbell::default())).unwrap_err();
+    assert!(err.to_string().contains("overlaps timer at xFE0C"), "{}", err);
+
+    vm.attach_device(0xFE0D..=0xFE0D, Box::new(Doorbell::default())).unwrap();
+}
+
+#[test]
+fn keyboard_is_read_through_the_bus() {
+    let source = "
+        .ORIG x3000
+POLL    LDI R1, KBSR
+        BRzp POLL
+        LDI R0, KBDR
+        HALT
+KBSR    .FILL xFE00
+KBDR    .FILL xFE02
+        .END
+";
+    let mut vm = LC3::with_console(Console::scripted(b"k"));
+    vm.load(&assemble(source).unwrap().image);
+    assert_eq!(vm.run().unwrap(), StopReason::Halted);
+    assert_eq!(vm.registers.get(Register::R0), b'k' as u16);
+    assert_eq!(vm.memory.get_ptr(0xFE02)[0], b'k' as u16);
+}
+
+/// Reads RNDR three times into R1-R3 and the uptime seconds into R4
+const SAMPLE: &str = "
+        .ORIG x3000
+        LDI R1, RNDR
+        LDI R2, RNDR
+        LDI R3, RNDR
+        LDI R4, CLKS
+        HALT
+RNDR    .FILL xFE10
+CLKS    .FILL xFE14
+        .END
+";
+
+fn sample(seed: Option<u64>) -> [u16; 3] {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.random = seed.is_some();
+    vm.config.random_seed = seed;
+    vm.load(&assemble(SAMPLE).unwrap().image);
+    vm.run().unwrap();
+    [Register::R1, Register::R2, Register::R3].map(|register| vm.registers.get(register))
+}
+
+#[test]
+fn seeded_random_numbers_are_reproducible() {
+    let numbers = sample(Some(42));
+    assert_eq!(sample(Some(42)), numbers);
+    assert_ne!(sample(Some(43)), numbers);
+    assert!(numbers[0] != numbers[1] && numbers[1] != numbers[2], "{:?}", numbers);
+
+    // Without the generator RNDR is ordinary memory
+    assert_eq!(sample(None), [0, 0, 0]);
+}
+
+#[test]
+fn snapshots_with_state_for_missing_devices_are_rejected() {
+    let mut original = doorbell_vm(Engine::Interpreter);
+    original.running = true;
+    let mut bytes = Vec::new();
+    original.save_snapshot(&mut bytes).unwrap();
+
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.clock = true;
+    let err = vm.load_snapshot(bytes.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("doorbell"), "{}", err);
+    assert!(!vm.running);
+    // The clock is only attached by a load that succeeds, so its range is still free
+    vm.attach_device(0xFE14..=0xFE1C, Box::new(Doorbell::default())).unwrap();
+}
+
+#[test]
+fn writing_rndr_reseeds_and_snapshots_keep_the_sequence() {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.random = true;
+    vm.attach_configured_devices().unwrap();
+    // Devices that are already attached are left alone
+    vm.attach_configured_devices().unwrap();
+    let mut console = Console::scripted(b"");
+    let mut read = |vm: &mut LC3| vm.memory.read(0xFE10, &mut console).unwrap();
+
+    vm.memory.write(0xFE10, 7);
+    let first = [read(&mut vm), read(&mut vm)];
+    vm.memory.write(0xFE10, 7);
+    assert_eq!([read(&mut vm), read(&mut vm)], first);
+
+    let mut bytes = Vec::new();
+    vm.save_snapshot(&mut bytes).unwrap();
+    let mut restored = LC3::with_console(Console::scripted(b""));
+    restored.config.random = true;
+    restored.load_snapshot(bytes.as_slice()).unwrap();
+    assert_eq!(read(&mut restored), read(&mut vm));
+}
+
+#[test]
+fn clock_reports_uptime_and_time_of_day() {
+    let source = "
+        .ORIG x3000
+        LDI R1, CLKS
+        LDI R2, RTCH
+        LDI R3, RTCM
+        LDI R4, RTCS
+        HALT
+CLKS    .FILL xFE14
+RTCH    .FILL xFE18
+RTCM    .FILL xFE1A
+RTCS    .FILL xFE1C
+        .END
+";
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.clock = true;
+    vm.load(&assemble(source).unwrap().image);
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers.get(Register::R1), 0);
+    assert!(vm.registers.get(Register::R2) < 24);
+    assert!(vm.registers.get(Register::R3) < 60);
+    assert!(vm.registers.get(Register::R4) < 60);
+}
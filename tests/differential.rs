@@ -0,0 +1,213 @@
+//! Runs the legacy single-struct VM in `src/lc3.rs` and the modular `vm`
+//! implementation in lockstep on generated instruction streams.
+
+mod common;
+
+#[path = "../src/lc3.rs"]
+#[allow(dead_code, clippy::all)]
+mod legacy;
+
+use common::lockstep::{self, Machine};
+use common::Rng;
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{CondFlag, Engine, Register, LC3, MEMORY_SIZE};
+
+const REGISTERS: [Register; lockstep::REGISTER_COUNT] = [
+    Register::R0, Register::R1, Register::R2, Register::R3, Register::R4,
+    Register::R5, Register::R6, Register::R7, Register::PC, Register::COND,
+];
+
+impl Machine for LC3 {
+    fn step(&mut self) {
+        LC3::step(self).expect("modular VM failed to execute instruction");
+    }
+
+    fn register(&self, index: usize) -> u16 {
+        self.registers.get(REGISTERS[index])
+    }
+
+    fn memory(&self) -> &[u16] {
+        self.memory.get_ptr(0)
+    }
+}
+
+impl Machine for legacy::LC3 {
+    fn step(&mut self) {
+        let pc = self.registers[legacy::Register::PC as usize];
+        self.registers[legacy::Register::PC as usize] = pc.wrapping_add(1);
+        let instr = self.mem_read(pc);
+        self.execute_instruction(instr);
+    }
+
+    fn register(&self, index: usize) -> u16 {
+        self.registers[index]
+    }
+
+    fn memory(&self) -> &[u16] {
+        &self.memory
+    }
+
+    /// The legacy VM panics on RTI/RES and performs trap I/O directly on
+    /// the process's stdin/stdout, so streams stop before reaching them.
+    fn supports(&self, instr: u16) -> bool {
+        legacy_supports(instr)
+    }
+}
+
+/// Whether the legacy VM executes `instr` the way the modular VM does. Besides
+/// RTI, RES and TRAP, it writes R7 before reading it for JSRR R7, which
+/// tests/cases/branch.cases covers instead.
+fn legacy_supports(instr: u16) -> bool {
+    !matches!(instr >> 12, 8 | 13 | 15) && instr & 0xF9C0 != 0x41C0
+}
+
+/// Generates a random instruction the legacy VM executes like the modular one
+fn instruction(rng: &mut Rng) -> u16 {
+    loop {
+        let instr = rng.next_u16();
+        if legacy_supports(instr) {
+            return instr;
+        }
+    }
+}
+
+/// Builds both machines with an identical random register file and an
+/// address space filled with generated instructions.
+fn machines(seed: u64) -> (LC3, legacy::LC3) {
+    let mut rng = Rng::new(seed);
+    let mut modular = LC3::with_console(Console::scripted(&[]));
+    let mut reference = legacy::LC3::new();
+
+    for address in 0..MEMORY_SIZE {
+        let word = instruction(&mut rng);
+        modular.memory.write(address as u16, word);
+        reference.memory[address] = word;
+    }
+
+    for (index, register) in REGISTERS[..8].iter().enumerate() {
+        let value = rng.next_u16();
+        modular.registers.set(*register, value);
+        reference.registers[index] = value;
+    }
+
+    let pc = rng.next_u16();
+    modular.registers.set(Register::PC, pc);
+    reference.registers[legacy::Register::PC as usize] = pc;
+
+    let flag = [CondFlag::POS, CondFlag::ZRO, CondFlag::NEG][(rng.next_u64() % 3) as usize];
+    modular.registers.set_condition_flag(flag);
+    reference.registers[legacy::Register::COND as usize] = flag as u16;
+
+    (modular, reference)
+}
+
+#[test]
+fn generated_streams_match_legacy_implementation() {
+    for seed in 1..=16 {
+        let (mut modular, mut reference) = machines(seed);
+        if let Err(divergence) = lockstep::run(&mut modular, &mut reference, 5_000) {
+            panic!("seed {}: {}", seed, divergence);
+        }
+    }
+}
+
+#[test]
+fn decode_cache_matches_the_interpreter() {
+    // Generated streams store all over the address space, including over code about to run
+    for seed in 17..=24 {
+        let (mut cached, _) = machines(seed);
+        let (mut interpreted, _) = machines(seed);
+        interpreted.config.engine = Engine::Interpreter;
+        if let Err(divergence) = lockstep::run(&mut cached, &mut interpreted, 20_000) {
+            panic!("seed {}: {}", seed, divergence);
+        }
+    }
+}
+
+/// A machine that stops generated streams where they would fault: stores
+/// can write TRAPs with unknown vectors, RTIs and reserved opcodes into them
+struct Generated<'a>(&'a mut LC3);
+
+impl Machine for Generated<'_> {
+    fn step(&mut self) {
+        self.0.step().expect("VM failed to execute instruction");
+    }
+
+    fn register(&self, index: usize) -> u16 {
+        self.0.register(index)
+    }
+
+    fn memory(&self) -> &[u16] {
+        self.0.memory()
+    }
+
+    fn supports(&self, instr: u16) -> bool {
+        !matches!(instr >> 12, 8 | 13 | 15)
+    }
+}
+
+#[test]
+fn microcode_matches_the_interpreter() {
+    for seed in 33..=40 {
+        let (mut microcoded, _) = machines(seed);
+        let (mut interpreted, _) = machines(seed);
+        microcoded.config.engine = Engine::Microcoded;
+        interpreted.config.engine = Engine::Interpreter;
+        if let Err(divergence) = lockstep::run(&mut Generated(&mut microcoded), &mut Generated(&mut interpreted), 20_000) {
+            panic!("seed {}: {}", seed, divergence);
+        }
+    }
+}
+
+#[test]
+fn translated_blocks_match_the_interpreter() {
+    // Blocks run under `LC3::run`, so compare whole machines after runs of random length
+    for seed in 25..=32 {
+        let (mut translated, _) = machines(seed);
+        let (mut interpreted, _) = machines(seed);
+        translated.config.engine = Engine::Translated;
+        interpreted.config.engine = Engine::Interpreter;
+        // `run` sets this, and only a running machine takes interrupts
+        interpreted.running = true;
+
+        let mut rng = Rng::new(seed);
+        let mut executed = 0;
+        while executed < 20_000 {
+            let count = 1 + rng.next_u64() % 200;
+            translated.config.max_instructions = Some(count);
+            translated.run().expect("translated VM failed");
+            for _ in 0..count {
+                interpreted.step().expect("interpreter failed");
+            }
+            executed += count;
+
+            for register in REGISTERS {
+                assert_eq!(
+                    translated.registers.get(register),
+                    interpreted.registers.get(register),
+                    "seed {}: {:?} differs after {} instructions",
+                    seed,
+                    register,
+                    executed
+                );
+            }
+            assert!(
+                translated.memory.get_ptr(0) == interpreted.memory.get_ptr(0),
+                "seed {}: memory differs after {} instructions",
+                seed,
+                executed
+            );
+        }
+    }
+}
+
+#[test]
+fn divergence_names_first_differing_register() {
+    let (mut modular, mut reference) = machines(99);
+    modular.registers.set(Register::R3, 1);
+    reference.registers[3] = 2;
+
+    let divergence = lockstep::run(&mut modular, &mut reference, 10).unwrap_err();
+    assert_eq!(divergence.step, 0);
+    assert_eq!(divergence.detail, "R3 is x0001 vs x0002");
+}
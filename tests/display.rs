@@ -0,0 +1,149 @@
+use std::fs;
+
+use lc3_vm::asm::assemble;
+use lc3_vm::io::console::Console;
+use lc3_vm::io::display::{Frame, FrameFormat, HEIGHT, WIDTH};
+use lc3_vm::vm::{DisplayConfig, Engine, LC3, VIDEO_START};
+
+/// Paints the top-left pixel red, the one right of it green and the one below
+/// it blue, then paints the top-left pixel white
+const SOURCE: &str = "
+        .ORIG x3000
+        LD R1, SCREEN
+        LD R0, RED
+        STR R0, R1, #0
+        LD R0, GREEN
+        STR R0, R1, #1
+        LD R2, ROW
+        ADD R2, R1, R2
+        LD R0, BLUE
+        STR R0, R2, #0
+        LD R0, WHITE
+        STR R0, R1, #0
+        HALT
+SCREEN  .FILL xC000
+ROW     .FILL #128
+RED     .FILL x7C00
+GREEN   .FILL x03E0
+BLUE    .FILL x001F
+WHITE   .FILL x7FFF
+        .END
+";
+
+fn painted() -> Frame {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.load(&assemble(SOURCE).unwrap().image);
+    vm.run().unwrap();
+    Frame::capture(&vm.memory)
+}
+
+#[test]
+fn frames_expand_15_bit_colour() {
+    let frame = painted();
+    assert_eq!(frame.pixels.len(), WIDTH * HEIGHT);
+    assert_eq!(frame.rgb(0, 0), [255, 255, 255]);
+    assert_eq!(frame.rgb(1, 0), [0, 255, 0]);
+    assert_eq!(frame.rgb(0, 1), [0, 0, 255]);
+    assert_eq!(frame.rgb(2, 0), [0, 0, 0]);
+
+    let mut frame = frame;
+    frame.pixels[2] = 0x4210; // 16 in each component
+    assert_eq!(frame.rgb(2, 0), [132, 132, 132]);
+}
+
+#[test]
+fn encodes_ppm_and_png() {
+    let frame = painted();
+
+    let ppm = frame.to_ppm();
+    let header = b"P6\n128 124\n255\n";
+    assert!(ppm.starts_with(header));
+    assert_eq!(ppm.len(), header.len() + WIDTH * HEIGHT * 3);
+    assert_eq!(&ppm[header.len()..header.len() + 6], &[255, 255, 255, 0, 255, 0]);
+
+    let png = frame.to_png();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    let mut chunks = Vec::new();
+    let mut data = Vec::new();
+    let mut at = 8;
+    while at < png.len() {
+        let length = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+        let kind = &png[at + 4..at + 8];
+        let body = &png[at + 8..at + 8 + length];
+        let crc = u32::from_be_bytes(png[at + 8 + length..at + 12 + length].try_into().unwrap());
+        assert_eq!(crc, crc32(&png[at + 4..at + 8 + length]), "{}", String::from_utf8_lossy(kind));
+        if kind == b"IDAT" {
+            data.extend_from_slice(body);
+        }
+        chunks.push(String::from_utf8_lossy(kind).into_owned());
+        at += 12 + length;
+    }
+    assert_eq!(chunks, ["IHDR", "IDAT", "IEND"]);
+
+    // Undo the stored deflate blocks and check the first scanline
+    let mut raw = Vec::new();
+    let mut at = 2;
+    loop {
+        let last = data[at] & 1 == 1;
+        let length = u16::from_le_bytes([data[at + 1], data[at + 2]]) as usize;
+        raw.extend_from_slice(&data[at + 5..at + 5 + length]);
+        at += 5 + length;
+        if last {
+            break;
+        }
+    }
+    assert_eq!(raw.len(), HEIGHT * (1 + WIDTH * 3));
+    assert_eq!(&raw[..7], &[0, 255, 255, 255, 0, 255, 0]);
+    assert_eq!(FrameFormat::Png.extension(), "png");
+}
+
+#[test]
+fn draws_half_blocks_with_true_colour() {
+    let text = painted().to_ansi();
+    assert!(text.starts_with("\x1b7\x1b[1;1H"));
+    assert!(text.ends_with("\x1b8"));
+    // White over blue, then green over black
+    assert!(text.contains("\x1b[38;2;255;255;255;48;2;0;0;255m\u{2580}"));
+    assert!(text.contains("\x1b[38;2;0;255;0;48;2;0;0;0m\u{2580}"));
+    assert_eq!(text.matches('\u{2580}').count(), WIDTH * HEIGHT / 2);
+}
+
+#[test]
+fn saves_a_frame_whenever_the_display_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    for engine in [Engine::Cached, Engine::Translated] {
+        let frames = dir.path().join(format!("{:?}", engine));
+        let mut vm = LC3::with_console(Console::scripted(b""));
+        vm.config.engine = engine;
+        vm.config.display = Some(DisplayConfig {
+            frames: Some(frames.clone()),
+            format: FrameFormat::Ppm,
+            frame_interval: 1,
+            ..DisplayConfig::default()
+        });
+        vm.load(&assemble(SOURCE).unwrap().image);
+        vm.run().unwrap();
+
+        let mut names: Vec<_> = fs::read_dir(&frames)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["frame-00000.ppm", "frame-00001.ppm", "frame-00002.ppm", "frame-00003.ppm"]);
+
+        let last = fs::read(frames.join("frame-00003.ppm")).unwrap();
+        assert_eq!(last, painted().to_ppm());
+        assert_eq!(vm.memory.get_ptr(VIDEO_START)[0], 0x7FFF);
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,65 @@
+use lc3_vm::asm::assemble;
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{ExtendedTrap, Fault, Register, LC3};
+
+/// Reads a number, prints it doubled at row 2, column 5 of a cleared screen
+const DOUBLE: &str = "
+        .ORIG x3000
+        TRAP x27
+        ADD R0, R0, R0
+        ADD R2, R0, #0
+        TRAP x28
+        AND R0, R0, #0
+        ADD R0, R0, #2
+        AND R1, R1, #0
+        ADD R1, R1, #5
+        TRAP x29
+        ADD R0, R2, #0
+        TRAP x26
+        HALT
+        .END
+";
+
+fn run(source: &str, input: &[u8], traps: &[(u8, ExtendedTrap)]) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(input));
+    vm.config.extended_traps = traps.iter().copied().collect();
+    vm.load(&assemble(source).unwrap().image);
+    vm.run().unwrap();
+    vm
+}
+
+#[test]
+fn default_vectors_print_read_and_move_the_cursor() {
+    let vm = run(DOUBLE, b"-1x2\x083\n", &ExtendedTrap::DEFAULTS);
+    assert_eq!(vm.registers.get(Register::R2) as i16, -26);
+    assert_eq!(
+        String::from_utf8_lossy(vm.console().output()),
+        "-12\x08 \x083\n\x1b[2J\x1b[H\x1b[3;6H-26HALT\n"
+    );
+}
+
+#[test]
+fn read_decimal_clamps_and_sets_flags() {
+    let source = ".ORIG x3000\nTRAP x40\nHALT\n.END\n";
+    let vm = run(source, b"99999\r", &[(0x40, ExtendedTrap::READD)]);
+    assert_eq!(vm.registers.get(Register::R0), 32767);
+    assert_eq!(vm.registers.get(Register::COND), 1);
+
+    let vm = run(source, b"\n", &[(0x40, ExtendedTrap::READD)]);
+    assert_eq!(vm.registers.get(Register::R0), 0);
+}
+
+#[test]
+fn extended_traps_are_off_by_default() {
+    let mut vm = LC3::with_console(Console::scripted(b"5\n"));
+    vm.load(&assemble(DOUBLE).unwrap().image);
+    let err = vm.run().unwrap_err();
+    assert_eq!(Fault::from_io_error(&err), Some(&Fault::UnknownTrap(0x27)));
+}
+
+#[test]
+fn standard_traps_keep_their_vectors() {
+    let source = ".ORIG x3000\nLD R0, CHAR\nOUT\nHALT\nCHAR .FILL x41\n.END\n";
+    let vm = run(source, b"", &[(0x21, ExtendedTrap::PRINTD)]);
+    assert_eq!(vm.console().output(), b"AHALT\n");
+}
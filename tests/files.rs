@@ -0,0 +1,159 @@
+use std::fs;
+
+use lc3_vm::asm::assemble;
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{CondFlag, Fault, Register, LC3};
+
+/// Writes "hello" to notes.txt, reopens it, seeks past "he", reads the rest
+/// back into BUF and prints it. R3 keeps the result of the first FOPEN.
+const ROUND_TRIP: &str = r#"
+        .ORIG x3000
+        LEA R0, NAME
+        AND R1, R1, #0
+        ADD R1, R1, #1
+        TRAP x30
+        ADD R3, R0, #0
+        LEA R1, TEXT
+        AND R2, R2, #0
+        ADD R2, R2, #5
+        TRAP x32
+        ADD R0, R3, #0
+        TRAP x33
+        LEA R0, NAME
+        AND R1, R1, #0
+        TRAP x30
+        ADD R4, R0, #0
+        AND R1, R1, #0
+        ADD R1, R1, #2
+        AND R2, R2, #0
+        TRAP x34
+        ADD R0, R4, #0
+        LEA R1, BUF
+        AND R2, R2, #0
+        ADD R2, R2, #10
+        TRAP x31
+        ADD R5, R0, #0
+        LEA R0, BUF
+        PUTS
+        HALT
+NAME    .STRINGZ "notes.txt"
+TEXT    .STRINGZ "hello"
+BUF     .BLKW 11
+        .END
+"#;
+
+fn vm(source: &str, sandbox: Option<&std::path::Path>) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.sandbox = sandbox.map(|path| path.to_path_buf());
+    vm.load(&assemble(source).unwrap().image);
+    vm
+}
+
+/// Opens the path in R0 for reading and halts
+fn open(path: &str) -> String {
+    format!(".ORIG x3000\nLEA R0, NAME\nAND R1, R1, #0\nTRAP x30\nHALT\nNAME .STRINGZ \"{}\"\n.END\n", path)
+}
+
+#[test]
+fn programs_write_seek_and_read_files_in_the_sandbox() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut vm = vm(ROUND_TRIP, Some(dir.path()));
+    vm.run().unwrap();
+
+    assert_eq!(fs::read_to_string(dir.path().join("notes.txt")).unwrap(), "hello");
+    assert_eq!(vm.registers.get(Register::R3), 0);
+    assert_eq!(vm.registers.get(Register::R4), 0, "the closed handle is reused");
+    assert_eq!(vm.registers.get(Register::R5), 3);
+    assert_eq!(vm.console().output(), b"lloHALT\n");
+    assert!(vm.stats().to_string().contains("x30 FOPEN"), "{}", vm.stats());
+}
+
+#[test]
+fn paths_cannot_leave_the_sandbox() {
+    let outer = tempfile::tempdir().unwrap();
+    let sandbox = outer.path().join("sandbox");
+    fs::create_dir(&sandbox).unwrap();
+    fs::write(outer.path().join("secret.txt"), "x").unwrap();
+    fs::write(sandbox.join("public.txt"), "x").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(outer.path().join("secret.txt"), sandbox.join("link.txt")).unwrap();
+
+    let secret = outer.path().join("secret.txt");
+    let mut refused = vec!["../secret.txt".to_string(), secret.display().to_string(), "".to_string(), "missing.txt".to_string()];
+    if cfg!(unix) {
+        refused.push("link.txt".to_string());
+    }
+    for path in refused {
+        let mut vm = vm(&open(&path), Some(&sandbox));
+        vm.run().unwrap();
+        assert_eq!(vm.registers.get(Register::R0), 0xFFFF, "{} was opened", path);
+        assert_eq!(vm.registers.get(Register::COND), CondFlag::NEG as u16, "{}", path);
+    }
+
+    let mut vm = vm(&open("public.txt"), Some(&sandbox));
+    vm.run().unwrap();
+    assert_eq!(vm.registers.get(Register::R0), 0);
+}
+
+#[test]
+fn file_traps_are_unknown_without_a_sandbox() {
+    let mut vm = vm(&open("notes.txt"), None);
+    let err = vm.run().unwrap_err();
+    assert_eq!(Fault::from_io_error(&err), Some(&Fault::UnknownTrap(0x30)));
+}
+
+#[test]
+fn bad_handles_fail_without_stopping_the_program() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = ".ORIG x3000\nAND R0, R0, #0\nADD R0, R0, #9\nTRAP x33\nADD R1, R0, #0\nAND R0, R0, #0\nTRAP x31\nHALT\n.END\n";
+    let mut vm = vm(source, Some(dir.path()));
+    vm.run().unwrap();
+    assert_eq!(vm.registers.get(Register::R1), 0xFFFF);
+    assert_eq!(vm.registers.get(Register::R0), 0xFFFF);
+    assert!(!vm.running);
+}
+
+/// Reads as much of big.bin as it can, keeping the count in R4, then writes
+/// the word at KBSR to out.bin and reads a key
+const TRANSFERS: &str = r#"
+        .ORIG x3000
+        LEA R0, BIG
+        AND R1, R1, #0
+        TRAP x30
+        LD R1, BUF
+        AND R2, R2, #0
+        ADD R2, R2, #-1
+        TRAP x31
+        ADD R4, R0, #0
+        LEA R0, COPY
+        AND R1, R1, #0
+        ADD R1, R1, #1
+        TRAP x30
+        LD R1, KBSR
+        AND R2, R2, #0
+        ADD R2, R2, #1
+        TRAP x32
+        GETC
+        HALT
+BIG     .STRINGZ "big.bin"
+COPY    .STRINGZ "out.bin"
+BUF     .FILL x4000
+KBSR    .FILL xFE00
+        .END
+"#;
+
+#[test]
+fn transfers_stay_positive_and_do_not_read_devices() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("big.bin"), vec![b'a'; 0x9000]).unwrap();
+    let mut vm = LC3::with_console(Console::scripted(b"k"));
+    vm.config.sandbox = Some(dir.path().to_path_buf());
+    vm.load(&assemble(TRANSFERS).unwrap().image);
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers.get(Register::R4), 0x7FFF);
+    assert_eq!(vm.memory.get_ptr(0x4000 + 0x7FFE)[0], b'a' as u16);
+    assert_eq!(vm.memory.get_ptr(0x4000 + 0x7FFF)[0], 0);
+    assert_eq!(fs::read(dir.path().join("out.bin")).unwrap(), [0]);
+    assert_eq!(vm.registers.get(Register::R0), b'k' as u16, "the key is still there for GETC");
+}
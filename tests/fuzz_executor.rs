@@ -0,0 +1,48 @@
+//! Offline smoke run of the executor fuzz harness in `fuzz/src/lib.rs`:
+//! replays the checked-in corpus and a batch of generated inputs without
+//! needing cargo-fuzz or a nightly toolchain.
+
+mod common;
+
+#[path = "../fuzz/src/lib.rs"]
+mod harness;
+
+use std::fs;
+use std::path::Path;
+
+use common::Rng;
+
+#[test]
+fn corpus_inputs_run_cleanly() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz");
+    for dir in ["corpus/executor", "artifacts/executor"] {
+        let Ok(entries) = fs::read_dir(root.join(dir)) else {
+            continue;
+        };
+        for entry in entries {
+            let path = entry.unwrap().path();
+            if path.is_file() {
+                harness::execute(&fs::read(&path).unwrap());
+            }
+        }
+    }
+}
+
+#[test]
+fn generated_inputs_run_cleanly() {
+    let mut rng = Rng::new(0x1C3);
+    for _ in 0..500 {
+        let length = 2 + (rng.next_u64() % 512) as usize;
+        let data: Vec<u8> = (0..length).map(|_| rng.next_u64() as u8).collect();
+        harness::execute(&data);
+    }
+}
+
+#[test]
+fn unknown_trap_and_unterminated_strings_do_not_panic() {
+    // No keyboard input; image at xFFFE: TRAP x22 (PUTS) with R0 = 0 walks
+    // all of memory, then an unknown trap vector
+    harness::execute(&[0, 0xFF, 0xFE, 0xF0, 0x22, 0xF0, 0xFF]);
+    // PUTS over a string running off the end of memory
+    harness::execute(&[0, 0x30, 0x00, 0xE0, 0x01, 0xF0, 0x22, 0xFF, 0xFF, 0xFF, 0xFF]);
+}
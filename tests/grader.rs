@@ -0,0 +1,116 @@
+use std::process::Command;
+
+use lc3_vm::grader::{grade, Spec};
+
+/// ADD R0, R1, R2 / STI R0, RESULT / GETC / OUT / HALT / RESULT .FILL x5000
+const PROGRAM: [u16; 6] = [0x1042, 0xB003, 0xF020, 0xF021, 0xF025, 0x5000];
+
+const SPEC: &str = r##"{
+  "cases": [
+    {
+      "name": "sums and echoes",
+      "registers": { "R1": 5, "R2": "x0007" },
+      "input": "k",
+      "expect": {
+        "halted": true,
+        "output": "kHALT\n",
+        "registers": { "R0": "#107" },
+        "memory": { "x5000": 12 }
+      }
+    },
+    {
+      "name": "wrong expectations",
+      "registers": { "R1": -1, "R2": -1 },
+      "memory": { "x5000": [1, 2] },
+      "input": "z",
+      "expect": {
+        "output_contains": "nope",
+        "memory": { "x5000": ["#-2", 3] }
+      }
+    },
+    {
+      "name": "runs out of input",
+      "expect": { "halted": true }
+    },
+    {
+      "name": "stopped by the budget",
+      "max_instructions": 2,
+      "expect": { "halted": false, "memory": { "x5000": 0 } }
+    }
+  ]
+}"##;
+
+fn image() -> Vec<u8> {
+    let mut bytes = 0x3000u16.to_be_bytes().to_vec();
+    for word in PROGRAM {
+        bytes.extend_from_slice(&word.to_be_bytes());
+    }
+    bytes
+}
+
+#[test]
+fn grades_each_case_on_a_fresh_machine() {
+    let spec = Spec::from_json(SPEC).unwrap();
+    let report = grade(&spec, &image());
+
+    assert_eq!((report.passed, report.total), (2, 4));
+    let [sums, wrong, no_input, budget] = &report.cases[..] else {
+        panic!("expected four results");
+    };
+
+    assert!(sums.passed, "{:?}", sums.failures);
+    assert_eq!(sums.stop, "halted");
+    assert_eq!(sums.instructions, 5);
+    assert!(sums.cycles > sums.instructions);
+
+    assert!(!wrong.passed);
+    assert_eq!(
+        wrong.failures,
+        vec![
+            "Output \"zHALT\\n\" does not contain \"nope\"".to_string(),
+            "x5001 was x0002, expected x0003".to_string(),
+        ]
+    );
+
+    assert!(!no_input.passed);
+    assert_eq!(no_input.stop, "error: Input script exhausted");
+
+    assert!(budget.passed, "{:?}", budget.failures);
+    assert_eq!(budget.stop, "instruction limit");
+
+    let text = report.to_string();
+    assert!(text.contains("PASS sums and echoes (halted, 5 instructions)"), "{}", text);
+    assert!(text.ends_with("2/4 cases passed"), "{}", text);
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["cases"][1]["passed"], false);
+    assert_eq!(json["cases"][0]["output"], "kHALT\n");
+}
+
+#[test]
+fn rejects_malformed_specs() {
+    assert!(Spec::from_json(r#"{ "cases": [ { "name": "x", "expect": { "outptu": "" } } ] }"#).is_err());
+    assert!(Spec::from_json("not json").is_err());
+}
+
+#[test]
+fn grade_subcommand_writes_reports_and_exit_status() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("program.obj"), image()).unwrap();
+    let spec = SPEC.replacen("{\n  \"cases\"", "{\n  \"image\": \"program.obj\",\n  \"cases\"", 1);
+    std::fs::write(dir.path().join("spec.json"), spec).unwrap();
+    let report = dir.path().join("report.json");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lc3-vm"))
+        .arg("grade")
+        .arg(dir.path().join("spec.json"))
+        .arg("--json")
+        .arg(&report)
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("FAIL wrong expectations"));
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(report).unwrap()).unwrap();
+    assert_eq!(json["passed"], 2);
+}
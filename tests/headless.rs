@@ -0,0 +1,160 @@
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use lc3_vm::io::console::Console;
+use lc3_vm::io::pipe::{PipeInput, EOF_KEY};
+use lc3_vm::vm::{Register, LC3};
+
+/// Echoes keys until end of input, then halts:
+/// GETC / ADD R1, R0, #-4 / BRz #2 / OUT / BRnzp #-5 / HALT
+const ECHO_UNTIL_EOF: [u16; 6] = [0xF020, 0x123C, 0x0402, 0xF021, 0x0FFB, 0xF025];
+
+fn write_image(dir: &std::path::Path) -> std::path::PathBuf {
+    write_program(dir, &ECHO_UNTIL_EOF)
+}
+
+fn write_program(dir: &std::path::Path, program: &[u16]) -> std::path::PathBuf {
+    let path = dir.join("program.obj");
+    let mut bytes = 0x3000u16.to_be_bytes().to_vec();
+    for word in program {
+        bytes.extend_from_slice(&word.to_be_bytes());
+    }
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[test]
+fn binary_runs_with_piped_stdin() {
+    let dir = tempfile::tempdir().unwrap();
+    let image = write_image(dir.path());
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lc3-vm"))
+        .arg(&image)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"abc").unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.stdout, b"abcHALT\n");
+}
+
+#[test]
+fn end_of_input_is_reported_as_eot() {
+    let mut pipe = PipeInput::from_reader(&b"x"[..]);
+
+    assert_eq!(pipe.read_key(), b'x');
+    assert_eq!(pipe.read_key(), EOF_KEY);
+
+    // After the end of input a key is always ready and keeps reading as EOT
+    assert!(pipe.check_key());
+    assert_eq!(pipe.read_key(), EOF_KEY);
+}
+
+/// Kills the child when dropped, so a failing test does not leave it running
+struct Running(Child);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts the binary with piped stdio, forwarding its stdout as it arrives
+fn spawn_streaming(dir: &std::path::Path, program: &[u16]) -> (Running, Receiver<u8>) {
+    let image = write_program(dir, program);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lc3-vm"))
+        .arg(&image)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let mut stdout = child.stdout.take().unwrap();
+    thread::spawn(move || {
+        let mut buffer = [0; 256];
+        while let Ok(count @ 1..) = stdout.read(&mut buffer) {
+            if buffer[..count].iter().any(|&byte| sender.send(byte).is_err()) {
+                break;
+            }
+        }
+    });
+    (Running(child), receiver)
+}
+
+/// Collects `count` bytes of output, failing if they take too long to arrive
+fn receive(receiver: &Receiver<u8>, count: usize) -> Vec<u8> {
+    (0..count)
+        .map(|_| receiver.recv_timeout(Duration::from_secs(10)).expect("output was not flushed"))
+        .collect()
+}
+
+#[test]
+fn output_is_flushed_before_waiting_for_input() {
+    let dir = tempfile::tempdir().unwrap();
+    // IN / HALT: the prompt must be visible while the program waits for the key
+    let (mut child, output) = spawn_streaming(dir.path(), &[0xF023, 0xF025]);
+
+    let prompt = b"Enter a character: ";
+    assert_eq!(receive(&output, prompt.len()), prompt);
+
+    child.0.stdin.take().unwrap().write_all(b"q").unwrap();
+    assert_eq!(receive(&output, 6), b"qHALT\n");
+    assert!(child.0.wait().unwrap().success());
+}
+
+#[test]
+fn output_is_flushed_while_a_program_computes() {
+    let dir = tempfile::tempdir().unwrap();
+    // LD R0, #2 / OUT / BRnzp #-1 / .FILL 'x': prints one character and spins without reading input
+    let (_child, output) = spawn_streaming(dir.path(), &[0x2002, 0xF021, 0x0FFF, 0x0078]);
+
+    assert_eq!(receive(&output, 1), b"x");
+}
+
+/// Delivers its bytes one at a time, each after a delay
+struct SlowReader {
+    bytes: &'static [u8],
+    delay: Duration,
+}
+
+impl Read for SlowReader {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match self.bytes.split_first() {
+            Some((&byte, rest)) if !buffer.is_empty() => {
+                thread::sleep(self.delay);
+                buffer[0] = byte;
+                self.bytes = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn polling_an_empty_pipe_waits_instead_of_spinning() {
+    let reader = SlowReader { bytes: b"k", delay: Duration::from_millis(300) };
+    let mut vm = LC3::with_console(Console::piped(PipeInput::from_reader(reader)));
+    // LDI R0, KBSR / BRzp #-2 / LDI R0, KBDR / HALT / KBSR / KBDR
+    for (offset, word) in [0xA003, 0x07FE, 0xA002, 0xF025, 0xFE00, 0xFE02].into_iter().enumerate() {
+        vm.memory.write(0x3000 + offset as u16, word);
+    }
+
+    // Stop short of the HALT, which would print to the test's stdout
+    while vm.registers.get(Register::PC) != 0x3003 {
+        vm.step().unwrap();
+    }
+
+    assert_eq!(vm.registers.get(Register::R0), b'k' as u16);
+    // Spinning would run millions of instructions in 300 ms; idle polls wait up to 10 ms each
+    assert!(vm.instruction_count < 1000, "{} instructions", vm.instruction_count);
+}
@@ -0,0 +1,183 @@
+use std::time::{Duration, Instant};
+
+use lc3_vm::asm::assemble;
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{CondFlag, Engine, Register, StopReason, LC3, PSR_USER};
+
+/// Counts timer interrupts in R3 while the main program counts in R4,
+/// acknowledging each one by clearing TMSR. R1 sums R4 and the interrupted PC
+/// at every interrupt, so it changes if one is taken an instruction early or
+/// late. The timer starts after the sixth instruction.
+const TICKS: &str = "
+        .ORIG x3000
+        LEA R0, ISR
+        STI R0, VECTOR
+        LD R0, INTERVAL
+        STI R0, TMIR
+        LD R0, CONTROL
+        STI R0, TMCR
+LOOP    ADD R4, R4, #1
+        ADD R2, R4, #0
+        BRnzp LOOP
+ISR     ADD R3, R3, #1
+        ADD R1, R1, R4
+        LDR R0, R6, #0
+        ADD R1, R1, R0
+        AND R5, R5, #0
+        STI R5, TMSR
+        RTI
+VECTOR  .FILL x0181
+TMCR    .FILL xFE08
+TMIR    .FILL xFE0A
+TMSR    .FILL xFE0C
+INTERVAL .FILL #100
+CONTROL .FILL xC000
+        .END
+";
+
+/// Waits for a 30 ms timer by polling TMSR, without interrupts
+const WAIT: &str = "
+        .ORIG x3000
+        LD R0, INTERVAL
+        STI R0, TMIR
+        LD R0, CONTROL
+        STI R0, TMCR
+POLL    LDI R1, TMSR
+        BRzp POLL
+        HALT
+TMCR    .FILL xFE08
+TMIR    .FILL xFE0A
+TMSR    .FILL xFE0C
+INTERVAL .FILL #30
+CONTROL .FILL x8001
+        .END
+";
+
+/// Takes one timer interrupt whose service routine stops the timer and
+/// returns to the PSR at BADPSR instead of the one saved on the stack
+const CORRUPT_PSR: &str = "
+        .ORIG x3000
+        LEA R0, ISR
+        STI R0, VECTOR
+        LD R0, INTERVAL
+        STI R0, TMIR
+        LD R0, CONTROL
+        STI R0, TMCR
+LOOP    BRnzp LOOP
+ISR     AND R0, R0, #0
+        STI R0, TMCR
+        LD R0, BADPSR
+        STR R0, R6, #1
+        RTI
+VECTOR  .FILL x0181
+TMCR    .FILL xFE08
+TMIR    .FILL xFE0A
+INTERVAL .FILL #10
+CONTROL .FILL xC000
+BADPSR  .FILL x8000
+        .END
+";
+
+fn vm(source: &str, engine: Engine) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.engine = engine;
+    vm.load(&assemble(source).unwrap().image);
+    vm
+}
+
+#[test]
+fn timer_interrupts_after_every_interval_on_every_engine() {
+    let mut runs = Vec::new();
+    for engine in [Engine::Interpreter, Engine::Cached, Engine::Translated, Engine::Microcoded] {
+        let mut vm = vm(TICKS, engine);
+        vm.config.max_instructions = Some(10_000);
+        assert_eq!(vm.run().unwrap(), StopReason::InstructionLimit);
+
+        // Interrupts at instructions 106, 206, ... 9906
+        assert_eq!(vm.registers.get(Register::R3), 99, "{:?}", engine);
+        assert_eq!(vm.stats().interrupts, 99, "{:?}", engine);
+        assert!(vm.registers.user_mode());
+        runs.push((vm.registers.get(Register::R1), vm.registers.get(Register::PC), vm.stats()));
+    }
+    assert!(runs.windows(2).all(|pair| pair[0] == pair[1]), "{:?}", runs);
+}
+
+#[test]
+fn service_routines_run_in_supervisor_mode_on_their_own_stack() {
+    let mut vm = vm(TICKS, Engine::Cached);
+    let isr = assemble(TICKS).unwrap().symbols["ISR"];
+    let user_stack = 0x5000;
+    vm.registers.set(Register::R6, user_stack);
+    vm.running = true;
+
+    while vm.registers.get(Register::PC) != isr {
+        vm.step().unwrap();
+    }
+    assert_eq!(vm.instruction_count, 106);
+    assert!(!vm.registers.user_mode());
+    assert_eq!(vm.registers.priority(), 4);
+    assert_eq!(vm.registers.get(Register::R6), 0x2FFE);
+    assert_eq!(vm.registers.saved_usp, user_stack);
+    let pushed = vm.memory.get_ptr(0x2FFE);
+    let return_pc = pushed[0];
+    assert_eq!(pushed[1] & PSR_USER, PSR_USER);
+
+    for _ in 0..7 {
+        vm.step().unwrap();
+    }
+    assert!(vm.registers.user_mode());
+    assert_eq!(vm.registers.priority(), 0);
+    assert_eq!(vm.registers.get(Register::PC), return_pc);
+    assert_eq!(vm.registers.get(Register::R6), user_stack);
+    assert_eq!(vm.registers.saved_ssp, 0x3000);
+}
+
+#[test]
+fn rti_loads_a_psr_without_exactly_one_condition_code_as_z() {
+    let bad_psr = assemble(CORRUPT_PSR).unwrap().symbols["BADPSR"];
+    for engine in [Engine::Interpreter, Engine::Cached, Engine::Translated, Engine::Microcoded] {
+        for psr in [PSR_USER, PSR_USER | 0x7, PSR_USER | 0x5] {
+            let mut vm = vm(CORRUPT_PSR, engine);
+            vm.memory.write(bad_psr, psr);
+            vm.config.max_instructions = Some(50);
+            assert_eq!(vm.run().unwrap(), StopReason::InstructionLimit);
+
+            assert_eq!(vm.stats().interrupts, 1, "{:?}", engine);
+            assert!(vm.registers.user_mode());
+            assert_eq!(vm.registers.get_condition_flag(), CondFlag::ZRO as u16, "{:?} x{:04X}", engine, psr);
+        }
+    }
+}
+
+#[test]
+fn millisecond_timer_measures_wall_clock_time() {
+    for engine in [Engine::Cached, Engine::Translated] {
+        let mut vm = vm(WAIT, engine);
+        vm.config.timeout = Some(Duration::from_secs(10));
+        let start = Instant::now();
+        assert_eq!(vm.run().unwrap(), StopReason::Halted);
+        assert!(start.elapsed() >= Duration::from_millis(30), "{:?}", engine);
+        assert_eq!(vm.stats().interrupts, 0);
+    }
+}
+
+#[test]
+fn snapshots_keep_the_timer_counting() {
+    let mut original = vm(TICKS, Engine::Cached);
+    original.config.max_instructions = Some(1050);
+    original.run().unwrap();
+
+    let mut bytes = Vec::new();
+    original.save_snapshot(&mut bytes).unwrap();
+    let mut restored = LC3::with_console(Console::scripted(b""));
+    restored.load_snapshot(bytes.as_slice()).unwrap();
+
+    for vm in [&mut original, &mut restored] {
+        vm.config.max_instructions = Some(1000);
+        vm.run().unwrap();
+    }
+    assert_eq!(restored.registers.get(Register::R3), original.registers.get(Register::R3));
+    assert_eq!(restored.registers.get(Register::R3), 20);
+    assert_eq!(restored.registers.psr(), original.registers.psr());
+    assert_eq!(restored.memory.get_ptr(0), original.memory.get_ptr(0));
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{Fault, OpCode, StopReason, LC3};
+
+fn vm_with(program: &[u16]) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(&[]));
+    for (offset, word) in program.iter().enumerate() {
+        vm.memory.write(0x3000 + offset as u16, *word);
+    }
+    vm
+}
+
+#[test]
+fn instruction_budget_stops_an_infinite_loop() {
+    let mut vm = vm_with(&[0x0FFF]); // BRnzp #-1
+    vm.config.max_instructions = Some(1234);
+
+    assert_eq!(vm.run().unwrap(), StopReason::InstructionLimit);
+    assert_eq!(vm.instruction_count, 1234);
+}
+
+#[test]
+fn timeout_stops_an_infinite_loop() {
+    let mut vm = vm_with(&[0x0FFF]);
+    vm.config.timeout = Some(Duration::from_millis(50));
+
+    assert_eq!(vm.run().unwrap(), StopReason::Timeout);
+}
+
+#[test]
+fn halting_within_limits_reports_halted() {
+    let mut vm = vm_with(&[0xF025]);
+    vm.config.max_instructions = Some(1);
+    vm.config.timeout = Some(Duration::from_secs(60));
+
+    assert_eq!(vm.run().unwrap(), StopReason::Halted);
+}
+
+#[test]
+fn faults_are_distinguishable_from_io_errors() {
+    let err = vm_with(&[0x8000]).run().unwrap_err();
+    assert_eq!(Fault::from_io_error(&err), Some(&Fault::IllegalOpcode(OpCode::RTI)));
+
+    let err = vm_with(&[0xF0FF]).run().unwrap_err();
+    assert_eq!(Fault::from_io_error(&err), Some(&Fault::UnknownTrap(0xFF)));
+
+    // GETC with no scripted input left is an I/O error, not a fault
+    let err = vm_with(&[0xF020]).run().unwrap_err();
+    assert_eq!(Fault::from_io_error(&err), None);
+}
+
+fn exit_code(dir: &Path, program: &[u16], args: &[&str]) -> i32 {
+    let image = dir.join("program.obj");
+    let mut bytes = 0x3000u16.to_be_bytes().to_vec();
+    for word in program {
+        bytes.extend_from_slice(&word.to_be_bytes());
+    }
+    std::fs::write(&image, bytes).unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_lc3-vm"))
+        .args(args)
+        .arg(&image)
+        .output()
+        .unwrap()
+        .status
+        .code()
+        .unwrap()
+}
+
+#[test]
+fn binary_exit_codes_distinguish_outcomes() {
+    let dir = tempfile::tempdir().unwrap();
+
+    assert_eq!(exit_code(dir.path(), &[0xF025], &[]), 0);
+    assert_eq!(exit_code(dir.path(), &[0xF020], &["--max-instructions"]), 2);
+    assert_eq!(exit_code(dir.path(), &[0xD000], &[]), 3);
+    assert_eq!(exit_code(dir.path(), &[0x0FFF], &["--max-instructions", "100"]), 4);
+    assert_eq!(exit_code(dir.path(), &[0x0FFF], &["--timeout", "0.1"]), 5);
+    assert_eq!(exit_code(dir.path(), &[0xF025], &["--replay", "/nonexistent/input.log"]), 1);
+}
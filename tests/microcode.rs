@@ -0,0 +1,121 @@
+use lc3_vm::asm::assemble;
+use lc3_vm::debugger::Debugger;
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{Engine, OpCode, Register, StopReason, FETCH_STATE, LC3};
+
+const SOURCE: &str = "
+        .ORIG x3000
+        LDI R1, PTR
+        BRz SKIP
+        STI R1, PTR
+SKIP    JSR SUB
+        HALT
+SUB     RET
+PTR     .FILL x3010
+        .END
+";
+
+fn vm() -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.engine = Engine::Microcoded;
+    vm.load(&assemble(SOURCE).unwrap().image);
+    vm.memory.write(0x3010, 7);
+    vm.running = true;
+    vm
+}
+
+/// The states an instruction passes through, up to the fetch of the next one
+fn states(vm: &mut LC3) -> Vec<u8> {
+    let mut states = vec![vm.microstep().unwrap()];
+    while vm.datapath().state != FETCH_STATE {
+        states.push(vm.microstep().unwrap());
+    }
+    states
+}
+
+#[test]
+fn instructions_follow_the_microsequencer() {
+    let mut vm = vm();
+
+    assert_eq!(states(&mut vm), [18, 33, 35, 32, 10, 24, 26, 25, 27]);
+    let datapath = *vm.datapath();
+    assert_eq!((datapath.mar, datapath.mdr, datapath.ir), (0x3010, 7, 0xA205));
+    assert_eq!(vm.registers.get(Register::R1), 7);
+    assert_eq!(vm.instruction_count, 1);
+
+    // BRz is not taken, so BEN is clear and state 22 is skipped
+    assert_eq!(states(&mut vm), [18, 33, 35, 32, 0]);
+    assert!(!vm.datapath().ben);
+    assert_eq!(states(&mut vm), [18, 33, 35, 32, 11, 29, 31, 23, 16]);
+    assert_eq!(states(&mut vm), [18, 33, 35, 32, 4, 21]);
+    assert_eq!(states(&mut vm), [18, 33, 35, 32, 12]);
+    assert_eq!(states(&mut vm), [18, 33, 35, 32, 15, 28, 30]);
+    assert!(!vm.running);
+    assert_eq!(vm.console().output(), b"HALT\n");
+}
+
+#[test]
+fn step_finishes_an_instruction_in_progress() {
+    let mut microcoded = vm();
+    for _ in 0..5 {
+        microcoded.microstep().unwrap();
+    }
+    assert_eq!(microcoded.instruction_count, 0);
+    // Even with another engine selected, the instruction in progress finishes first
+    microcoded.config.engine = Engine::Cached;
+    microcoded.step().unwrap();
+    assert_eq!(microcoded.instruction_count, 1);
+    assert_eq!(microcoded.datapath().state, FETCH_STATE);
+
+    let mut interpreted = vm();
+    interpreted.config.engine = Engine::Interpreter;
+    interpreted.step().unwrap();
+    assert_eq!(microcoded.registers.get(Register::R1), interpreted.registers.get(Register::R1));
+    assert_eq!(microcoded.registers.get(Register::PC), interpreted.registers.get(Register::PC));
+
+    let mut vm = vm();
+    assert_eq!(vm.run().unwrap(), StopReason::Halted);
+    assert_eq!(vm.stats().count(OpCode::LDI), 1);
+    assert_eq!(vm.memory.get_ptr(0x3010)[0], 7);
+}
+
+#[test]
+fn snapshots_are_taken_between_instructions() {
+    let mut original = vm();
+    for _ in 0..5 {
+        original.microstep().unwrap();
+    }
+    let mut bytes = Vec::new();
+    let err = original.save_snapshot(&mut bytes).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    original.step().unwrap();
+    original.save_snapshot(&mut bytes).unwrap();
+
+    // Loading abandons the instruction in progress
+    let mut restored = vm();
+    for _ in 0..5 {
+        restored.microstep().unwrap();
+    }
+    restored.load_snapshot(bytes.as_slice()).unwrap();
+    assert_eq!(restored.datapath().state, FETCH_STATE);
+    assert_eq!(restored.run().unwrap(), StopReason::Halted);
+    assert_eq!(original.run().unwrap(), StopReason::Halted);
+    assert_eq!(restored.instruction_count, original.instruction_count);
+    assert_eq!(restored.registers.get(Register::R1), original.registers.get(Register::R1));
+}
+
+#[test]
+fn debugger_shows_the_datapath_after_each_microstep() {
+    let mut vm = vm();
+    let mut debugger = Debugger::new(&mut vm);
+    let mut output = Vec::new();
+    for _ in 0..4 {
+        debugger.execute("ustep", &mut output).unwrap();
+    }
+
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "State 18 -> 33  MAR=x3000  MDR=x0000  IR=x0000  BEN=0");
+    assert_eq!(lines[2], "State 35 -> 32  MAR=x3000  MDR=xA205  IR=xA205  BEN=0");
+    assert_eq!(lines[3], "State 32 -> 10  MAR=x3000  MDR=xA205  IR=xA205  BEN=0");
+}
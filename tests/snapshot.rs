@@ -0,0 +1,149 @@
+mod common;
+
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{Checkpoint, CondFlag, Register, LC3, SNAPSHOT_MAGIC, SNAPSHOT_VERSION};
+
+/// Echoes keys until it reads 'q', then halts:
+/// GETC / LD R1, #5 / ADD R1, R1, R0 / BRz #2 / OUT / BRnzp #-6 / HALT / .FILL #-113
+const ECHO: [u16; 8] = [0xF020, 0x2205, 0x1240, 0x0402, 0xF021, 0x0FFA, 0xF025, 0xFF8F];
+
+fn echo_vm(input: &[u8]) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(input));
+    for (offset, word) in ECHO.iter().enumerate() {
+        vm.memory.write(0x3000 + offset as u16, *word);
+    }
+    vm.running = true;
+    vm
+}
+
+fn run_until_halt(vm: &mut LC3) {
+    while vm.running {
+        vm.step().unwrap();
+    }
+}
+
+#[test]
+fn restored_machine_continues_identically() {
+    let mut original = echo_vm(b"abcq");
+    for _ in 0..12 {
+        original.step().unwrap();
+    }
+
+    let mut bytes = Vec::new();
+    original.save_snapshot(&mut bytes).unwrap();
+    assert!(bytes.starts_with(SNAPSHOT_MAGIC));
+
+    let mut restored = LC3::with_console(Console::scripted(&[]));
+    restored.load_snapshot(bytes.as_slice()).unwrap();
+    assert_eq!(restored.instruction_count, 12);
+    assert_eq!(restored.console().pending_input(), original.console().pending_input());
+
+    run_until_halt(&mut original);
+    run_until_halt(&mut restored);
+
+    assert_eq!(restored.registers.get(Register::PC), original.registers.get(Register::PC));
+    assert_eq!(restored.registers.get(Register::R0), b'q' as u16);
+    assert_eq!(restored.instruction_count, original.instruction_count);
+    assert_eq!(restored.memory.get_ptr(0), original.memory.get_ptr(0));
+    assert_eq!(original.console().output(), b"abcHALT\n");
+    assert_eq!(restored.console().output(), b"cHALT\n");
+}
+
+#[test]
+fn rejects_foreign_and_future_files() {
+    let mut vm = LC3::with_console(Console::scripted(&[]));
+    let err = vm.load_snapshot(&b"\x30\x00\x12\x34\x56\x78\x9A\xBC\xDE"[..]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let mut bytes = Vec::new();
+    vm.save_snapshot(&mut bytes).unwrap();
+    bytes[9] = 99;
+    let err = vm.load_snapshot(bytes.as_slice()).unwrap_err();
+    assert!(err.to_string().contains("version"), "{}", err);
+
+    bytes[9] = SNAPSHOT_VERSION as u8;
+    bytes.truncate(bytes.len() - 1);
+    vm.registers.set(Register::R3, 7);
+    assert!(vm.load_snapshot(bytes.as_slice()).is_err());
+    assert_eq!(vm.registers.get(Register::R3), 7, "a failed load must not modify the machine");
+}
+
+#[test]
+fn corrupt_device_state_leaves_the_machine_unchanged() {
+    let mut original = echo_vm(b"abcq");
+    for _ in 0..12 {
+        original.step().unwrap();
+    }
+    let mut bytes = Vec::new();
+    original.save_snapshot(&mut bytes).unwrap();
+
+    // The timer's section comes last; give it 3 bytes of state instead of 14
+    let end = bytes.len() - 4 - 14;
+    assert_eq!(&bytes[end - 5..end], b"timer");
+    bytes.truncate(end);
+    bytes.extend_from_slice(&3u32.to_be_bytes());
+    bytes.extend_from_slice(&[0; 3]);
+
+    let mut vm = echo_vm(b"");
+    vm.registers.set(Register::R3, 7);
+    let err = vm.load_snapshot(bytes.as_slice()).unwrap_err();
+    assert!(err.to_string().contains("timer"), "{}", err);
+    assert_eq!(vm.registers.get(Register::R3), 7);
+    assert_eq!(vm.registers.get(Register::PC), 0x3000);
+    assert_eq!(vm.instruction_count, 0);
+    assert_eq!(vm.memory.get_ptr(0x3000)[..ECHO.len()], ECHO);
+}
+
+#[test]
+fn a_psr_without_exactly_one_condition_code_loads_as_z() {
+    let mut bytes = Vec::new();
+    echo_vm(b"").save_snapshot(&mut bytes).unwrap();
+    // The PSR follows the magic, version, register count and ten registers
+    bytes[32..34].copy_from_slice(&0x8006u16.to_be_bytes());
+
+    let mut vm = LC3::with_console(Console::scripted(&[]));
+    vm.load_snapshot(bytes.as_slice()).unwrap();
+    assert!(vm.registers.user_mode());
+    assert_eq!(vm.registers.get_condition_flag(), CondFlag::ZRO as u16);
+}
+
+#[test]
+fn loads_version_1_snapshots() {
+    let mut original = echo_vm(b"abcq");
+    for _ in 0..12 {
+        original.step().unwrap();
+    }
+    let mut bytes = Vec::new();
+    original.save_snapshot(&mut bytes).unwrap();
+
+    // Version 1 has no PSR, stack pointers or device section
+    bytes[9] = 1;
+    let pending = u32::from_be_bytes(bytes[47..51].try_into().unwrap()) as usize;
+    bytes.truncate(51 + pending + 2 * 65536);
+    bytes.drain(32..38);
+
+    let mut restored = LC3::with_console(Console::scripted(&[]));
+    restored.load_snapshot(bytes.as_slice()).unwrap();
+    assert_eq!(restored.instruction_count, 12);
+    assert!(restored.registers.user_mode());
+    assert_eq!(restored.registers.get(Register::PC), original.registers.get(Register::PC));
+
+    run_until_halt(&mut restored);
+    assert_eq!(restored.console().output(), b"cHALT\n");
+}
+
+#[test]
+fn run_writes_periodic_checkpoints() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("echo.lc3snap");
+
+    let mut vm = echo_vm(b"xyzq");
+    vm.config.checkpoint = Some(Checkpoint { path: path.clone(), interval: 10 });
+    vm.run().unwrap();
+
+    let mut resumed = LC3::with_console(Console::scripted(&[]));
+    resumed.load_snapshot_file(&path).unwrap();
+    assert!(resumed.running);
+    assert_eq!(resumed.instruction_count % 10, 0);
+    assert!(resumed.instruction_count > 0 && resumed.instruction_count < vm.instruction_count);
+}
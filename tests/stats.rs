@@ -0,0 +1,122 @@
+use lc3_vm::asm::assemble;
+use lc3_vm::io::console::Console;
+use lc3_vm::vm::{CycleModel, Engine, OpCode, LC3};
+
+/// Polls the keyboard until a key arrives, echoes it three times and halts
+const SOURCE: &str = "
+        .ORIG x3000
+POLL    LDI R1, KBSR
+        BRzp POLL
+        LDI R0, KBDR
+        AND R2, R2, #0
+        ADD R2, R2, #3
+ECHO    OUT
+        ADD R2, R2, #-1
+        BRp ECHO
+        HALT
+KBSR    .FILL xFE00
+KBDR    .FILL xFE02
+        .END
+";
+
+fn run(engine: Engine) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(b"x"));
+    vm.config.engine = engine;
+    vm.config.cycles = Some(CycleModel::default());
+    vm.load(&assemble(SOURCE).unwrap().image);
+    vm.run().unwrap();
+    vm
+}
+
+#[test]
+fn counts_instructions_traps_and_device_accesses() {
+    let vm = run(Engine::Cached);
+    let stats = vm.stats();
+
+    assert_eq!(vm.console().output(), b"xxxHALT\n");
+    assert_eq!(stats.retired(), 15);
+    assert_eq!(stats.retired(), vm.instruction_count);
+    assert_eq!(stats.count(OpCode::LDI), 2);
+    assert_eq!(stats.count(OpCode::ADD), 4);
+    assert_eq!(stats.count(OpCode::TRAP), 4);
+    assert_eq!(stats.traps.get(&0x21), Some(&3));
+    assert_eq!(stats.traps.get(&0x25), Some(&1));
+    // The scripted key is ready on the first poll; the LDI of KBDR reads it afterwards
+    assert_eq!((stats.mmio_reads, stats.mmio_writes), (2, 0));
+
+    let report = stats.to_string();
+    assert!(report.starts_with("Instructions retired: 15\n"), "{}", report);
+    assert!(report.contains("x21 OUT"), "{}", report);
+    assert!(report.contains("\nCycles: "), "{}", report);
+}
+
+#[test]
+fn every_engine_reports_the_same_counters() {
+    let expected = run(Engine::Interpreter).stats();
+    for engine in [Engine::Cached, Engine::Translated, Engine::Microcoded] {
+        assert_eq!(run(engine).stats(), expected, "{:?}", engine);
+    }
+}
+
+#[test]
+fn counters_can_be_reset() {
+    let mut vm = run(Engine::Translated);
+    vm.reset_stats();
+    assert_eq!(vm.stats(), Default::default());
+    assert_eq!(vm.instruction_count, 15);
+}
+
+/// Counts R1 down from 2, then loads through a pointer and halts
+const COUNTDOWN: &str = "
+        .ORIG x3000
+        AND R1, R1, #0
+        ADD R1, R1, #2
+LOOP    ADD R1, R1, #-1
+        BRp LOOP
+        LDI R2, PTR
+        HALT
+PTR     .FILL x3000
+        .END
+";
+
+fn cycles(model: CycleModel) -> u64 {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.cycles = Some(model);
+    vm.load(&assemble(COUNTDOWN).unwrap().image);
+    vm.run().unwrap();
+    vm.stats().cycles
+}
+
+#[test]
+fn cycles_follow_the_model() {
+    // AND 5, ADD 5, two ADDs 10, two BRs 10 and one taken 1, LDI 9, TRAP 7
+    assert_eq!(cycles(CycleModel::default()), 47);
+    // Eleven memory accesses, each two cycles slower
+    assert_eq!(cycles(CycleModel { memory: 3, ..CycleModel::default() }), 69);
+
+    let fast = run(Engine::Cached).stats();
+    let slow_devices = (fast.mmio_reads + fast.mmio_writes) * 9;
+    let mut vm = LC3::with_console(Console::scripted(b"x"));
+    vm.config.cycles = Some(CycleModel { mmio: 10, ..CycleModel::default() });
+    vm.load(&assemble(SOURCE).unwrap().image);
+    vm.run().unwrap();
+    assert_eq!(vm.stats().cycles, fast.cycles + slow_devices);
+}
+
+#[test]
+fn traces_show_the_word_that_ran_even_when_it_overwrites_itself() {
+    for engine in [Engine::Interpreter, Engine::Cached, Engine::Translated, Engine::Microcoded] {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut vm = LC3::with_console(Console::scripted(b""));
+        vm.config.engine = engine;
+        vm.trace_to(Box::new(file.reopen().unwrap()));
+        // ST R0, #-1 stores zero over itself
+        vm.memory.write(0x3000, 0x31FF);
+        vm.memory.write(0x3001, 0xF025);
+        vm.run().unwrap();
+        drop(vm);
+
+        let trace = std::fs::read_to_string(file.path()).unwrap();
+        assert!(trace.starts_with("x3000  x31FF  ST R0, #-1"), "{:?}: {}", engine, trace);
+    }
+}
@@ -0,0 +1,153 @@
+//! Runs the binary on a pseudo-terminal to check that raw mode is always undone.
+#![cfg(unix)]
+
+use std::fs::File;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// LDI R0, KBSR / BRzp #-2 / LDI R0, KBDR / HALT / KBSR / KBDR
+const WAIT_FOR_KEY: [u16; 6] = [0xA003, 0x07FE, 0xA002, 0xF025, 0xFE00, 0xFE02];
+
+struct Pty {
+    master: File,
+    slave: OwnedFd,
+}
+
+fn open_pty() -> Pty {
+    let (mut master, mut slave) = (0, 0);
+    let result = unsafe {
+        libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), std::ptr::null())
+    };
+    assert_eq!(result, 0, "openpty failed");
+    unsafe {
+        Pty {
+            master: File::from_raw_fd(master),
+            slave: OwnedFd::from_raw_fd(slave),
+        }
+    }
+}
+
+fn local_flags(pty: &Pty) -> libc::tcflag_t {
+    let mut term: libc::termios = unsafe { std::mem::zeroed() };
+    assert_eq!(unsafe { libc::tcgetattr(pty.slave.as_raw_fd(), &mut term) }, 0);
+    term.c_lflag
+}
+
+fn spawn(dir: &Path, pty: &Pty, program: &[u16], args: &[&str]) -> Child {
+    let image = dir.join("program.obj");
+    let mut bytes = 0x3000u16.to_be_bytes().to_vec();
+    for word in program {
+        bytes.extend_from_slice(&word.to_be_bytes());
+    }
+    std::fs::write(&image, bytes).unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_lc3-vm"))
+        .args(args)
+        .arg(&image)
+        .stdin(Stdio::from(pty.slave.try_clone().unwrap()))
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+/// Waits for the child to exit, returning its exit code and the CPU time it used
+fn wait_with_cpu_time(child: Child) -> (i32, Duration) {
+    let mut status = 0;
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let pid = child.id() as libc::pid_t;
+    assert_eq!(unsafe { libc::wait4(pid, &mut status, 0, &mut usage) }, pid);
+
+    let time = |tv: libc::timeval| Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000);
+    (libc::WEXITSTATUS(status), time(usage.ru_utime) + time(usage.ru_stime))
+}
+
+/// Waits until the program has put the terminal into raw mode
+fn wait_for_raw_mode(pty: &Pty) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while local_flags(pty) & libc::ICANON != 0 {
+        assert!(Instant::now() < deadline, "terminal never entered raw mode");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn terminal_is_restored_when_killed_by_a_signal() {
+    let dir = tempfile::tempdir().unwrap();
+    let pty = open_pty();
+    let mut child = spawn(dir.path(), &pty, &WAIT_FOR_KEY, &[]);
+
+    wait_for_raw_mode(&pty);
+    assert_eq!(local_flags(&pty) & libc::ECHO, 0);
+    assert_ne!(local_flags(&pty) & libc::ISIG, 0);
+
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) };
+    let status = child.wait().unwrap();
+
+    use std::os::unix::process::ExitStatusExt;
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+    let flags = local_flags(&pty);
+    assert_ne!(flags & libc::ICANON, 0);
+    assert_ne!(flags & libc::ECHO, 0);
+}
+
+#[test]
+fn ctrl_c_can_be_delivered_to_the_program() {
+    let dir = tempfile::tempdir().unwrap();
+    let pty = open_pty();
+    let mut child = spawn(dir.path(), &pty, &WAIT_FOR_KEY, &["--ctrl-c", "deliver", "--exit-code", "r0"]);
+
+    wait_for_raw_mode(&pty);
+    assert_eq!(local_flags(&pty) & libc::ISIG, 0);
+
+    std::io::Write::write_all(&mut &pty.master, &[0x03]).unwrap();
+    let status = child.wait().unwrap();
+
+    assert_eq!(status.code(), Some(3));
+    assert_ne!(local_flags(&pty) & libc::ISIG, 0);
+    assert_ne!(local_flags(&pty) & libc::ICANON, 0);
+}
+
+#[test]
+fn terminal_is_restored_after_a_fault() {
+    let dir = tempfile::tempdir().unwrap();
+    let pty = open_pty();
+    let before = local_flags(&pty);
+
+    // RTI outside an interrupt faults on the first instruction
+    let status = spawn(dir.path(), &pty, &[0x8000], &[]).wait().unwrap();
+
+    assert_eq!(status.code(), Some(3));
+    assert_eq!(local_flags(&pty), before);
+}
+
+#[test]
+fn getc_waits_for_a_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let pty = open_pty();
+    // GETC / HALT
+    let mut child = spawn(dir.path(), &pty, &[0xF020, 0xF025], &["--exit-code", "r0"]);
+
+    wait_for_raw_mode(&pty);
+    thread::sleep(Duration::from_millis(200));
+    std::io::Write::write_all(&mut &pty.master, b"g").unwrap();
+
+    assert_eq!(child.wait().unwrap().code(), Some(b'g' as i32));
+}
+
+#[test]
+fn polling_for_a_key_does_not_spin_the_host() {
+    let dir = tempfile::tempdir().unwrap();
+    let pty = open_pty();
+    let child = spawn(dir.path(), &pty, &WAIT_FOR_KEY, &["--exit-code", "r0"]);
+
+    wait_for_raw_mode(&pty);
+    thread::sleep(Duration::from_secs(1));
+    std::io::Write::write_all(&mut &pty.master, b"k").unwrap();
+
+    let (code, cpu_time) = wait_with_cpu_time(child);
+    assert_eq!(code, b'k' as i32);
+    assert!(cpu_time < Duration::from_millis(400), "used {:?} of CPU in a second", cpu_time);
+}
@@ -0,0 +1,138 @@
+use std::fs;
+use std::time::Duration;
+
+use lc3_vm::asm::assemble;
+use lc3_vm::io::console::Console;
+use lc3_vm::io::serial::SerialBackend;
+use lc3_vm::vm::{StopReason, UartConfig, LC3, UART_ADDRESS};
+
+/// Sends every byte it receives back, adding one to it, until the other end closes the link
+const ECHO: &str = "
+        .ORIG x3000
+POLL    LDI R1, USR
+        BRn GOT
+        LD R2, CLOSED
+        AND R2, R1, R2
+        BRnp DONE
+        BRnzp POLL
+GOT     LDI R0, UDR
+        ADD R0, R0, #1
+        STI R0, UDR
+        BRnzp POLL
+DONE    HALT
+USR     .FILL xFE20
+UDR     .FILL xFE22
+CLOSED  .FILL x2000
+        .END
+";
+
+fn echo_vm(backend: SerialBackend) -> LC3 {
+    let mut vm = LC3::with_console(Console::scripted(b""));
+    vm.config.uart = Some(UartConfig { address: UART_ADDRESS, backend });
+    vm.config.timeout = Some(Duration::from_secs(10));
+    vm.load(&assemble(ECHO).unwrap().image);
+    vm
+}
+
+#[test]
+fn files_feed_the_uart_without_touching_the_console() {
+    let dir = tempfile::tempdir().unwrap();
+    let (input, output) = (dir.path().join("in"), dir.path().join("out"));
+    fs::write(&input, "HAL").unwrap();
+
+    let mut vm = echo_vm(SerialBackend::Files { input, output: output.clone() });
+    assert_eq!(vm.run().unwrap(), StopReason::Halted);
+    assert_eq!(fs::read_to_string(output).unwrap(), "IBM");
+    assert_eq!(vm.console().output(), b"HALT\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn a_fifo_input_does_not_wait_for_a_writer_to_attach() {
+    let dir = tempfile::tempdir().unwrap();
+    let (input, output) = (dir.path().join("in"), dir.path().join("out"));
+    let path = std::ffi::CString::new(input.to_str().unwrap()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o600) }, 0);
+
+    let mut vm = echo_vm(SerialBackend::Files { input: input.clone(), output: output.clone() });
+    vm.attach_configured_devices().unwrap();
+    let guest = std::thread::spawn(move || vm.run().unwrap());
+    fs::write(&input, "HAL").unwrap();
+    assert_eq!(guest.join().unwrap(), StopReason::Halted);
+    assert_eq!(fs::read_to_string(output).unwrap(), "IBM");
+}
+
+#[cfg(unix)]
+#[test]
+fn the_uart_talks_to_programs_over_unix_sockets() {
+    use std::io::{Read, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("serial.sock");
+
+    // The guest connects to a host program
+    let listener = UnixListener::bind(&path).unwrap();
+    let host = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(b"abc").unwrap();
+        let mut reply = [0; 3];
+        stream.read_exact(&mut reply).unwrap();
+        reply
+    });
+    let mut vm = echo_vm(SerialBackend::Connect(path.clone()));
+    let guest = std::thread::spawn(move || vm.run().unwrap());
+    assert_eq!(&host.join().unwrap(), b"bcd");
+    assert_eq!(guest.join().unwrap(), StopReason::Halted);
+
+    // The guest listens and a host program connects
+    fs::remove_file(&path).unwrap();
+    let mut vm = echo_vm(SerialBackend::Listen(path.clone()));
+    vm.attach_configured_devices().unwrap();
+    let guest = std::thread::spawn(move || vm.run().unwrap());
+    let mut stream = UnixStream::connect(&path).unwrap();
+    stream.write_all(b"xy").unwrap();
+    stream.shutdown(std::net::Shutdown::Write).unwrap();
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).unwrap();
+    assert_eq!(reply, b"yz");
+    assert_eq!(guest.join().unwrap(), StopReason::Halted);
+}
+
+#[cfg(unix)]
+#[test]
+fn a_snapshot_that_fails_to_load_opens_no_link() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("serial.sock");
+    // State for a random number generator the loading machine does not have
+    let mut original = LC3::new();
+    original.config.random = true;
+    original.attach_configured_devices().unwrap();
+    let mut bytes = Vec::new();
+    original.save_snapshot(&mut bytes).unwrap();
+
+    let mut vm = echo_vm(SerialBackend::Listen(path.clone()));
+    assert!(vm.load_snapshot(bytes.as_slice()).is_err());
+    assert!(!path.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn a_pty_link_can_be_opened_like_a_serial_port() {
+    use std::io::{Read, Write};
+
+    let dir = tempfile::tempdir().unwrap();
+    let link = dir.path().join("ttyLC3");
+    let mut vm = echo_vm(SerialBackend::Pty(link.clone()));
+    vm.config.max_instructions = Some(2_000_000);
+    vm.attach_configured_devices().unwrap();
+    let guest = std::thread::spawn(move || vm.run().unwrap());
+
+    let mut port = fs::OpenOptions::new().read(true).write(true).open(&link).unwrap();
+    port.write_all(b"01").unwrap();
+    let mut reply = [0; 2];
+    port.read_exact(&mut reply).unwrap();
+    assert_eq!(&reply, b"12");
+    drop(port);
+    assert_ne!(guest.join().unwrap(), StopReason::Halted, "a pty stays open between programs");
+}